@@ -0,0 +1,474 @@
+use crate::{DrawReason, GameOutcome, Move, MoveKind, PieceType, Square, Team, WinReason};
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct CastlingRights {
+    pub kingside: bool,
+    pub queenside: bool,
+}
+
+/// State that `make_move` overwrites and `unmake_move` has to put back.
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    mv: Move,
+    castling_rights: [CastlingRights; 2],
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+    hash: u64,
+}
+
+/// A chess position plus the moves that led to it, which are needed to unmake
+/// moves and to detect repetitions.
+#[derive(Debug, Clone)]
+pub struct Board {
+    pub(crate) squares: [Option<(Team, PieceType)>; 64],
+    pub(crate) side_to_move: Team,
+    pub(crate) castling_rights: [CastlingRights; 2],
+    pub(crate) en_passant: Option<Square>,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    pub(crate) hash: u64,
+    history: Vec<HistoryEntry>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::starting_position()
+    }
+}
+
+impl Board {
+    pub(crate) fn empty() -> Board {
+        Board {
+            squares: [None; 64],
+            side_to_move: Team::White,
+            castling_rights: [CastlingRights::default(); 2],
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn starting_position() -> Board {
+        const BACK_RANK: [PieceType; 8] = [
+            PieceType::Rook,
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Queen,
+            PieceType::King,
+            PieceType::Bishop,
+            PieceType::Knight,
+            PieceType::Rook,
+        ];
+
+        let mut board = Board::empty();
+        for (file, piece_type) in BACK_RANK.into_iter().enumerate() {
+            let file = file as u8;
+            board.squares[Square::new(file, 0).index()] = Some((Team::White, piece_type));
+            board.squares[Square::new(file, 1).index()] = Some((Team::White, PieceType::Pawn));
+            board.squares[Square::new(file, 6).index()] = Some((Team::Black, PieceType::Pawn));
+            board.squares[Square::new(file, 7).index()] = Some((Team::Black, piece_type));
+        }
+        let all_rights = CastlingRights {
+            kingside: true,
+            queenside: true,
+        };
+        board.castling_rights = [all_rights; 2];
+        board.hash = board.compute_hash();
+        board
+    }
+
+    pub fn piece_at(&self, square: Square) -> Option<(Team, PieceType)> {
+        self.squares[square.index()]
+    }
+
+    pub fn side_to_move(&self) -> Team {
+        self.side_to_move
+    }
+
+    pub fn castling_rights(&self, team: Team) -> CastlingRights {
+        self.castling_rights[team.index()]
+    }
+
+    /// The square a pawn skipped over with a double push on the last move.
+    pub fn en_passant_square(&self) -> Option<Square> {
+        self.en_passant
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// A Zobrist key identifying the position for repetition purposes.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn king_square(&self, team: Team) -> Option<Square> {
+        Square::all().find(|square| self.piece_at(*square) == Some((team, PieceType::King)))
+    }
+
+    /// The moves played so far, oldest first.
+    pub fn moves(&self) -> impl DoubleEndedIterator<Item = Move> + '_ {
+        self.history.iter().map(|entry| entry.mv)
+    }
+
+    pub fn last_move(&self) -> Option<Move> {
+        self.history.last().map(|entry| entry.mv)
+    }
+
+    /// Plays `mv`, which must be one of `legal_moves()`.
+    pub fn make_move(&mut self, mv: Move) {
+        let team = self.side_to_move;
+        self.history.push(HistoryEntry {
+            mv,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+        });
+
+        apply_move(&mut self.squares, team, mv);
+
+        let rights = &mut self.castling_rights[team.index()];
+        if mv.piece == PieceType::King {
+            rights.kingside = false;
+            rights.queenside = false;
+        }
+        // a rook leaving its corner, or being captured there, loses that side's right
+        for square in [mv.from, mv.to] {
+            for side in [Team::White, Team::Black] {
+                if square.rank() != side.back_rank() {
+                    continue;
+                }
+                let rights = &mut self.castling_rights[side.index()];
+                match square.file() {
+                    0 => rights.queenside = false,
+                    7 => rights.kingside = false,
+                    _ => {}
+                }
+            }
+        }
+
+        self.en_passant = if mv.kind == MoveKind::DoublePawnPush {
+            mv.from.offset(0, team.pawn_direction())
+        } else {
+            None
+        };
+        self.halfmove_clock = if mv.piece == PieceType::Pawn || mv.is_capture() {
+            0
+        } else {
+            self.halfmove_clock + 1
+        };
+        if team == Team::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = team.opponent();
+        self.hash = self.compute_hash();
+    }
+
+    /// Takes back the last move, returning it, or `None` at the start of the game.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let entry = self.history.pop()?;
+        let mv = entry.mv;
+        let team = self.side_to_move.opponent();
+
+        self.squares[mv.to.index()] = None;
+        self.squares[mv.from.index()] = Some((team, mv.piece));
+        if let (Some(captured), Some(square)) = (mv.captured, mv.captured_square()) {
+            self.squares[square.index()] = Some((team.opponent(), captured));
+        }
+        if let Some((rook_from, rook_to)) = mv.castling_rook() {
+            self.squares[rook_to.index()] = None;
+            self.squares[rook_from.index()] = Some((team, PieceType::Rook));
+        }
+
+        if team == Team::Black {
+            self.fullmove_number -= 1;
+        }
+        self.side_to_move = team;
+        self.castling_rights = entry.castling_rights;
+        self.en_passant = entry.en_passant;
+        self.halfmove_clock = entry.halfmove_clock;
+        self.hash = entry.hash;
+        Some(mv)
+    }
+
+    /// How many times the current position has occurred, counting this one.
+    pub fn repetition_count(&self) -> usize {
+        // an irreversible move means no earlier position can come back
+        let reversible = self.halfmove_clock as usize;
+        1 + self
+            .history
+            .iter()
+            .rev()
+            .take(reversible)
+            .filter(|entry| entry.hash == self.hash)
+            .count()
+    }
+
+    /// Dead positions where neither side can mate: bare kings, a lone minor
+    /// piece, or bishops that all stand on squares of one colour.
+    pub fn has_insufficient_material(&self) -> bool {
+        let mut minor_pieces: Vec<(Square, PieceType)> = Vec::new();
+        for square in Square::all() {
+            match self.piece_at(square) {
+                None | Some((_, PieceType::King)) => {}
+                Some((_, piece_type @ (PieceType::Bishop | PieceType::Knight))) => {
+                    minor_pieces.push((square, piece_type))
+                }
+                Some(_) => return false,
+            }
+        }
+
+        if minor_pieces.len() <= 1 {
+            return true;
+        }
+
+        let first_is_light = minor_pieces[0].0.is_light();
+        minor_pieces.iter().all(|(square, piece_type)| {
+            *piece_type == PieceType::Bishop && square.is_light() == first_is_light
+        })
+    }
+
+    /// Whether `team` has anything that could ever mate: more than a king and
+    /// a lone minor piece, or a lone minor piece when the other side has
+    /// pieces of its own to get in the king's way. Bishops that all stand on
+    /// one colour never mate.
+    pub fn has_mating_material(&self, team: Team) -> bool {
+        let mut minor_pieces: Vec<(Square, PieceType)> = Vec::new();
+        let mut opponent_has_pieces = false;
+        for square in Square::all() {
+            match self.piece_at(square) {
+                None | Some((_, PieceType::King)) => {}
+                Some((owner, _)) if owner != team => opponent_has_pieces = true,
+                Some((_, piece_type @ (PieceType::Bishop | PieceType::Knight))) => {
+                    minor_pieces.push((square, piece_type))
+                }
+                Some(_) => return true,
+            }
+        }
+
+        match minor_pieces.as_slice() {
+            [] => false,
+            [_] => opponent_has_pieces,
+            [(first, _), ..] => minor_pieces.iter().any(|(square, piece_type)| {
+                *piece_type == PieceType::Knight || square.is_light() != first.is_light()
+            }),
+        }
+    }
+
+    /// How the game ends when `flagged` runs out of time: a loss, unless the
+    /// opponent could never have mated.
+    pub fn timeout_outcome(&self, flagged: Team) -> GameOutcome {
+        if self.has_mating_material(flagged.opponent()) {
+            GameOutcome::Decisive {
+                winner: flagged.opponent(),
+                reason: WinReason::Timeout,
+            }
+        } else {
+            GameOutcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+        }
+    }
+
+    /// A draw the side to move may claim: the fifty-move rule or threefold repetition.
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.halfmove_clock >= 100 {
+            return Some(DrawReason::FiftyMoveRule);
+        }
+        if self.repetition_count() >= 3 {
+            return Some(DrawReason::ThreefoldRepetition);
+        }
+        None
+    }
+
+    /// How the game has ended, if it has. With `automatic_draw_claims` the
+    /// claimable draws end the game on their own; otherwise only the
+    /// seventy-five-move rule and fivefold repetition do.
+    pub fn outcome(&self, automatic_draw_claims: bool) -> Option<GameOutcome> {
+        if self.legal_moves().is_empty() {
+            return Some(if self.is_in_check() {
+                GameOutcome::Decisive {
+                    winner: self.side_to_move.opponent(),
+                    reason: WinReason::Checkmate,
+                }
+            } else {
+                GameOutcome::Draw(DrawReason::Stalemate)
+            });
+        }
+        if self.has_insufficient_material() {
+            return Some(GameOutcome::Draw(DrawReason::InsufficientMaterial));
+        }
+        if self.halfmove_clock >= 150 {
+            return Some(GameOutcome::Draw(DrawReason::SeventyFiveMoveRule));
+        }
+        if self.repetition_count() >= 5 {
+            return Some(GameOutcome::Draw(DrawReason::FivefoldRepetition));
+        }
+        if automatic_draw_claims {
+            return self.claimable_draw().map(GameOutcome::Draw);
+        }
+        None
+    }
+
+    pub(crate) fn compute_hash(&self) -> u64 {
+        let mut hash = 0;
+        for square in Square::all() {
+            if let Some((team, piece_type)) = self.piece_at(square) {
+                hash ^= ZOBRIST.pieces[team.index()][piece_type.index()][square.index()];
+            }
+        }
+        if self.side_to_move == Team::Black {
+            hash ^= ZOBRIST.side;
+        }
+        for team in [Team::White, Team::Black] {
+            let rights = self.castling_rights(team);
+            if rights.kingside {
+                hash ^= ZOBRIST.castling[team.index() * 2];
+            }
+            if rights.queenside {
+                hash ^= ZOBRIST.castling[team.index() * 2 + 1];
+            }
+        }
+        // the en passant square only matters when a pawn can actually take there
+        if let Some(square) = self.en_passant {
+            let team = self.side_to_move;
+            let capturable = [-1, 1].into_iter().any(|file_delta| {
+                square
+                    .offset(file_delta, -team.pawn_direction())
+                    .is_some_and(|from| self.piece_at(from) == Some((team, PieceType::Pawn)))
+            });
+            if capturable {
+                hash ^= ZOBRIST.en_passant[square.file() as usize];
+            }
+        }
+        hash
+    }
+}
+
+/// Moves the pieces for `mv` on a bare square array, without touching any
+/// other position state. Move generation uses this to test legality.
+pub(crate) fn apply_move(squares: &mut [Option<(Team, PieceType)>; 64], team: Team, mv: Move) {
+    if let Some(square) = mv.captured_square() {
+        squares[square.index()] = None;
+    }
+    squares[mv.from.index()] = None;
+    squares[mv.to.index()] = Some((team, mv.promotion.unwrap_or(mv.piece)));
+    if let Some((rook_from, rook_to)) = mv.castling_rook() {
+        squares[rook_from.index()] = None;
+        squares[rook_to.index()] = Some((team, PieceType::Rook));
+    }
+}
+
+struct ZobristKeys {
+    pieces: [[[u64; 64]; 6]; 2],
+    side: u64,
+    castling: [u64; 4],
+    en_passant: [u64; 8],
+}
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_zobrist_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[[0; 64]; 6]; 2],
+        side: 0,
+        castling: [0; 4],
+        en_passant: [0; 8],
+    };
+    let mut state = 0x2545_F491_4F6C_DD1D;
+    let mut team = 0;
+    while team < 2 {
+        let mut piece_type = 0;
+        while piece_type < 6 {
+            let mut square = 0;
+            while square < 64 {
+                let (next, key) = splitmix64(state);
+                state = next;
+                keys.pieces[team][piece_type][square] = key;
+                square += 1;
+            }
+            piece_type += 1;
+        }
+        team += 1;
+    }
+    let (next, key) = splitmix64(state);
+    state = next;
+    keys.side = key;
+    let mut i = 0;
+    while i < 4 {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys.castling[i] = key;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 8 {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys.en_passant[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+static ZOBRIST: ZobristKeys = generate_zobrist_keys();
+
+#[cfg(test)]
+mod tests {
+    use crate::Board;
+
+    /// Castling both ways, en passant, promotions with and without capture,
+    /// and checks between them.
+    const POSITIONS: [&str; 4] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ];
+
+    #[test]
+    fn unmake_restores_fen_and_hash() {
+        for fen in POSITIONS {
+            let mut board = Board::from_fen(fen).unwrap();
+            let hash = board.hash();
+            for mv in board.legal_moves() {
+                board.make_move(mv);
+                assert_eq!(board.unmake_move(), Some(mv));
+                assert_eq!(board.to_fen(), fen, "after {} from {}", mv, fen);
+                assert_eq!(board.hash(), hash, "after {} from {}", mv, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_after_a_move_matches_the_position_read_fresh() {
+        for fen in POSITIONS {
+            let mut board = Board::from_fen(fen).unwrap();
+            for mv in board.legal_moves() {
+                board.make_move(mv);
+                let fresh = Board::from_fen(&board.to_fen()).unwrap();
+                assert_eq!(board.hash(), fresh.hash(), "after {} from {}", mv, fen);
+                board.unmake_move();
+            }
+        }
+    }
+
+    #[test]
+    fn unmake_at_the_start_does_nothing() {
+        let mut board = Board::starting_position();
+        assert_eq!(board.unmake_move(), None);
+        assert_eq!(board.to_fen(), Board::starting_position().to_fen());
+    }
+}
@@ -0,0 +1,363 @@
+use crate::Team;
+use std::fmt;
+use std::time::Duration;
+
+/// What a player gets back for each move, on top of the time of the stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Bonus {
+    /// Sudden death: nothing.
+    #[default]
+    None,
+    /// The increment is added after every move.
+    Fischer(Duration),
+    /// After every move, the time it took is given back, up to the delay.
+    Bronstein(Duration),
+    /// The clock only starts running once the delay has passed on each move.
+    UsDelay(Duration),
+}
+
+/// One period of a time control: `time` for `moves` moves, or for the rest of
+/// the game when `moves` is `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stage {
+    pub moves: Option<u32>,
+    pub time: Duration,
+}
+
+/// A full time control, such as 40 moves in 90 minutes followed by 30 minutes
+/// for the rest, with a 30 second increment from the first move. When the last
+/// stage has a move count it repeats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    pub stages: Vec<Stage>,
+    pub bonus: Bonus,
+}
+
+impl TimeControl {
+    /// `time` for the whole game.
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl {
+            stages: vec![Stage { moves: None, time }],
+            bonus: Bonus::None,
+        }
+    }
+
+    pub fn with_bonus(mut self, bonus: Bonus) -> TimeControl {
+        self.bonus = bonus;
+        self
+    }
+
+    /// The stage with `index`, or the last one once they have all been
+    /// played. `None` when there are no stages at all.
+    pub fn stage(&self, index: usize) -> Option<Stage> {
+        self.stages.get(index).or(self.stages.last()).copied()
+    }
+
+    /// The time each side starts with: that of the first stage.
+    pub fn starting_time(&self) -> Duration {
+        self.stage(0).map_or(Duration::ZERO, |stage| stage.time)
+    }
+}
+
+/// Written the way PGN's `TimeControl` tag has it, in seconds: "40/5400+30:1800+30".
+/// Delays, which the tag has no form for, are written as "d" and the delay.
+impl fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, stage) in self.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, ":")?;
+            }
+            if let Some(moves) = stage.moves {
+                write!(f, "{}/", moves)?;
+            }
+            write!(f, "{}", stage.time.as_secs())?;
+            match self.bonus {
+                Bonus::None => {}
+                Bonus::Fischer(increment) => write!(f, "+{}", increment.as_secs())?,
+                Bonus::Bronstein(delay) | Bonus::UsDelay(delay) => {
+                    write!(f, "d{}", delay.as_secs())?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A pair of chess clocks. The owner says whose clock is running and for how
+/// long with `tick`, and presses it with `press` when a side has moved, so the
+/// clock itself never reads the time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    /// The stage each side is in and how many moves it has made in it.
+    stages: [(usize, u32); 2],
+    /// Whose move is being timed and how long it has taken so far.
+    turn: Option<(Team, Duration)>,
+    flagged: Option<Team>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Clock {
+        Clock {
+            remaining: [control.starting_time(); 2],
+            control,
+            stages: [(0, 0); 2],
+            turn: None,
+            flagged: None,
+        }
+    }
+
+    pub fn time_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    pub fn remaining(&self, team: Team) -> Duration {
+        self.remaining[team.index()]
+    }
+
+    /// What is left of the US delay on the move being timed, before `team`'s
+    /// own time starts running.
+    pub fn delay_left(&self, team: Team) -> Duration {
+        match (self.control.bonus, self.turn) {
+            (Bonus::UsDelay(delay), Some((turn, spent))) if turn == team => {
+                delay.saturating_sub(spent)
+            }
+            (Bonus::UsDelay(delay), _) => delay,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// The side that ran out of time, once one has. The clock stops then.
+    pub fn flagged(&self) -> Option<Team> {
+        self.flagged
+    }
+
+    /// Runs `team`'s clock for `elapsed`. A different side from the last tick
+    /// starts a new move without a press, which is how takebacks hand the
+    /// clock back. Returns the side that ran out of time, if this did it.
+    pub fn tick(&mut self, team: Team, elapsed: Duration) -> Option<Team> {
+        if self.flagged.is_some() {
+            return None;
+        }
+        let spent = match self.turn {
+            Some((turn, spent)) if turn == team => spent,
+            _ => Duration::ZERO,
+        };
+        self.turn = Some((team, spent + elapsed));
+
+        let used = match self.control.bonus {
+            Bonus::UsDelay(delay) => {
+                (spent + elapsed).saturating_sub(delay) - spent.saturating_sub(delay)
+            }
+            _ => elapsed,
+        };
+        let remaining = &mut self.remaining[team.index()];
+        *remaining = remaining.saturating_sub(used);
+        if remaining.is_zero() {
+            self.flagged = Some(team);
+            self.turn = None;
+        }
+        self.flagged
+    }
+
+    /// Sets the time `team` has left, to keep the clock in step with one kept
+    /// elsewhere.
+    pub fn set_remaining(&mut self, team: Team, remaining: Duration) {
+        if self.flagged.is_none() {
+            self.remaining[team.index()] = remaining;
+        }
+    }
+
+    /// Ends `team`'s move: adds its bonus and, when it completes a stage, the
+    /// time of the next one.
+    pub fn press(&mut self, team: Team) {
+        if self.flagged.is_some() {
+            return;
+        }
+        let spent = match self.turn.take() {
+            Some((turn, spent)) if turn == team => spent,
+            _ => Duration::ZERO,
+        };
+        let remaining = &mut self.remaining[team.index()];
+        match self.control.bonus {
+            Bonus::Fischer(increment) => *remaining += increment,
+            Bonus::Bronstein(delay) => *remaining += spent.min(delay),
+            Bonus::None | Bonus::UsDelay(_) => {}
+        }
+
+        let (stage, moves) = &mut self.stages[team.index()];
+        *moves += 1;
+        let Some(current) = self.control.stage(*stage) else {
+            return;
+        };
+        if current.moves == Some(*moves) {
+            *stage += 1;
+            *moves = 0;
+            *remaining += self
+                .control
+                .stage(*stage)
+                .map_or(Duration::ZERO, |next| next.time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Board, DrawReason, GameOutcome, WinReason};
+
+    fn seconds(seconds: u64) -> Duration {
+        Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn sudden_death_flags_the_side_that_runs_out() {
+        let mut clock = Clock::new(TimeControl::sudden_death(seconds(60)));
+        assert_eq!(clock.tick(Team::White, seconds(10)), None);
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(50));
+
+        assert_eq!(clock.tick(Team::Black, seconds(59)), None);
+        assert_eq!(clock.tick(Team::Black, seconds(2)), Some(Team::Black));
+        assert_eq!(clock.remaining(Team::Black), Duration::ZERO);
+        assert_eq!(clock.flagged(), Some(Team::Black));
+
+        // a flagged clock has stopped
+        assert_eq!(clock.tick(Team::White, seconds(60)), None);
+        clock.press(Team::Black);
+        assert_eq!(clock.remaining(Team::White), seconds(50));
+        assert_eq!(clock.remaining(Team::Black), Duration::ZERO);
+    }
+
+    #[test]
+    fn fischer_adds_the_increment_after_each_move() {
+        let control = TimeControl::sudden_death(seconds(60)).with_bonus(Bonus::Fischer(seconds(2)));
+        let mut clock = Clock::new(control);
+        clock.tick(Team::White, seconds(5));
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(57));
+        clock.press(Team::Black);
+        assert_eq!(clock.remaining(Team::Black), seconds(62));
+    }
+
+    #[test]
+    fn bronstein_gives_back_the_time_used_up_to_the_delay() {
+        let control =
+            TimeControl::sudden_death(seconds(60)).with_bonus(Bonus::Bronstein(seconds(5)));
+        let mut clock = Clock::new(control);
+        clock.tick(Team::White, seconds(3));
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(60));
+
+        clock.tick(Team::Black, seconds(4));
+        clock.tick(Team::Black, seconds(4));
+        clock.press(Team::Black);
+        assert_eq!(clock.remaining(Team::Black), seconds(57));
+    }
+
+    #[test]
+    fn us_delay_holds_the_clock_until_the_delay_has_passed() {
+        let control = TimeControl::sudden_death(seconds(60)).with_bonus(Bonus::UsDelay(seconds(5)));
+        let mut clock = Clock::new(control);
+        assert_eq!(clock.delay_left(Team::White), seconds(5));
+        clock.tick(Team::White, seconds(3));
+        assert_eq!(clock.remaining(Team::White), seconds(60));
+        assert_eq!(clock.delay_left(Team::White), seconds(2));
+        clock.tick(Team::White, seconds(4));
+        assert_eq!(clock.remaining(Team::White), seconds(58));
+        assert_eq!(clock.delay_left(Team::White), Duration::ZERO);
+
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(58));
+        assert_eq!(clock.delay_left(Team::White), seconds(5));
+    }
+
+    #[test]
+    fn stages_add_their_time_once_their_moves_are_made() {
+        let control = TimeControl {
+            stages: vec![
+                Stage {
+                    moves: Some(2),
+                    time: seconds(60),
+                },
+                Stage {
+                    moves: None,
+                    time: seconds(30),
+                },
+            ],
+            bonus: Bonus::Fischer(seconds(1)),
+        };
+        assert_eq!(control.to_string(), "2/60+1:30+1");
+        let mut clock = Clock::new(control);
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(61));
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(92));
+        clock.press(Team::White);
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(94));
+        assert_eq!(clock.remaining(Team::Black), seconds(60));
+    }
+
+    #[test]
+    fn a_last_stage_with_moves_repeats() {
+        let mut clock = Clock::new(TimeControl {
+            stages: vec![Stage {
+                moves: Some(2),
+                time: seconds(60),
+            }],
+            bonus: Bonus::None,
+        });
+        for _ in 0..4 {
+            clock.press(Team::Black);
+        }
+        assert_eq!(clock.remaining(Team::Black), seconds(180));
+    }
+
+    #[test]
+    fn a_control_without_stages_has_no_time() {
+        let control = TimeControl {
+            stages: Vec::new(),
+            bonus: Bonus::Fischer(seconds(1)),
+        };
+        assert_eq!(control.stage(0), None);
+        assert_eq!(control.starting_time(), Duration::ZERO);
+        let mut clock = Clock::new(control);
+        clock.press(Team::White);
+        assert_eq!(clock.remaining(Team::White), seconds(1));
+        assert_eq!(clock.tick(Team::Black, seconds(1)), Some(Team::Black));
+    }
+
+    #[test]
+    fn running_out_against_a_side_that_cannot_mate_is_a_draw() {
+        let rook = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            rook.timeout_outcome(Team::Black),
+            GameOutcome::Decisive {
+                winner: Team::White,
+                reason: WinReason::Timeout,
+            }
+        );
+        assert_eq!(
+            rook.timeout_outcome(Team::White),
+            GameOutcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+        );
+
+        let knight = Board::from_fen("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(
+            knight.timeout_outcome(Team::Black),
+            GameOutcome::Draw(DrawReason::TimeoutVsInsufficientMaterial)
+        );
+
+        // a lone knight can mate a king boxed in by its own pawn
+        let knight_against_pawn = Board::from_fen("4k3/4p3/8/8/8/8/8/1N2K3 w - - 0 1").unwrap();
+        assert_eq!(
+            knight_against_pawn.timeout_outcome(Team::Black),
+            GameOutcome::Decisive {
+                winner: Team::White,
+                reason: WinReason::Timeout,
+            }
+        );
+    }
+}
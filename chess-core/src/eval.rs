@@ -0,0 +1,229 @@
+use crate::{Board, PieceType, Square, Team};
+
+// Piece-square tables from White's side, written as the board is seen from
+// White: the first row is the eighth rank.
+#[rustfmt::skip]
+const PAWN_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const KNIGHT_TABLE: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+
+#[rustfmt::skip]
+const BISHOP_TABLE: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const ROOK_TABLE: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+
+#[rustfmt::skip]
+const QUEEN_TABLE: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+
+#[rustfmt::skip]
+const KING_MIDDLEGAME_TABLE: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+
+#[rustfmt::skip]
+const KING_ENDGAME_TABLE: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+/// Once the pieces other than kings and pawns are worth no more than this in
+/// total, kings are scored by the endgame table and head for the centre.
+const ENDGAME_MATERIAL: i32 = 1300;
+
+/// The material value of a piece in centipawns. Kings are never traded, so
+/// they count for nothing.
+pub fn piece_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 0,
+    }
+}
+
+/// A static score of the position in centipawns from the side to move's point
+/// of view: material plus piece-square bonuses.
+pub fn evaluate(board: &Board) -> i32 {
+    let mut piece_material = 0;
+    for square in Square::all() {
+        if let Some((_, piece_type)) = board.piece_at(square) {
+            if piece_type != PieceType::Pawn {
+                piece_material += piece_value(piece_type);
+            }
+        }
+    }
+    let is_endgame = piece_material <= ENDGAME_MATERIAL;
+
+    let mut score = 0;
+    for square in Square::all() {
+        let Some((team, piece_type)) = board.piece_at(square) else {
+            continue;
+        };
+        let table = match piece_type {
+            PieceType::Pawn => &PAWN_TABLE,
+            PieceType::Knight => &KNIGHT_TABLE,
+            PieceType::Bishop => &BISHOP_TABLE,
+            PieceType::Rook => &ROOK_TABLE,
+            PieceType::Queen => &QUEEN_TABLE,
+            PieceType::King if is_endgame => &KING_ENDGAME_TABLE,
+            PieceType::King => &KING_MIDDLEGAME_TABLE,
+        };
+        // Black reads the tables upside down
+        let row = match team {
+            Team::White => 7 - square.rank(),
+            Team::Black => square.rank(),
+        };
+        let value = piece_value(piece_type) + table[row as usize * 8 + square.file() as usize];
+        if team == board.side_to_move() {
+            score += value;
+        } else {
+            score -= value;
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `fen` with the board turned upside down and the colours swapped, which
+    /// is the same position for the other side.
+    fn mirror(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |text: &str| -> String {
+            text.chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect()
+        };
+        let placement: Vec<String> = fields[0].split('/').rev().map(swap_case).collect();
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        let castling: String = match fields[2] {
+            "-" => "-".to_string(),
+            castling => "KQkq"
+                .chars()
+                .filter(|right| swap_case(castling).contains(*right))
+                .collect(),
+        };
+        let en_passant = match fields[3] {
+            "-" => "-".to_string(),
+            square => {
+                let (file, rank) = square.split_at(1);
+                format!("{}{}", file, 9 - rank.parse::<u32>().unwrap())
+            }
+        };
+        format!(
+            "{} {} {} {} {} {}",
+            placement.join("/"),
+            side,
+            castling,
+            en_passant,
+            fields[4],
+            fields[5]
+        )
+    }
+
+    fn evaluate_fen(fen: &str) -> i32 {
+        evaluate(&Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn the_same_for_either_colour() {
+        for fen in [
+            crate::STARTING_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let mirrored = mirror(fen);
+            assert_eq!(mirror(&mirrored), fen);
+            assert_eq!(evaluate_fen(fen), evaluate_fen(&mirrored), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn from_the_side_to_moves_point_of_view() {
+        assert_eq!(evaluate(&Board::starting_position()), 0);
+        // White is a queen up
+        let white = evaluate_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+        let black = evaluate_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1");
+        assert!(white > piece_value(PieceType::Rook));
+        assert_eq!(white, -black);
+    }
+
+    #[test]
+    fn kings_head_for_the_centre_in_the_endgame() {
+        let corner = evaluate_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/K7 w - - 0 1");
+        let centre = evaluate_fen("4k3/pppppppp/8/8/3K4/8/PPPPPPPP/8 w - - 0 1");
+        assert!(centre > corner);
+    }
+}
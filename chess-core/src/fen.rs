@@ -0,0 +1,417 @@
+use crate::{Board, CastlingRights, PieceType, Square, Team};
+use std::fmt;
+use std::str::FromStr;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Why a FEN string could not be read as a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    BadRankLength { rank: u8, squares: usize },
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastlingRights(String),
+    CastlingWithoutPieces(char),
+    InvalidEnPassantSquare(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    KingCount { team: Team, count: usize },
+    PawnOnBackRank(Square),
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => {
+                write!(f, "expected 4 or 6 space-separated fields, found {}", count)
+            }
+            FenError::WrongRankCount(count) => {
+                write!(f, "expected 8 ranks separated by '/', found {}", count)
+            }
+            FenError::BadRankLength { rank, squares } => {
+                write!(
+                    f,
+                    "rank {} describes {} squares instead of 8",
+                    rank, squares
+                )
+            }
+            FenError::InvalidPiece(letter) => write!(f, "'{}' is not a piece letter", letter),
+            FenError::InvalidSideToMove(field) => {
+                write!(f, "side to move must be 'w' or 'b', not '{}'", field)
+            }
+            FenError::InvalidCastlingRights(field) => write!(
+                f,
+                "castling rights must be '-' or a combination of 'KQkq', not '{}'",
+                field
+            ),
+            FenError::CastlingWithoutPieces(right) => write!(
+                f,
+                "castling right '{}' needs the king and rook on their starting squares",
+                right
+            ),
+            FenError::InvalidEnPassantSquare(field) => write!(
+                f,
+                "'{}' is not an en passant square left by the last move",
+                field
+            ),
+            FenError::InvalidHalfmoveClock(field) => {
+                write!(f, "'{}' is not a valid halfmove clock", field)
+            }
+            FenError::InvalidFullmoveNumber(field) => {
+                write!(f, "'{}' is not a valid fullmove number", field)
+            }
+            FenError::KingCount { team, count } => {
+                write!(f, "{:?} has {} kings instead of 1", team, count)
+            }
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on the back rank at {}", square),
+            FenError::OpponentInCheck => write!(f, "the side that just moved is in check"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Board {
+    /// Reads a position in Forsyth-Edwards Notation. The two move clocks may be
+    /// left off, in which case they start at 0 and 1.
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let mut board = Board::empty();
+        parse_placement(&mut board, fields[0])?;
+
+        board.side_to_move = match fields[1] {
+            "w" => Team::White,
+            "b" => Team::Black,
+            other => return Err(FenError::InvalidSideToMove(other.to_string())),
+        };
+
+        parse_castling_rights(&mut board, fields[2])?;
+
+        if fields[3] != "-" {
+            let square = Square::from_algebraic(fields[3])
+                .filter(|square| is_en_passant_square(&board, *square))
+                .ok_or_else(|| FenError::InvalidEnPassantSquare(fields[3].to_string()))?;
+            board.en_passant = Some(square);
+        }
+
+        if fields.len() == 6 {
+            board.halfmove_clock = fields[4]
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+            board.fullmove_number = fields[5]
+                .parse()
+                .ok()
+                .filter(|number| *number > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(fields[5].to_string()))?;
+        }
+
+        for team in [Team::White, Team::Black] {
+            let count = Square::all()
+                .filter(|square| board.piece_at(*square) == Some((team, PieceType::King)))
+                .count();
+            if count != 1 {
+                return Err(FenError::KingCount { team, count });
+            }
+        }
+        if let Some(square) = Square::all().find(|square| {
+            (square.rank() == 0 || square.rank() == 7)
+                && board
+                    .piece_at(*square)
+                    .is_some_and(|(_, piece_type)| piece_type == PieceType::Pawn)
+        }) {
+            return Err(FenError::PawnOnBackRank(square));
+        }
+        let opponent = board.side_to_move.opponent();
+        if board
+            .king_square(opponent)
+            .is_some_and(|square| board.is_square_attacked(square, board.side_to_move))
+        {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        board.hash = board.compute_hash();
+        Ok(board)
+    }
+
+    /// Writes the position in Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::new(file, rank)) {
+                    None => empty += 1,
+                    Some((team, piece_type)) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        let letter = piece_type.letter();
+                        fen.push(match team {
+                            Team::White => letter,
+                            Team::Black => letter.to_ascii_lowercase(),
+                        });
+                    }
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.side_to_move {
+            Team::White => " w ",
+            Team::Black => " b ",
+        });
+
+        let white = self.castling_rights(Team::White);
+        let black = self.castling_rights(Team::Black);
+        let mut castling = String::new();
+        for (has_right, letter) in [
+            (white.kingside, 'K'),
+            (white.queenside, 'Q'),
+            (black.kingside, 'k'),
+            (black.queenside, 'q'),
+        ] {
+            if has_right {
+                castling.push(letter);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        fen.push_str(&castling);
+
+        match self.en_passant {
+            Some(square) => fen.push_str(&format!(" {}", square)),
+            None => fen.push_str(" -"),
+        }
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock, self.fullmove_number
+        ));
+        fen
+    }
+}
+
+impl FromStr for Board {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Board, FenError> {
+        Board::from_fen(fen)
+    }
+}
+
+fn parse_placement(board: &mut Board, field: &str) -> Result<(), FenError> {
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    // FEN lists the eighth rank first
+    for (rank, text) in (0..8u8).rev().zip(ranks) {
+        let mut file: usize = 0;
+        for letter in text.chars() {
+            if let Some(empty) = letter.to_digit(10).filter(|digit| (1..=8).contains(digit)) {
+                file += empty as usize;
+                continue;
+            }
+            let piece_type =
+                PieceType::from_letter(letter).ok_or(FenError::InvalidPiece(letter))?;
+            let team = if letter.is_ascii_uppercase() {
+                Team::White
+            } else {
+                Team::Black
+            };
+            if file < 8 {
+                board.squares[Square::new(file as u8, rank).index()] = Some((team, piece_type));
+            }
+            file += 1;
+        }
+        if file != 8 {
+            return Err(FenError::BadRankLength {
+                rank: rank + 1,
+                squares: file,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn parse_castling_rights(board: &mut Board, field: &str) -> Result<(), FenError> {
+    if field == "-" {
+        return Ok(());
+    }
+    for letter in field.chars() {
+        let (team, rook_file) = match letter {
+            'K' => (Team::White, 7),
+            'Q' => (Team::White, 0),
+            'k' => (Team::Black, 7),
+            'q' => (Team::Black, 0),
+            _ => return Err(FenError::InvalidCastlingRights(field.to_string())),
+        };
+        let rank = team.back_rank();
+        if board.piece_at(Square::new(4, rank)) != Some((team, PieceType::King))
+            || board.piece_at(Square::new(rook_file, rank)) != Some((team, PieceType::Rook))
+        {
+            return Err(FenError::CastlingWithoutPieces(letter));
+        }
+        let rights: &mut CastlingRights = &mut board.castling_rights[team.index()];
+        if rook_file == 7 {
+            rights.kingside = true;
+        } else {
+            rights.queenside = true;
+        }
+    }
+    Ok(())
+}
+
+/// Whether `square` could have been skipped by a double pawn push on the last move.
+fn is_en_passant_square(board: &Board, square: Square) -> bool {
+    let mover = board.side_to_move.opponent();
+    let start_rank = if mover == Team::White { 1 } else { 6 };
+    let pawn_square = square.offset(0, mover.pawn_direction());
+    let start_square = square.offset(0, -mover.pawn_direction());
+    board.piece_at(square).is_none()
+        && pawn_square.is_some_and(|pawn| board.piece_at(pawn) == Some((mover, PieceType::Pawn)))
+        && start_square
+            .is_some_and(|start| start.rank() == start_rank && board.piece_at(start).is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(fen: &str) -> FenError {
+        Board::from_fen(fen).unwrap_err()
+    }
+
+    #[test]
+    fn round_trips() {
+        for fen in [
+            STARTING_FEN,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 17",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 b - - 99 120",
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+            assert_eq!(fen.parse::<Board>().unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn move_clocks_may_be_left_off() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn starting_fen_is_the_starting_position() {
+        assert_eq!(Board::starting_position().to_fen(), STARTING_FEN);
+    }
+
+    #[test]
+    fn field_and_placement_errors() {
+        assert_eq!(error(""), FenError::WrongFieldCount(0));
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0"),
+            FenError::WrongFieldCount(5)
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::WrongRankCount(7)
+        );
+        assert_eq!(
+            error("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            FenError::BadRankLength {
+                rank: 7,
+                squares: 9
+            }
+        );
+        assert_eq!(
+            error("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            FenError::BadRankLength {
+                rank: 7,
+                squares: 7
+            }
+        );
+        assert_eq!(
+            error("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            FenError::InvalidPiece('x')
+        );
+    }
+
+    #[test]
+    fn state_field_errors() {
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+            FenError::InvalidSideToMove("x".to_string())
+        );
+        assert_eq!(
+            error("r3k2r/8/8/8/8/8/8/R3K2R w KQx - 0 1"),
+            FenError::InvalidCastlingRights("KQx".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w K - 0 1"),
+            FenError::CastlingWithoutPieces('K')
+        );
+        assert_eq!(
+            error("r3k3/8/8/8/8/8/8/R3K2R w KQkq - 0 1"),
+            FenError::CastlingWithoutPieces('k')
+        );
+        assert_eq!(
+            error(&STARTING_FEN.replace(" - ", " e3 ")),
+            FenError::InvalidEnPassantSquare("e3".to_string())
+        );
+        assert_eq!(
+            error(&STARTING_FEN.replace(" - ", " e9 ")),
+            FenError::InvalidEnPassantSquare("e9".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - x 1"),
+            FenError::InvalidHalfmoveClock("x".to_string())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K3 w - - 0 0"),
+            FenError::InvalidFullmoveNumber("0".to_string())
+        );
+    }
+
+    #[test]
+    fn position_errors() {
+        assert_eq!(
+            error("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::KingCount {
+                team: Team::Black,
+                count: 0
+            }
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            FenError::KingCount {
+                team: Team::White,
+                count: 2
+            }
+        );
+        assert_eq!(
+            error("4k2P/8/8/8/8/8/8/4K3 w - - 0 1"),
+            FenError::PawnOnBackRank(Square::from_algebraic("h8").unwrap())
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"),
+            FenError::OpponentInCheck
+        );
+    }
+}
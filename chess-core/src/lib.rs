@@ -0,0 +1,32 @@
+//! Chess rules without a game engine attached: board representation, legal move
+//! generation, making and unmaking moves, detection of how a game ends, FEN
+//! import and export, SAN, and reading and writing PGN. It also has a small
+//! alpha-beta search for playing against the computer, and chess clocks for
+//! the common time controls.
+
+mod board;
+mod clock;
+mod eval;
+mod fen;
+mod movegen;
+mod moves;
+mod outcome;
+mod pgn;
+mod piece;
+mod san;
+mod search;
+mod square;
+
+pub use board::{Board, CastlingRights};
+pub use clock::{Bonus, Clock, Stage, TimeControl};
+pub use eval::{evaluate, piece_value};
+pub use fen::{FenError, STARTING_FEN};
+pub use moves::{Move, MoveKind};
+pub use outcome::{DrawReason, GameOutcome, WinReason};
+pub use pgn::{read_pgn, write_pgn, PgnError, PgnGame, PgnTags};
+pub use piece::{PieceType, Team};
+pub use san::SanError;
+pub use search::{
+    is_mate_score, search, search_until_stopped, SearchLimits, SearchResult, MATE_SCORE,
+};
+pub use square::Square;
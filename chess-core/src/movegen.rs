@@ -0,0 +1,398 @@
+use crate::board::apply_move;
+use crate::{Board, Move, MoveKind, PieceType, Square, Team};
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+type Squares = [Option<(Team, PieceType)>; 64];
+
+impl Board {
+    /// Every move the side to move may legally play.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let team = self.side_to_move();
+        let own_king = self.king_square(team);
+        let mut moves = self.pseudo_legal_moves();
+        moves.retain(|mv| {
+            let mut squares = self.squares;
+            apply_move(&mut squares, team, *mv);
+            let king_square = if mv.piece == PieceType::King {
+                Some(mv.to)
+            } else {
+                own_king
+            };
+            king_square.is_none_or(|square| !is_attacked(&squares, square, team.opponent()))
+        });
+        moves
+    }
+
+    /// The legal move from `from` to `to`, if there is one. Promotions need
+    /// `promotion` to pick between the four possible moves.
+    pub fn find_move(
+        &self,
+        from: Square,
+        to: Square,
+        promotion: Option<PieceType>,
+    ) -> Option<Move> {
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+    }
+
+    /// Reads a legal move in the long algebraic notation moves are displayed
+    /// in, such as "e2e4" or "e7e8q", which is what UCI and CECP engines use.
+    /// Castling is written as the king's move.
+    pub fn parse_move(&self, text: &str) -> Option<Move> {
+        let text = text.trim();
+        let from = Square::from_algebraic(text.get(0..2)?)?;
+        let to = Square::from_algebraic(text.get(2..4)?)?;
+        let promotion = match text.get(4..)? {
+            "" => None,
+            letter => {
+                let mut letters = letter.chars();
+                let piece_type = PieceType::from_letter(letters.next()?)?;
+                if letters.next().is_some() {
+                    return None;
+                }
+                Some(piece_type)
+            }
+        };
+        self.find_move(from, to, promotion)
+    }
+
+    /// Reads a legal move in either long algebraic notation or SAN, for the
+    /// engines that answer in SAN.
+    pub fn read_move(&self, text: &str) -> Option<Move> {
+        self.parse_move(text)
+            .or_else(|| self.parse_san(text.trim()).ok())
+    }
+
+    pub fn is_in_check(&self) -> bool {
+        let team = self.side_to_move();
+        self.king_square(team)
+            .is_some_and(|square| self.is_square_attacked(square, team.opponent()))
+    }
+
+    pub fn is_square_attacked(&self, square: Square, by: Team) -> bool {
+        is_attacked(&self.squares, square, by)
+    }
+
+    fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let team = self.side_to_move();
+        let mut moves = Vec::with_capacity(64);
+        for from in Square::all() {
+            let Some((piece_team, piece_type)) = self.piece_at(from) else {
+                continue;
+            };
+            if piece_team != team {
+                continue;
+            }
+            match piece_type {
+                PieceType::Pawn => self.pawn_moves(from, team, &mut moves),
+                PieceType::Knight => {
+                    self.step_moves(from, team, piece_type, &KNIGHT_OFFSETS, &mut moves)
+                }
+                PieceType::Bishop => {
+                    self.slide_moves(from, team, piece_type, &BISHOP_DIRECTIONS, &mut moves)
+                }
+                PieceType::Rook => {
+                    self.slide_moves(from, team, piece_type, &ROOK_DIRECTIONS, &mut moves)
+                }
+                PieceType::Queen => {
+                    self.slide_moves(from, team, piece_type, &ROOK_DIRECTIONS, &mut moves);
+                    self.slide_moves(from, team, piece_type, &BISHOP_DIRECTIONS, &mut moves);
+                }
+                PieceType::King => {
+                    self.step_moves(from, team, piece_type, &KING_OFFSETS, &mut moves);
+                    self.castling_moves(from, team, &mut moves);
+                }
+            }
+        }
+        moves
+    }
+
+    fn pawn_moves(&self, from: Square, team: Team, moves: &mut Vec<Move>) {
+        let direction = team.pawn_direction();
+        let start_rank = if team == Team::White { 1 } else { 6 };
+        let promotion_rank = team.opponent().back_rank();
+        let mut push = |to: Square, captured: Option<PieceType>, kind: MoveKind| {
+            let mv = Move {
+                from,
+                to,
+                piece: PieceType::Pawn,
+                captured,
+                promotion: None,
+                kind,
+            };
+            if to.rank() == promotion_rank {
+                for promotion in PieceType::PROMOTIONS {
+                    moves.push(Move {
+                        promotion: Some(promotion),
+                        ..mv
+                    });
+                }
+            } else {
+                moves.push(mv);
+            }
+        };
+
+        if let Some(to) = from.offset(0, direction) {
+            if self.piece_at(to).is_none() {
+                push(to, None, MoveKind::Normal);
+                if from.rank() == start_rank {
+                    if let Some(to) = to.offset(0, direction) {
+                        if self.piece_at(to).is_none() {
+                            push(to, None, MoveKind::DoublePawnPush);
+                        }
+                    }
+                }
+            }
+        }
+
+        for file_delta in [-1, 1] {
+            let Some(to) = from.offset(file_delta, direction) else {
+                continue;
+            };
+            match self.piece_at(to) {
+                Some((target_team, target_type)) if target_team != team => {
+                    push(to, Some(target_type), MoveKind::Normal);
+                }
+                None if self.en_passant_square() == Some(to) => {
+                    push(to, Some(PieceType::Pawn), MoveKind::EnPassant);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn step_moves(
+        &self,
+        from: Square,
+        team: Team,
+        piece_type: PieceType,
+        offsets: &[(i8, i8)],
+        moves: &mut Vec<Move>,
+    ) {
+        for (file_delta, rank_delta) in offsets {
+            let Some(to) = from.offset(*file_delta, *rank_delta) else {
+                continue;
+            };
+            match self.piece_at(to) {
+                Some((target_team, _)) if target_team == team => {}
+                target => moves.push(Move {
+                    from,
+                    to,
+                    piece: piece_type,
+                    captured: target.map(|(_, target_type)| target_type),
+                    promotion: None,
+                    kind: MoveKind::Normal,
+                }),
+            }
+        }
+    }
+
+    fn slide_moves(
+        &self,
+        from: Square,
+        team: Team,
+        piece_type: PieceType,
+        directions: &[(i8, i8)],
+        moves: &mut Vec<Move>,
+    ) {
+        for (file_delta, rank_delta) in directions {
+            let mut current = from;
+            while let Some(to) = current.offset(*file_delta, *rank_delta) {
+                let target = self.piece_at(to);
+                if target.is_some_and(|(target_team, _)| target_team == team) {
+                    break;
+                }
+                moves.push(Move {
+                    from,
+                    to,
+                    piece: piece_type,
+                    captured: target.map(|(_, target_type)| target_type),
+                    promotion: None,
+                    kind: MoveKind::Normal,
+                });
+                if target.is_some() {
+                    break;
+                }
+                current = to;
+            }
+        }
+    }
+
+    /// Castling is refused while in check or when the king would pass through
+    /// an attacked square; the landing square is checked with every other move.
+    fn castling_moves(&self, from: Square, team: Team, moves: &mut Vec<Move>) {
+        let rank = team.back_rank();
+        let rights = self.castling_rights(team);
+        if from != Square::new(4, rank) || !(rights.kingside || rights.queenside) {
+            return;
+        }
+        if self.is_square_attacked(from, team.opponent()) {
+            return;
+        }
+
+        let is_empty = |file: u8| self.piece_at(Square::new(file, rank)).is_none();
+        let has_rook =
+            |file: u8| self.piece_at(Square::new(file, rank)) == Some((team, PieceType::Rook));
+        let is_safe = |file: u8| !self.is_square_attacked(Square::new(file, rank), team.opponent());
+        let castle = |to_file: u8| Move {
+            from,
+            to: Square::new(to_file, rank),
+            piece: PieceType::King,
+            captured: None,
+            promotion: None,
+            kind: MoveKind::Castle,
+        };
+
+        if rights.kingside && has_rook(7) && is_empty(5) && is_empty(6) && is_safe(5) {
+            moves.push(castle(6));
+        }
+        if rights.queenside
+            && has_rook(0)
+            && is_empty(1)
+            && is_empty(2)
+            && is_empty(3)
+            && is_safe(3)
+        {
+            moves.push(castle(2));
+        }
+    }
+}
+
+fn is_attacked(squares: &Squares, square: Square, by: Team) -> bool {
+    let piece_at = |target: Option<Square>, piece_type: PieceType| {
+        target.is_some_and(|target| squares[target.index()] == Some((by, piece_type)))
+    };
+
+    // a pawn attacks diagonally forward, so look for one diagonally behind
+    if [-1, 1].into_iter().any(|file_delta| {
+        piece_at(
+            square.offset(file_delta, -by.pawn_direction()),
+            PieceType::Pawn,
+        )
+    }) {
+        return true;
+    }
+    if KNIGHT_OFFSETS.iter().any(|(file_delta, rank_delta)| {
+        piece_at(square.offset(*file_delta, *rank_delta), PieceType::Knight)
+    }) {
+        return true;
+    }
+    if KING_OFFSETS.iter().any(|(file_delta, rank_delta)| {
+        piece_at(square.offset(*file_delta, *rank_delta), PieceType::King)
+    }) {
+        return true;
+    }
+
+    let slider_attacks = |directions: &[(i8, i8)], piece_type: PieceType| {
+        directions.iter().any(|(file_delta, rank_delta)| {
+            let mut current = square;
+            while let Some(next) = current.offset(*file_delta, *rank_delta) {
+                match squares[next.index()] {
+                    None => current = next,
+                    Some((team, found)) => {
+                        return team == by && (found == piece_type || found == PieceType::Queen);
+                    }
+                }
+            }
+            false
+        })
+    };
+    slider_attacks(&ROOK_DIRECTIONS, PieceType::Rook)
+        || slider_attacks(&BISHOP_DIRECTIONS, PieceType::Bishop)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Board;
+
+    /// How many move sequences `depth` moves long there are from `board`.
+    fn perft(board: &mut Board, depth: u32) -> u64 {
+        let moves = board.legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            board.make_move(mv);
+            nodes += perft(board, depth - 1);
+            board.unmake_move();
+        }
+        nodes
+    }
+
+    fn assert_perft(fen: &str, depth: u32, expected: u64) {
+        let mut board = Board::from_fen(fen).unwrap();
+        assert_eq!(
+            perft(&mut board, depth),
+            expected,
+            "perft {} of {}",
+            depth,
+            fen
+        );
+        assert_eq!(board.to_fen(), fen);
+    }
+
+    #[test]
+    fn perft_starting_position() {
+        assert_perft(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            4,
+            197_281,
+        );
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_perft(
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            3,
+            97_862,
+        );
+    }
+
+    #[test]
+    fn perft_position_3() {
+        assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5, 674_624);
+    }
+
+    #[test]
+    fn perft_position_4() {
+        assert_perft(
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            4,
+            422_333,
+        );
+    }
+
+    #[test]
+    fn perft_position_5() {
+        assert_perft(
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            3,
+            62_379,
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{PieceType, Square};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum MoveKind {
+    Normal,
+    DoublePawnPush,
+    EnPassant,
+    Castle,
+}
+
+/// A move together with what it needs to be undone: the moving piece and
+/// anything it captured.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub piece: PieceType,
+    pub captured: Option<PieceType>,
+    pub promotion: Option<PieceType>,
+    pub kind: MoveKind,
+}
+
+impl Move {
+    pub fn is_capture(&self) -> bool {
+        self.captured.is_some()
+    }
+
+    /// Where the captured piece stood. This is the destination except for
+    /// en passant, where the pawn taken sits beside it.
+    pub fn captured_square(&self) -> Option<Square> {
+        self.captured?;
+        if self.kind == MoveKind::EnPassant {
+            Some(Square::new(self.to.file(), self.from.rank()))
+        } else {
+            Some(self.to)
+        }
+    }
+
+    /// The rook's origin and destination when this move castles.
+    pub fn castling_rook(&self) -> Option<(Square, Square)> {
+        if self.kind != MoveKind::Castle {
+            return None;
+        }
+        let rank = self.from.rank();
+        if self.to.file() == 6 {
+            Some((Square::new(7, rank), Square::new(5, rank)))
+        } else {
+            Some((Square::new(0, rank), Square::new(3, rank)))
+        }
+    }
+}
+
+// Written in long algebraic notation, e.g. "e2e4" or "e7e8q".
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(promotion) = self.promotion {
+            write!(f, "{}", promotion.letter().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::Team;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    Decisive { winner: Team, reason: WinReason },
+    Draw(DrawReason),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WinReason {
+    Checkmate,
+    /// The loser ran out of time.
+    Timeout,
+    Resignation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    Stalemate,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    InsufficientMaterial,
+    /// A side ran out of time, but the other had nothing to mate with.
+    TimeoutVsInsufficientMaterial,
+    /// The players agreed to a draw.
+    Agreement,
+}
+
+impl GameOutcome {
+    pub fn winner(&self) -> Option<Team> {
+        match self {
+            GameOutcome::Decisive { winner, .. } => Some(*winner),
+            GameOutcome::Draw(_) => None,
+        }
+    }
+
+    /// The result as written at the end of a PGN game: "1-0", "0-1" or "1/2-1/2".
+    pub fn result_token(&self) -> &'static str {
+        match self.winner() {
+            Some(Team::White) => "1-0",
+            Some(Team::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl fmt::Display for GameOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameOutcome::Decisive { winner, reason } => {
+                let reason = match reason {
+                    WinReason::Checkmate => "checkmate",
+                    WinReason::Timeout => "timeout",
+                    WinReason::Resignation => "resignation",
+                };
+                write!(f, "{:?} wins by {}", winner, reason)
+            }
+            GameOutcome::Draw(reason) => {
+                let reason = match reason {
+                    DrawReason::Stalemate => "stalemate",
+                    DrawReason::FiftyMoveRule => "the fifty-move rule",
+                    DrawReason::SeventyFiveMoveRule => "the seventy-five-move rule",
+                    DrawReason::ThreefoldRepetition => "threefold repetition",
+                    DrawReason::FivefoldRepetition => "fivefold repetition",
+                    DrawReason::InsufficientMaterial => "insufficient material",
+                    DrawReason::TimeoutVsInsufficientMaterial => {
+                        "timeout against insufficient material"
+                    }
+                    DrawReason::Agreement => "agreement",
+                };
+                write!(f, "Draw by {}", reason)
+            }
+        }
+    }
+}
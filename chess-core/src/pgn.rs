@@ -0,0 +1,565 @@
+use crate::{Board, FenError, GameOutcome, Move, SanError, Team, STARTING_FEN};
+use std::fmt;
+
+const MAX_LINE_LENGTH: usize = 80;
+
+/// The Seven Tag Roster every PGN game starts with, less the result, which is
+/// taken from the outcome. Unknown values are written as "?".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnTags {
+    pub event: String,
+    pub site: String,
+    /// In the PGN form "YYYY.MM.DD", with "??" for unknown parts.
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for PgnTags {
+    fn default() -> Self {
+        PgnTags {
+            event: "Casual Game".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            round: "-".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+        }
+    }
+}
+
+/// Writes the game that led to `board` as PGN. Games that did not start from
+/// the standard position get `SetUp` and `FEN` tags; a game without an outcome
+/// is still in progress and ends with "*".
+pub fn write_pgn(tags: &PgnTags, board: &Board, outcome: Option<GameOutcome>) -> String {
+    let mut position = board.clone();
+    let mut moves: Vec<Move> = Vec::new();
+    while let Some(mv) = position.unmake_move() {
+        moves.push(mv);
+    }
+    moves.reverse();
+
+    let result = outcome.map_or("*", |outcome| outcome.result_token());
+    let mut pgn = String::new();
+    for (name, value) in [
+        ("Event", tags.event.as_str()),
+        ("Site", &tags.site),
+        ("Date", &tags.date),
+        ("Round", &tags.round),
+        ("White", &tags.white),
+        ("Black", &tags.black),
+        ("Result", result),
+    ] {
+        pgn.push_str(&tag_pair(name, value));
+    }
+    let starting_fen = position.to_fen();
+    if starting_fen != STARTING_FEN {
+        pgn.push_str(&tag_pair("SetUp", "1"));
+        pgn.push_str(&tag_pair("FEN", &starting_fen));
+    }
+    pgn.push('\n');
+
+    let mut tokens: Vec<String> = Vec::new();
+    for (i, mv) in moves.into_iter().enumerate() {
+        let number = position.fullmove_number();
+        match position.side_to_move() {
+            Team::White => tokens.push(format!("{}.", number)),
+            // a game starting with Black to move needs "1..." before the first move
+            Team::Black if i == 0 => tokens.push(format!("{}...", number)),
+            Team::Black => {}
+        }
+        tokens.push(position.san(mv));
+        position.make_move(mv);
+    }
+    tokens.push(result.to_string());
+
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            pgn.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            pgn.push(' ');
+            line_length += 1;
+        }
+        line_length += token.len();
+        pgn.push_str(&token);
+    }
+    pgn.push('\n');
+    pgn
+}
+
+fn tag_pair(name: &str, value: &str) -> String {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("[{} \"{}\"]\n", name, value)
+}
+
+/// One game read from a PGN file, with its variations and comments dropped.
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    /// Every tag pair in the order it was written.
+    pub tags: Vec<(String, String)>,
+    pub starting_position: Board,
+    pub moves: Vec<Move>,
+    /// "1-0", "0-1", "1/2-1/2", or "*" when the game is unfinished.
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Why a PGN file could not be read. Games and lines are counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    UnterminatedTag {
+        line: usize,
+    },
+    InvalidFen {
+        game: usize,
+        error: FenError,
+    },
+    InvalidMove {
+        game: usize,
+        move_number: u32,
+        side: Team,
+        token: String,
+        error: SanError,
+    },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::UnterminatedTag { line } => {
+                write!(f, "line {}: tag pair is missing its closing ']'", line)
+            }
+            PgnError::InvalidFen { game, error } => {
+                write!(f, "game {}: invalid FEN tag: {}", game, error)
+            }
+            PgnError::InvalidMove {
+                game,
+                move_number,
+                side,
+                token,
+                error,
+            } => {
+                let dots = if *side == Team::White { "." } else { "..." };
+                write!(
+                    f,
+                    "game {}, move {}{}: '{}' {}",
+                    game, move_number, dots, token, error
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+enum Token {
+    Tag(String, String),
+    Symbol(String),
+    StartVariation,
+    EndVariation,
+}
+
+#[derive(Default)]
+struct GameBuilder {
+    tags: Vec<(String, String)>,
+    position: Option<Board>,
+    starting_position: Option<Board>,
+    moves: Vec<Move>,
+}
+
+impl GameBuilder {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.moves.is_empty()
+    }
+
+    /// The position the next move is played from, set up from the FEN tag on
+    /// the first call.
+    fn position(&mut self, game: usize) -> Result<&mut Board, PgnError> {
+        if self.position.is_none() {
+            let fen = self
+                .tags
+                .iter()
+                .find(|(name, _)| name == "FEN")
+                .map_or(STARTING_FEN, |(_, value)| value.as_str());
+            let board =
+                Board::from_fen(fen).map_err(|error| PgnError::InvalidFen { game, error })?;
+            self.starting_position = Some(board.clone());
+            self.position = Some(board);
+        }
+        Ok(self.position.as_mut().unwrap())
+    }
+
+    fn finish(mut self, game: usize, result: &str) -> Result<PgnGame, PgnError> {
+        self.position(game)?;
+        Ok(PgnGame {
+            tags: self.tags,
+            starting_position: self.starting_position.unwrap(),
+            moves: self.moves,
+            result: result.to_string(),
+        })
+    }
+}
+
+/// Reads every game in `text`. Comments, NAGs, annotation symbols and
+/// variations are skipped; only the main line is kept.
+pub fn read_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut current = GameBuilder::default();
+    let mut variation_depth: usize = 0;
+
+    for token in tokenize(text)? {
+        let game = games.len() + 1;
+        match token {
+            Token::Tag(name, value) => {
+                // tags after movetext belong to the next game, even without a result
+                if !current.moves.is_empty() {
+                    games.push(std::mem::take(&mut current).finish(game, "*")?);
+                }
+                current.tags.push((name, value));
+            }
+            Token::StartVariation => variation_depth += 1,
+            Token::EndVariation => variation_depth = variation_depth.saturating_sub(1),
+            Token::Symbol(_) if variation_depth > 0 => {}
+            Token::Symbol(symbol) if matches!(symbol.as_str(), "1-0" | "0-1" | "1/2-1/2" | "*") => {
+                games.push(std::mem::take(&mut current).finish(game, &symbol)?);
+            }
+            Token::Symbol(symbol) => {
+                let san = strip_move_number(&symbol);
+                if san.is_empty() {
+                    continue;
+                }
+                let position = current.position(game)?;
+                let mv = position
+                    .parse_san(san)
+                    .map_err(|error| PgnError::InvalidMove {
+                        game,
+                        move_number: position.fullmove_number(),
+                        side: position.side_to_move(),
+                        token: san.to_string(),
+                        error,
+                    })?;
+                position.make_move(mv);
+                current.moves.push(mv);
+            }
+        }
+    }
+    if !current.is_empty() {
+        games.push(current.finish(games.len() + 1, "*")?);
+    }
+    Ok(games)
+}
+
+/// What is left of `symbol` without a move number in front, which may be
+/// written on its own ("12.", "12...") or run into the move ("12.e4"). Digits
+/// not followed by a dot are part of the move, as in "0-0".
+fn strip_move_number(symbol: &str) -> &str {
+    match symbol.find(|c: char| !c.is_ascii_digit()) {
+        // a move number without its dot
+        None => "",
+        Some(end) if end > 0 && symbol[end..].starts_with('.') => {
+            symbol[end..].trim_start_matches('.')
+        }
+        Some(_) => symbol,
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    let line_of = |offset: usize| text[..offset].matches('\n').count() + 1;
+
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            // a line starting with '%' is an escape for other software
+            '%' if offset == 0 || text[..offset].ends_with('\n') => skip_until(&mut chars, '\n'),
+            ';' => skip_until(&mut chars, '\n'),
+            '{' => skip_until(&mut chars, '}'),
+            '(' => tokens.push(Token::StartVariation),
+            ')' => tokens.push(Token::EndVariation),
+            '$' => while chars.next_if(|(_, c)| c.is_ascii_digit()).is_some() {},
+            '[' => {
+                let unterminated = || PgnError::UnterminatedTag {
+                    line: line_of(offset),
+                };
+                let mut name = String::new();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && *c != '"' && *c != ']')
+                {
+                    name.push(c);
+                }
+                while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+                let mut value = String::new();
+                if chars.next_if(|(_, c)| *c == '"').is_some() {
+                    loop {
+                        match chars.next() {
+                            Some((_, '\\')) => value.extend(chars.next().map(|(_, c)| c)),
+                            Some((_, '"')) => break,
+                            Some((_, c)) => value.push(c),
+                            None => return Err(unterminated()),
+                        }
+                    }
+                }
+                while chars.next_if(|(_, c)| *c != ']' && *c != '\n').is_some() {}
+                if chars.next_if(|(_, c)| *c == ']').is_none() {
+                    return Err(unterminated());
+                }
+                tokens.push(Token::Tag(name, value));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut symbol = c.to_string();
+                while let Some((_, c)) =
+                    chars.next_if(|(_, c)| !c.is_whitespace() && !"{}[]();$".contains(*c))
+                {
+                    symbol.push(c);
+                }
+                tokens.push(Token::Symbol(symbol));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn skip_until(chars: &mut std::iter::Peekable<std::str::CharIndices>, end: char) {
+    for (_, c) in chars.by_ref() {
+        if c == end {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn san_moves(game: &PgnGame) -> Vec<String> {
+        let mut board = game.starting_position.clone();
+        game.moves
+            .iter()
+            .map(|mv| {
+                let san = board.san(*mv);
+                board.make_move(*mv);
+                san
+            })
+            .collect()
+    }
+
+    fn play(fen: &str, moves: &[&str]) -> Board {
+        let mut board = Board::from_fen(fen).unwrap();
+        for san in moves {
+            let mv = board.parse_san(san).unwrap();
+            board.make_move(mv);
+        }
+        board
+    }
+
+    #[test]
+    fn writes_the_seven_tag_roster_and_movetext() {
+        let board = play(STARTING_FEN, &["f3", "e5", "g4", "Qh4"]);
+        let tags = PgnTags {
+            white: "Fool".to_string(),
+            black: "Say \"Mate\"".to_string(),
+            ..PgnTags::default()
+        };
+        assert_eq!(
+            write_pgn(&tags, &board, board.outcome(true)),
+            "[Event \"Casual Game\"]\n\
+             [Site \"?\"]\n\
+             [Date \"????.??.??\"]\n\
+             [Round \"-\"]\n\
+             [White \"Fool\"]\n\
+             [Black \"Say \\\"Mate\\\"\"]\n\
+             [Result \"0-1\"]\n\
+             \n\
+             1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+    }
+
+    #[test]
+    fn writes_setup_for_other_starting_positions() {
+        let fen = "4k3/8/8/8/8/8/4p3/4K3 b - - 0 40";
+        let board = play(fen, &["Kd7", "Kxe2"]);
+        let pgn = write_pgn(&PgnTags::default(), &board, None);
+        assert!(pgn.contains(
+            "[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4p3/4K3 b - - 0 40\"]\n"
+        ));
+        assert!(pgn.ends_with("\n\n40... Kd7 41. Kxe2 *\n"));
+    }
+
+    #[test]
+    fn wraps_movetext_and_reads_back() {
+        let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8"];
+        let moves: Vec<&str> = shuffle.iter().cycle().take(60).copied().collect();
+        let board = play(STARTING_FEN, &moves);
+        let pgn = write_pgn(&PgnTags::default(), &board, None);
+        assert!(pgn.lines().all(|line| line.len() <= MAX_LINE_LENGTH));
+        assert!(pgn.lines().filter(|line| !line.starts_with('[')).count() > 2);
+
+        let games = read_pgn(&pgn).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("Event"), Some("Casual Game"));
+        assert_eq!(games[0].result, "*");
+        assert_eq!(san_moves(&games[0]), moves);
+    }
+
+    #[test]
+    fn castling_may_be_written_with_zeros() {
+        let games = read_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5. d3 0-0 *").unwrap();
+        assert_eq!(
+            san_moves(&games[0]),
+            ["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O", "Nf6", "d3", "O-O"]
+        );
+    }
+
+    #[test]
+    fn move_numbers_may_run_into_the_move() {
+        let games = read_pgn("1.e4 e5 2.Nf3 2...Nc6 3 Bb5 *").unwrap();
+        assert_eq!(san_moves(&games[0]), ["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+    }
+
+    #[test]
+    fn reads_every_game() {
+        let text = "[Event \"First\"]\n\
+                    [White \"A\"]\n\
+                    \n\
+                    1. e4 e5 1-0\n\
+                    \n\
+                    [Event \"Second\"]\n\
+                    [SetUp \"1\"]\n\
+                    [FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\
+                    \n\
+                    1. e4 Kd7 1/2-1/2\n\
+                    \n\
+                    1. d4 d5\n\
+                    [Event \"Fourth\"]\n\
+                    0-1\n";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(games.len(), 4);
+
+        assert_eq!(games[0].tag("Event"), Some("First"));
+        assert_eq!(games[0].tag("White"), Some("A"));
+        assert_eq!(san_moves(&games[0]), ["e4", "e5"]);
+        assert_eq!(games[0].result, "1-0");
+
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(
+            games[1].starting_position.to_fen(),
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"
+        );
+        assert_eq!(san_moves(&games[1]), ["e4", "Kd7"]);
+        assert_eq!(games[1].result, "1/2-1/2");
+
+        // a game without tags, ended by the next one's tags rather than a result
+        assert!(games[2].tags.is_empty());
+        assert_eq!(san_moves(&games[2]), ["d4", "d5"]);
+        assert_eq!(games[2].result, "*");
+
+        assert_eq!(games[3].tag("Event"), Some("Fourth"));
+        assert!(games[3].moves.is_empty());
+        assert_eq!(games[3].result, "0-1");
+    }
+
+    #[test]
+    fn skips_comments() {
+        let text = "% a line for other software\n\
+                    1. e4 {the king's pawn,\n\
+                    two squares} e5 ; the same for Black\n\
+                    2. Nf3 {Nc3 is also played} Nc6 *";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(san_moves(&games[0]), ["e4", "e5", "Nf3", "Nc6"]);
+    }
+
+    #[test]
+    fn skips_nags_and_annotation_symbols() {
+        let games = read_pgn("1. e4 $1 e5 $14 $146 2. Nf3! Nc6?! 3. Bb5!! a6?? *").unwrap();
+        assert_eq!(
+            san_moves(&games[0]),
+            ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6"]
+        );
+    }
+
+    #[test]
+    fn keeps_only_the_main_line() {
+        let text = "1. e4 (1. d4 d5 (1... Nf6 2. c4) 2. c4) e5 \
+                    2. Nf3 (2. Bc4 {the Bishop's Opening} Nf6 $2) Nc6 *";
+        let games = read_pgn(text).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(san_moves(&games[0]), ["e4", "e5", "Nf3", "Nc6"]);
+    }
+
+    #[test]
+    fn reports_an_illegal_move() {
+        let error = read_pgn("1. d4 d5 *\n\n1. e4 e5 2. Ke3 *").unwrap_err();
+        assert_eq!(
+            error,
+            PgnError::InvalidMove {
+                game: 2,
+                move_number: 2,
+                side: Team::White,
+                token: "Ke3".to_string(),
+                error: SanError::Illegal,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "game 2, move 2.: 'Ke3' is not a legal move"
+        );
+    }
+
+    #[test]
+    fn reports_an_ambiguous_move() {
+        let error = read_pgn("1. d4 d5 2. Nf3 Nf6 3. Nd2 *").unwrap_err();
+        assert_eq!(
+            error,
+            PgnError::InvalidMove {
+                game: 1,
+                move_number: 3,
+                side: Team::White,
+                token: "Nd2".to_string(),
+                error: SanError::Ambiguous,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_a_move_it_cannot_read_for_black() {
+        let error = read_pgn("1. e4 e9 *").unwrap_err();
+        assert_eq!(
+            error,
+            PgnError::InvalidMove {
+                game: 1,
+                move_number: 1,
+                side: Team::Black,
+                token: "e9".to_string(),
+                error: SanError::Unreadable,
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "game 1, move 1...: 'e9' is not a move in SAN"
+        );
+    }
+
+    #[test]
+    fn reports_bad_tags() {
+        assert_eq!(
+            read_pgn("[Event \"One\"]\n[Site \"Two\"\n1. e4 *").unwrap_err(),
+            PgnError::UnterminatedTag { line: 2 }
+        );
+        assert!(matches!(
+            read_pgn("[FEN \"8/8/8 w - - 0 1\"]\n1. e4 *").unwrap_err(),
+            PgnError::InvalidFen { game: 1, .. }
+        ));
+    }
+}
@@ -0,0 +1,80 @@
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Team {
+    White,
+    Black,
+}
+
+impl Team {
+    pub fn opponent(self) -> Team {
+        match self {
+            Team::White => Team::Black,
+            Team::Black => Team::White,
+        }
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The direction this side's pawns advance in, as a rank delta.
+    pub(crate) fn pawn_direction(self) -> i8 {
+        match self {
+            Team::White => 1,
+            Team::Black => -1,
+        }
+    }
+
+    pub(crate) fn back_rank(self) -> u8 {
+        match self {
+            Team::White => 0,
+            Team::Black => 7,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum PieceType {
+    Pawn,
+    Bishop,
+    Knight,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceType {
+    pub const PROMOTIONS: [PieceType; 4] = [
+        PieceType::Queen,
+        PieceType::Rook,
+        PieceType::Bishop,
+        PieceType::Knight,
+    ];
+
+    /// The upper-case letter used for this piece in algebraic notation.
+    pub fn letter(self) -> char {
+        match self {
+            PieceType::Pawn => 'P',
+            PieceType::Bishop => 'B',
+            PieceType::Knight => 'N',
+            PieceType::Rook => 'R',
+            PieceType::Queen => 'Q',
+            PieceType::King => 'K',
+        }
+    }
+
+    pub fn from_letter(letter: char) -> Option<PieceType> {
+        match letter.to_ascii_uppercase() {
+            'P' => Some(PieceType::Pawn),
+            'B' => Some(PieceType::Bishop),
+            'N' => Some(PieceType::Knight),
+            'R' => Some(PieceType::Rook),
+            'Q' => Some(PieceType::Queen),
+            'K' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+}
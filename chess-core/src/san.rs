@@ -0,0 +1,237 @@
+use crate::{Board, Move, MoveKind, PieceType, Square};
+use std::fmt;
+
+impl Board {
+    /// `mv` in Standard Algebraic Notation, such as "Nbd7", "exd6" or "e8=Q#".
+    /// `mv` must be legal in this position.
+    pub fn san(&self, mv: Move) -> String {
+        let mut san = String::new();
+        if let Some((_, rook_to)) = mv.castling_rook() {
+            san.push_str(if rook_to.file() == 5 { "O-O" } else { "O-O-O" });
+        } else {
+            if mv.piece == PieceType::Pawn {
+                if mv.is_capture() {
+                    san.push(mv.from.file_letter());
+                }
+            } else {
+                san.push(mv.piece.letter());
+                san.push_str(&self.disambiguation(mv));
+            }
+            if mv.is_capture() {
+                san.push('x');
+            }
+            san.push_str(&mv.to.to_string());
+            if let Some(promotion) = mv.promotion {
+                san.push('=');
+                san.push(promotion.letter());
+            }
+        }
+
+        let mut after = self.clone();
+        after.make_move(mv);
+        if after.is_in_check() {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    /// The file, rank or whole square needed to tell `mv` apart from other moves
+    /// of the same kind of piece to the same square: the file if that is enough,
+    /// then the rank, and the full square only when neither is.
+    fn disambiguation(&self, mv: Move) -> String {
+        let rivals: Vec<Move> = self
+            .legal_moves()
+            .into_iter()
+            .filter(|other| other.piece == mv.piece && other.to == mv.to && other.from != mv.from)
+            .collect();
+        if rivals.is_empty() {
+            String::new()
+        } else if rivals
+            .iter()
+            .all(|other| other.from.file() != mv.from.file())
+        {
+            mv.from.file_letter().to_string()
+        } else if rivals
+            .iter()
+            .all(|other| other.from.rank() != mv.from.rank())
+        {
+            mv.from.rank_digit().to_string()
+        } else {
+            mv.from.to_string()
+        }
+    }
+}
+
+/// Why a SAN move could not be matched to a legal move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanError {
+    Unreadable,
+    Illegal,
+    Ambiguous,
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::Unreadable => write!(f, "is not a move in SAN"),
+            SanError::Illegal => write!(f, "is not a legal move"),
+            SanError::Ambiguous => write!(f, "is ambiguous"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Board {
+    /// Finds the legal move written as `san`. Check, mate and annotation
+    /// suffixes are ignored, castling may be written with zeros, and the "="
+    /// before a promotion piece may be left out.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let text = san.trim_end_matches(['+', '#', '!', '?']);
+
+        if matches!(text, "O-O" | "0-0" | "O-O-O" | "0-0-0") {
+            let to_file = if text.len() == 3 { 6 } else { 2 };
+            return self
+                .legal_moves()
+                .into_iter()
+                .find(|mv| mv.kind == MoveKind::Castle && mv.to.file() == to_file)
+                .ok_or(SanError::Illegal);
+        }
+
+        let mut chars: Vec<char> = text.chars().collect();
+        let piece = match chars.first() {
+            Some(letter) if letter.is_ascii_uppercase() => {
+                let piece = PieceType::from_letter(*letter).ok_or(SanError::Unreadable)?;
+                chars.remove(0);
+                piece
+            }
+            _ => PieceType::Pawn,
+        };
+
+        let mut promotion = None;
+        if let Some(letter) = chars.last().filter(|letter| letter.is_ascii_uppercase()) {
+            promotion = Some(
+                PieceType::from_letter(*letter)
+                    .filter(|piece| PieceType::PROMOTIONS.contains(piece))
+                    .ok_or(SanError::Unreadable)?,
+            );
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+
+        if chars.len() < 2 {
+            return Err(SanError::Unreadable);
+        }
+        let destination: String = chars.split_off(chars.len() - 2).into_iter().collect();
+        let to = Square::from_algebraic(&destination).ok_or(SanError::Unreadable)?;
+
+        // what is left is an optional origin file and rank, then an optional 'x'
+        if chars.last() == Some(&'x') {
+            chars.pop();
+        }
+        let mut from_file = None;
+        let mut from_rank = None;
+        for letter in chars {
+            match letter {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => {
+                    from_file = Some(letter as u8 - b'a')
+                }
+                '1'..='8' if from_rank.is_none() => from_rank = Some(letter as u8 - b'1'),
+                _ => return Err(SanError::Unreadable),
+            }
+        }
+
+        let mut candidates = self.legal_moves().into_iter().filter(|mv| {
+            mv.piece == piece
+                && mv.to == to
+                && mv.promotion == promotion
+                && mv.kind != MoveKind::Castle
+                && from_file.is_none_or(|file| mv.from.file() == file)
+                && from_rank.is_none_or(|rank| mv.from.rank() == rank)
+        });
+        let mv = candidates.next().ok_or(SanError::Illegal)?;
+        if candidates.next().is_some() {
+            return Err(SanError::Ambiguous);
+        }
+        Ok(mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The SAN of the move given in long algebraic notation, checking it
+    /// reads back as the same move.
+    fn san(fen: &str, mv: &str) -> String {
+        let board = Board::from_fen(fen).unwrap();
+        let mv = board.parse_move(mv).unwrap();
+        let san = board.san(mv);
+        assert_eq!(board.parse_san(&san), Ok(mv), "{} in {}", san, fen);
+        san
+    }
+
+    #[test]
+    fn disambiguates_by_file_then_rank_then_square() {
+        let knights = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+        assert_eq!(san(knights, "b1d2"), "Nbd2");
+        assert_eq!(san(knights, "f1d2"), "Nfd2");
+        assert_eq!(san(knights, "b1c3"), "Nc3");
+
+        let rooks = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        assert_eq!(san(rooks, "a1a3"), "R1a3");
+        assert_eq!(san(rooks, "a5a3"), "R5a3");
+
+        let queens = "4k3/8/8/8/8/Q7/8/Q1Q4K w - - 0 1";
+        assert_eq!(san(queens, "a1b2"), "Qa1b2");
+        assert_eq!(san(queens, "a3b2"), "Q3b2");
+        assert_eq!(san(queens, "c1b2"), "Qcb2");
+    }
+
+    #[test]
+    fn marks_check_and_mate() {
+        assert_eq!(san("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", "a1a8"), "Ra8+");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn writes_pawn_moves_and_castling() {
+        let promotion = "3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(san(promotion, "e7e8q"), "e8=Q+");
+        assert_eq!(san(promotion, "e7d8n"), "exd8=N");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), "exd6");
+
+        let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(san(castling, "e1g1"), "O-O");
+        assert_eq!(san(castling, "e1c1"), "O-O-O");
+    }
+
+    #[test]
+    fn reads_loose_san() {
+        let board = Board::from_fen("3r3k/4P3/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(board.parse_san("0-0-0"), board.parse_san("O-O-O"));
+        assert_eq!(board.parse_san("e8Q"), board.parse_san("e8=Q+"));
+        assert_eq!(
+            board.parse_san("exd8=R!?"),
+            Ok(board.parse_move("e7d8r").unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_bad_san() {
+        let queens = Board::from_fen("4k3/8/8/8/8/Q7/8/Q1Q4K w - - 0 1").unwrap();
+        assert_eq!(queens.parse_san("Qb2"), Err(SanError::Ambiguous));
+        assert_eq!(queens.parse_san("Qab2"), Err(SanError::Ambiguous));
+        assert_eq!(queens.parse_san("Qh7"), Err(SanError::Illegal));
+        assert_eq!(queens.parse_san("O-O"), Err(SanError::Illegal));
+        assert_eq!(queens.parse_san("Zb2"), Err(SanError::Unreadable));
+        assert_eq!(queens.parse_san("Q"), Err(SanError::Unreadable));
+        assert_eq!(queens.parse_san("e8=K"), Err(SanError::Unreadable));
+    }
+}
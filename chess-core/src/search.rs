@@ -0,0 +1,456 @@
+use crate::eval::{evaluate, piece_value};
+use crate::{Board, Move};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// The score of being mated on the spot. Mates further away score a little
+/// less, so the search prefers the quickest mate and the slowest loss.
+pub const MATE_SCORE: i32 = 30_000;
+
+const MAX_PLY: usize = 64;
+// how many nodes to search between looks at the clock
+const NODES_PER_TIME_CHECK: u64 = 1024;
+
+/// When to stop deepening. Without a time budget every depth up to `depth` is
+/// searched; with one, the search stops when it runs out, keeping the best
+/// move of the deepest iteration it finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: u32,
+    pub time: Option<Duration>,
+}
+
+/// No limit but the deepest the search goes: it runs until it is stopped or
+/// has searched to the maximum depth.
+impl Default for SearchLimits {
+    fn default() -> Self {
+        SearchLimits {
+            depth: MAX_PLY as u32,
+            time: None,
+        }
+    }
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits { depth, time: None }
+    }
+
+    pub fn time(time: Duration) -> SearchLimits {
+        SearchLimits {
+            time: Some(time),
+            ..SearchLimits::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// `None` only when the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// The line the search expects to be played, starting with `best_move`.
+    pub pv: Vec<Move>,
+    /// Centipawns from the side to move's point of view.
+    pub score: i32,
+    /// The deepest iteration that finished.
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+/// Finds a move for the side to move with iterative-deepening alpha-beta
+/// search and a quiescence search over captures at the leaves.
+pub fn search(board: &Board, limits: SearchLimits) -> SearchResult {
+    search_until_stopped(board, limits, &AtomicBool::new(false), |_| {})
+}
+
+/// Like [`search`], but also gives up as soon as `stop` is set, which another
+/// thread can do to cancel a search it no longer needs. The result is then that
+/// of the last iteration finished before the stop. `report` is called with the
+/// result so far after every iteration.
+pub fn search_until_stopped(
+    board: &Board,
+    limits: SearchLimits,
+    stop: &AtomicBool,
+    mut report: impl FnMut(&SearchResult),
+) -> SearchResult {
+    let mut searcher = Searcher {
+        nodes: 0,
+        // the first iteration always finishes so there is a searched move to play
+        deadline: None,
+        stop,
+        stopped: false,
+        killers: [[None; 2]; MAX_PLY],
+        pv: vec![Vec::new(); MAX_PLY + 1],
+    };
+    let mut board = board.clone();
+    let first_move = board.legal_moves().first().copied();
+    let mut result = SearchResult {
+        best_move: first_move,
+        pv: first_move.into_iter().collect(),
+        score: 0,
+        depth: 0,
+        nodes: 0,
+    };
+    if result.best_move.is_none() {
+        return result;
+    }
+
+    let deadline = limits.time.map(|time| Instant::now() + time);
+    for depth in 1..=limits.depth.max(1) {
+        let score = searcher.search_root(&mut board, depth, result.best_move);
+        searcher.deadline = deadline;
+        if searcher.stopped {
+            break;
+        }
+        result.pv = searcher.pv[0].clone();
+        result.best_move = result.pv.first().copied();
+        result.score = score;
+        result.depth = depth;
+        result.nodes = searcher.nodes;
+        report(&result);
+        // no point looking deeper once a forced mate has been found
+        if is_mate_score(score) {
+            break;
+        }
+    }
+    result.nodes = searcher.nodes;
+    result
+}
+
+/// Whether `score` means a forced mate for one side or the other.
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE_SCORE - MAX_PLY as i32
+}
+
+struct Searcher<'a> {
+    nodes: u64,
+    deadline: Option<Instant>,
+    stop: &'a AtomicBool,
+    stopped: bool,
+    /// Quiet moves that caused a beta cutoff, two per ply, tried early in
+    /// sibling positions.
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// The best line found from each ply of the current path.
+    pv: Vec<Vec<Move>>,
+}
+
+impl Searcher<'_> {
+    fn search_root(&mut self, board: &mut Board, depth: u32, previous_best: Option<Move>) -> i32 {
+        let mut moves = board.legal_moves();
+        self.order_moves(&mut moves, 0, previous_best);
+        self.pv[0].clear();
+
+        let mut alpha = -MATE_SCORE - 1;
+        let beta = MATE_SCORE + 1;
+        for mv in moves {
+            board.make_move(mv);
+            let score = -self.negamax(board, depth - 1, 1, -beta, -alpha);
+            board.unmake_move();
+            if self.stopped {
+                break;
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(0, mv);
+            }
+        }
+        alpha
+    }
+
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.pv[ply].clear();
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        if is_draw(board) {
+            return 0;
+        }
+
+        let in_check = board.is_in_check();
+        // look one move further when in check so the search doesn't stop short of a mate
+        let depth = if in_check && ply < MAX_PLY / 2 {
+            depth + 1
+        } else {
+            depth
+        };
+        if depth == 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        let mut moves = board.legal_moves();
+        if moves.is_empty() {
+            return if in_check {
+                -MATE_SCORE + ply as i32
+            } else {
+                0
+            };
+        }
+        self.order_moves(&mut moves, ply, None);
+
+        for mv in moves {
+            board.make_move(mv);
+            let score = -self.negamax(board, depth - 1, ply + 1, -beta, -alpha);
+            board.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                if !mv.is_capture() && mv.promotion.is_none() {
+                    self.store_killer(mv, ply);
+                }
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, mv);
+            }
+        }
+        alpha
+    }
+
+    /// Makes `mv` followed by the line found after it the best line from `ply`.
+    fn update_pv(&mut self, ply: usize, mv: Move) {
+        let (head, tail) = self.pv.split_at_mut(ply + 1);
+        let line = &mut head[ply];
+        line.clear();
+        line.push(mv);
+        line.extend_from_slice(&tail[0]);
+    }
+
+    /// Searches captures and promotions only, so positions are not judged in
+    /// the middle of an exchange. Standing pat on the static evaluation is
+    /// allowed because the side to move need not capture.
+    fn quiescence(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        // the line stops where the quiescence search starts
+        self.pv[ply].clear();
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+
+        let stand_pat = evaluate(board);
+        if stand_pat >= beta {
+            return beta;
+        }
+        alpha = alpha.max(stand_pat);
+        if ply >= MAX_PLY - 1 {
+            return alpha;
+        }
+
+        let mut moves: Vec<Move> = board
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.is_capture() || mv.promotion.is_some())
+            .collect();
+        self.order_moves(&mut moves, ply, None);
+
+        for mv in moves {
+            board.make_move(mv);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.unmake_move();
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return beta;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Puts the likeliest best moves first so alpha-beta cuts off sooner: the
+    /// previous iteration's best move, then captures of valuable pieces by
+    /// cheap ones, promotions, killer moves, and the remaining quiet moves.
+    fn order_moves(&self, moves: &mut [Move], ply: usize, best_move: Option<Move>) {
+        let killers = self.killers[ply];
+        moves.sort_by_cached_key(|mv| {
+            let score = if Some(*mv) == best_move {
+                1_000_000
+            } else if let Some(captured) = mv.captured {
+                100_000 + 10 * piece_value(captured) - piece_value(mv.piece)
+            } else if let Some(promotion) = mv.promotion {
+                90_000 + piece_value(promotion)
+            } else if killers.contains(&Some(*mv)) {
+                80_000
+            } else {
+                0
+            };
+            -score
+        });
+    }
+
+    fn store_killer(&mut self, mv: Move, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(NODES_PER_TIME_CHECK) {
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+        self.stopped
+    }
+}
+
+/// Positions the search scores as drawn. A single repetition counts, since
+/// whatever could be done the first time can be done again.
+fn is_draw(board: &Board) -> bool {
+    board.halfmove_clock() >= 100
+        || board.repetition_count() >= 2
+        || board.has_insufficient_material()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameOutcome, Team, WinReason};
+
+    fn best_move(fen: &str, limits: SearchLimits) -> SearchResult {
+        search(&Board::from_fen(fen).unwrap(), limits)
+    }
+
+    /// Plays out the line the search expects and returns where it ends.
+    fn play_pv(fen: &str, result: &SearchResult) -> Board {
+        let mut board = Board::from_fen(fen).unwrap();
+        for mv in &result.pv {
+            board.make_move(*mv);
+        }
+        board
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let result = best_move(fen, SearchLimits::depth(3));
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+        assert_eq!(result.score, MATE_SCORE - 1);
+        assert!(is_mate_score(result.score));
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        // 1. Kb6 Kb8 2. Rh8#
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let result = best_move(fen, SearchLimits::depth(4));
+        assert_eq!(result.score, MATE_SCORE - 3);
+        assert_eq!(result.pv.len(), 3);
+        assert_eq!(
+            play_pv(fen, &result).outcome(true),
+            Some(GameOutcome::Decisive {
+                winner: Team::White,
+                reason: WinReason::Checkmate,
+            })
+        );
+    }
+
+    #[test]
+    fn sees_being_mated() {
+        // Black to move can't stop Ra8 or Rb8
+        let fen = "7k/8/6K1/8/8/8/R7/1R6 b - - 0 1";
+        let result = best_move(fen, SearchLimits::depth(3));
+        assert!(result.score <= -(MATE_SCORE - 3));
+    }
+
+    #[test]
+    fn takes_a_hanging_piece() {
+        let result = best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", SearchLimits::depth(3));
+        assert_eq!(result.best_move.unwrap().to_string(), "d2d5");
+        assert!(result.score > piece_value(crate::PieceType::Bishop));
+    }
+
+    #[test]
+    fn quiescence_sees_the_recapture() {
+        // at depth 1, Qxd5 wins a pawn until the quiescence search sees cxd5
+        let fen = "4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1";
+        let result = best_move(fen, SearchLimits::depth(1));
+        assert_ne!(result.best_move.unwrap().to_string(), "d1d5");
+        assert!(result.score > 0);
+    }
+
+    #[test]
+    fn captures_are_ordered_by_victim_then_attacker() {
+        // the pawn and the queen can both take the rook; the queen can take a pawn
+        let board = Board::from_fen("4k3/8/8/3r1p2/2P5/8/8/3QK3 w - - 0 1").unwrap();
+        let stop = AtomicBool::new(false);
+        let searcher = Searcher {
+            nodes: 0,
+            deadline: None,
+            stop: &stop,
+            stopped: false,
+            killers: [[None; 2]; MAX_PLY],
+            pv: vec![Vec::new(); MAX_PLY + 1],
+        };
+        let mut moves = board.legal_moves();
+        searcher.order_moves(&mut moves, 0, None);
+        let order: Vec<String> = moves.iter().take(2).map(|mv| mv.to_string()).collect();
+        assert_eq!(order, ["c4d5", "d1d5"]);
+
+        let quiet = board.parse_move("e1e2").unwrap();
+        searcher.order_moves(&mut moves, 0, Some(quiet));
+        assert_eq!(moves[0], quiet);
+    }
+
+    #[test]
+    fn stops_at_the_depth_limit() {
+        let board = Board::starting_position();
+        for depth in 1..=3 {
+            let mut reported = Vec::new();
+            let result = search_until_stopped(
+                &board,
+                SearchLimits::depth(depth),
+                &AtomicBool::new(false),
+                |result| reported.push(result.depth),
+            );
+            assert_eq!(result.depth, depth);
+            assert_eq!(reported, (1..=depth).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn stops_when_the_time_is_up() {
+        let started = Instant::now();
+        let result = search(
+            &Board::starting_position(),
+            SearchLimits::time(Duration::from_millis(100)),
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(result.depth >= 1);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    fn stopping_at_once_plays_the_first_legal_move() {
+        let board = Board::starting_position();
+        let result = search_until_stopped(
+            &board,
+            SearchLimits::default(),
+            &AtomicBool::new(true),
+            |_| panic!("no iteration should finish"),
+        );
+        assert_eq!(result.best_move, board.legal_moves().first().copied());
+        assert_eq!(result.depth, 0);
+    }
+
+    #[test]
+    fn no_move_without_legal_moves() {
+        // stalemate
+        let result = best_move("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", SearchLimits::depth(3));
+        assert_eq!(result.best_move, None);
+        assert!(result.pv.is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+/// One of the 64 squares, indexed rank by rank from a1 (0) to h8 (63).
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Copy, Clone)]
+pub struct Square(u8);
+
+impl Square {
+    /// Builds a square from a zero-based file (a = 0) and rank (1st rank = 0).
+    pub fn new(file: u8, rank: u8) -> Square {
+        debug_assert!(file < 8 && rank < 8, "square out of range");
+        Square(rank * 8 + file)
+    }
+
+    pub fn from_index(index: usize) -> Square {
+        debug_assert!(index < 64, "square out of range");
+        Square(index as u8)
+    }
+
+    pub fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    pub fn rank(self) -> u8 {
+        self.0 / 8
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn offset(self, file_delta: i8, rank_delta: i8) -> Option<Square> {
+        let file = self.file() as i8 + file_delta;
+        let rank = self.rank() as i8 + rank_delta;
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            Some(Square::new(file as u8, rank as u8))
+        } else {
+            None
+        }
+    }
+
+    /// The file as written in algebraic notation, 'a' to 'h'.
+    pub fn file_letter(self) -> char {
+        (b'a' + self.file()) as char
+    }
+
+    /// The rank as written in algebraic notation, '1' to '8'.
+    pub fn rank_digit(self) -> char {
+        (b'1' + self.rank()) as char
+    }
+
+    pub fn is_light(self) -> bool {
+        (self.file() + self.rank()) % 2 == 1
+    }
+
+    /// Parses a square written as in algebraic notation, such as "e4".
+    pub fn from_algebraic(text: &str) -> Option<Square> {
+        let mut chars = text.chars();
+        let file = chars.next()?;
+        let rank = chars.next()?;
+        if chars.next().is_some() || !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+            return None;
+        }
+        Some(Square::new(file as u8 - b'a', rank as u8 - b'1'))
+    }
+
+    pub fn all() -> impl Iterator<Item = Square> {
+        (0..64).map(Square)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file_letter(), self.rank_digit())
+    }
+}
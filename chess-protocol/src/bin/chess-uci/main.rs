@@ -0,0 +1,127 @@
+//! The built-in search as a headless engine, for engine tournaments and
+//! regression suites. It reads commands on standard input and answers on
+//! standard output, in UCI or, when the first command is `xboard`, in CECP.
+
+mod uci;
+mod xboard;
+
+use chess_core::{search_until_stopped, Board, SearchLimits, SearchResult};
+use chess_protocol::xboard::XboardCommand;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const NAME: &str = "chess";
+const AUTHOR: &str = "the chess developers";
+
+/// Keeps this much of the clock back for the time it takes to send the move.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+/// How many moves the remaining time is spread over when the GUI doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// What the protocol loops wait on: the GUI's commands, read on their own
+/// thread, and the searches finishing.
+enum Event {
+    Line(String),
+    InputClosed,
+    SearchDone { id: u32, result: SearchResult },
+}
+
+/// A search running on its own thread, so the GUI's commands are still read
+/// while it thinks. `report` is called after every iteration and `done` with
+/// the final result; a search that runs `until_stopped` holds on to its
+/// result until it is stopped, even when it has nothing left to search.
+struct BackgroundSearch {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl BackgroundSearch {
+    fn start(
+        board: Board,
+        limits: SearchLimits,
+        until_stopped: bool,
+        mut report: impl FnMut(&SearchResult, Duration) + Send + 'static,
+        done: impl FnOnce(SearchResult) + Send + 'static,
+    ) -> BackgroundSearch {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            let started = Instant::now();
+            let result = search_until_stopped(&board, limits, &thread_stop, |result| {
+                report(result, started.elapsed());
+            });
+            while until_stopped && !thread_stop.load(Ordering::Relaxed) {
+                thread::park();
+            }
+            done(result);
+        });
+        BackgroundSearch { stop, thread }
+    }
+
+    /// Asks the search to finish with the best move it has, without waiting.
+    fn hurry(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.thread.thread().unpark();
+    }
+
+    /// Stops the search and waits for it to hand over its move.
+    fn stop(self) {
+        self.hurry();
+        let _ = self.thread.join();
+    }
+}
+
+fn stop_search(search: &mut Option<BackgroundSearch>) {
+    if let Some(search) = search.take() {
+        search.stop();
+    }
+}
+
+/// How long to think with `time_left` on the clock for `moves_to_go` moves,
+/// getting `increment` back after each.
+fn time_budget(time_left: Duration, increment: Duration, moves_to_go: u32) -> Duration {
+    // never plan on more than half of what is left
+    (time_left / moves_to_go.max(1) + increment * 3 / 4)
+        .min(time_left / 2)
+        .saturating_sub(MOVE_OVERHEAD)
+}
+
+fn main() {
+    let (sender, events) = mpsc::channel();
+    let input = sender.clone();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if input.send(Event::Line(line)).is_err() {
+                return;
+            }
+        }
+        let _ = input.send(Event::InputClosed);
+    });
+
+    // the first command says which protocol the GUI speaks
+    let Some(first) = next_line(&events) else {
+        return;
+    };
+    if XboardCommand::parse(&first) == Some(XboardCommand::Xboard) {
+        xboard::run(&events, sender);
+    } else {
+        uci::run(first, &events);
+    }
+}
+
+fn next_line(events: &Receiver<Event>) -> Option<String> {
+    loop {
+        match events.recv() {
+            Ok(Event::Line(line)) if !line.trim().is_empty() => return Some(line),
+            Ok(Event::Line(_) | Event::SearchDone { .. }) => {}
+            Ok(Event::InputClosed) | Err(_) => return None,
+        }
+    }
+}
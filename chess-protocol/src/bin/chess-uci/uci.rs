@@ -0,0 +1,177 @@
+use crate::{
+    next_line, stop_search, time_budget, BackgroundSearch, Event, AUTHOR, DEFAULT_MOVES_TO_GO,
+    MOVE_OVERHEAD, NAME,
+};
+use chess_core::{Board, SearchLimits, SearchResult, Team};
+use chess_protocol::uci::{EngineMessage, GoParams, GuiCommand, Info, Score};
+use std::sync::mpsc::Receiver;
+use std::time::Duration;
+
+/// Answers UCI commands, starting with `first`, until `quit` or the end of
+/// input. Searches print their own `bestmove` when they are done, or for
+/// `go infinite`, not before `stop`.
+pub fn run(first: String, events: &Receiver<Event>) {
+    let mut board = Board::starting_position();
+    let mut search: Option<BackgroundSearch> = None;
+
+    let mut first = Some(first);
+    while let Some(text) = first.take().or_else(|| next_line(events)) {
+        let Some(command) = GuiCommand::parse(&text) else {
+            continue;
+        };
+        match command {
+            GuiCommand::Uci => {
+                send(EngineMessage::IdName(NAME.to_string()));
+                send(EngineMessage::IdAuthor(AUTHOR.to_string()));
+                send(EngineMessage::UciOk);
+            }
+            GuiCommand::IsReady => send(EngineMessage::ReadyOk),
+            GuiCommand::UciNewGame => {
+                stop_search(&mut search);
+                board = Board::starting_position();
+            }
+            GuiCommand::Position(position) => match position.to_board() {
+                Ok(position) => board = position,
+                Err(error) => send(EngineMessage::Info(Info {
+                    string: Some(format!("ignoring position: {}", error)),
+                    ..Info::default()
+                })),
+            },
+            GuiCommand::Go(params) => {
+                stop_search(&mut search);
+                let limits = limits(&params, board.side_to_move());
+                search = Some(BackgroundSearch::start(
+                    board.clone(),
+                    limits,
+                    params.infinite,
+                    |result, elapsed| send(EngineMessage::Info(info(result, elapsed))),
+                    |result| {
+                        send(EngineMessage::BestMove {
+                            best: result
+                                .best_move
+                                .map_or_else(|| "0000".to_string(), |mv| mv.to_string()),
+                            ponder: result.pv.get(1).map(|mv| mv.to_string()),
+                        })
+                    },
+                ));
+            }
+            GuiCommand::Stop => stop_search(&mut search),
+            GuiCommand::Quit => break,
+            GuiCommand::Debug(_) | GuiCommand::SetOption { .. } | GuiCommand::PonderHit => {}
+        }
+    }
+    stop_search(&mut search);
+}
+
+/// Turns a `go` command into search limits. A fixed move time wins over the
+/// clock; with neither, the search runs to `depth` or until it is stopped.
+fn limits(params: &GoParams, side: Team) -> SearchLimits {
+    let (time_left, increment) = match side {
+        Team::White => (params.wtime, params.winc),
+        Team::Black => (params.btime, params.binc),
+    };
+    let time = if params.infinite {
+        None
+    } else if let Some(movetime) = params.movetime {
+        Some(Duration::from_millis(movetime).saturating_sub(MOVE_OVERHEAD))
+    } else {
+        time_left.map(|time_left| {
+            time_budget(
+                Duration::from_millis(time_left),
+                Duration::from_millis(increment.unwrap_or(0)),
+                params.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO),
+            )
+        })
+    };
+    SearchLimits {
+        depth: params.depth.unwrap_or(SearchLimits::default().depth),
+        time,
+    }
+}
+
+fn info(result: &SearchResult, elapsed: Duration) -> Info {
+    let millis = elapsed.as_millis() as u64;
+    Info {
+        depth: Some(result.depth),
+        score: Some(Score::from_search(result.score)),
+        nodes: Some(result.nodes),
+        nps: Some(result.nodes * 1000 / millis.max(1)),
+        time: Some(millis),
+        pv: result.pv.iter().map(|mv| mv.to_string()).collect(),
+        ..Info::default()
+    }
+}
+
+fn send(message: EngineMessage) {
+    println!("{}", message);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn go(line: &str) -> GoParams {
+        match GuiCommand::parse(line) {
+            Some(GuiCommand::Go(params)) => params,
+            _ => panic!("not a go command: {}", line),
+        }
+    }
+
+    fn millis(millis: u64) -> Option<Duration> {
+        Some(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn a_move_time_wins_over_the_clock() {
+        let limits = limits(&go("go wtime 60000 btime 60000 movetime 1000"), Team::White);
+        assert_eq!(limits.time, millis(970));
+        assert_eq!(limits.depth, SearchLimits::default().depth);
+    }
+
+    #[test]
+    fn spreads_the_clock_over_the_moves_to_go() {
+        let params = go("go wtime 60000 btime 30000 winc 1000 binc 2000 movestogo 20");
+        // 60000 / 20 + 3/4 of the increment, less the overhead
+        assert_eq!(limits(&params, Team::White).time, millis(3720));
+        assert_eq!(limits(&params, Team::Black).time, millis(2970));
+        // without movestogo, over the default number of moves
+        assert_eq!(
+            limits(&go("go wtime 90000"), Team::White).time,
+            millis(2970)
+        );
+        // and never more than half of what is left
+        assert_eq!(
+            limits(&go("go btime 1000 movestogo 1"), Team::Black).time,
+            millis(470)
+        );
+        assert_eq!(limits(&go("go wtime 20"), Team::White).time, millis(0));
+    }
+
+    #[test]
+    fn the_other_sides_clock_is_no_budget() {
+        let limits = limits(&go("go btime 60000"), Team::White);
+        assert_eq!(limits, SearchLimits::default());
+    }
+
+    #[test]
+    fn infinite_ignores_the_clock() {
+        let limits = limits(&go("go infinite wtime 60000 movetime 1000"), Team::White);
+        assert_eq!(limits, SearchLimits::default());
+    }
+
+    #[test]
+    fn depth_limits_the_search() {
+        assert_eq!(
+            limits(&go("go depth 5"), Team::Black),
+            SearchLimits::depth(5)
+        );
+        assert_eq!(
+            limits(&go("go depth 5 movetime 2000"), Team::White),
+            SearchLimits {
+                depth: 5,
+                time: millis(1970),
+            }
+        );
+        assert_eq!(limits(&go("go"), Team::White), SearchLimits::default());
+    }
+}
@@ -0,0 +1,268 @@
+use crate::{
+    stop_search, time_budget, BackgroundSearch, Event, DEFAULT_MOVES_TO_GO, MOVE_OVERHEAD, NAME,
+};
+use chess_core::{Board, SearchLimits, SearchResult, Team};
+use chess_protocol::uci::Score;
+use chess_protocol::xboard::{Thinking, XboardCommand, XboardOutput};
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::Duration;
+
+/// How long to think when the GUI has sent no time control at all.
+const DEFAULT_MOVE_TIME: Duration = Duration::from_secs(5);
+/// The score XBoard shows as a mate, with the number of moves added on.
+const XBOARD_MATE: i32 = 100_000;
+
+/// The time control set by `level` or `st`, and the clock sent with `time`.
+#[derive(Default)]
+struct TimeControl {
+    /// Moves per session, 0 for the whole game.
+    moves: u32,
+    increment: Duration,
+    move_time: Option<Duration>,
+    depth: Option<u32>,
+    time_left: Option<Duration>,
+}
+
+impl TimeControl {
+    fn limits(&self, board: &Board) -> SearchLimits {
+        let time = match (self.move_time, self.time_left) {
+            (Some(move_time), _) => move_time.saturating_sub(MOVE_OVERHEAD),
+            (None, Some(time_left)) => {
+                let moves_to_go = if self.moves > 0 {
+                    self.moves - (board.fullmove_number() - 1) % self.moves
+                } else {
+                    DEFAULT_MOVES_TO_GO
+                };
+                time_budget(time_left, self.increment, moves_to_go)
+            }
+            (None, None) => match self.depth {
+                Some(depth) => return SearchLimits::depth(depth),
+                None => DEFAULT_MOVE_TIME,
+            },
+        };
+        SearchLimits {
+            depth: self.depth.unwrap_or(SearchLimits::default().depth),
+            time: Some(time),
+        }
+    }
+}
+
+/// The engine's side of a CECP game. Unlike UCI, the engine keeps the game
+/// itself: moves arrive one at a time, and it plays the side it has been told
+/// to whenever that side is to move, until `force` puts it in force mode.
+struct XboardEngine {
+    board: Board,
+    /// The side the engine plays, or `None` in force mode.
+    side: Option<Team>,
+    time_control: TimeControl,
+    post: bool,
+    search: Option<BackgroundSearch>,
+    /// Counts searches, so the result of one that was stopped and thrown away
+    /// is told apart from the one running.
+    search_id: u32,
+    events: Sender<Event>,
+}
+
+/// Answers CECP commands until `quit` or the end of input.
+pub fn run(events: &Receiver<Event>, sender: Sender<Event>) {
+    let mut engine = XboardEngine {
+        board: Board::starting_position(),
+        side: Some(Team::Black),
+        time_control: TimeControl::default(),
+        post: false,
+        search: None,
+        search_id: 0,
+        events: sender,
+    };
+
+    while let Ok(event) = events.recv() {
+        match event {
+            Event::Line(line) => {
+                let Some(command) = XboardCommand::parse(&line) else {
+                    send(XboardOutput::Error {
+                        kind: "unknown command".to_string(),
+                        command: line,
+                    });
+                    continue;
+                };
+                if command == XboardCommand::Quit {
+                    break;
+                }
+                engine.command(command);
+            }
+            Event::SearchDone { id, result } if id == engine.search_id => {
+                engine.search_done(result);
+            }
+            Event::SearchDone { .. } => {}
+            Event::InputClosed => break,
+        }
+    }
+    stop_search(&mut engine.search);
+}
+
+impl XboardEngine {
+    fn command(&mut self, command: XboardCommand) {
+        match command {
+            XboardCommand::Protover(_) => send(XboardOutput::Feature(vec![
+                ("myname".to_string(), NAME.to_string()),
+                ("ping".to_string(), "1".to_string()),
+                ("setboard".to_string(), "1".to_string()),
+                ("usermove".to_string(), "1".to_string()),
+                ("san".to_string(), "0".to_string()),
+                ("sigint".to_string(), "0".to_string()),
+                ("sigterm".to_string(), "0".to_string()),
+                ("colors".to_string(), "0".to_string()),
+                ("analyze".to_string(), "0".to_string()),
+                ("done".to_string(), "1".to_string()),
+            ])),
+            XboardCommand::New => {
+                self.cancel_search();
+                self.board = Board::starting_position();
+                self.side = Some(Team::Black);
+                self.time_control.depth = None;
+            }
+            XboardCommand::Force | XboardCommand::Result { .. } => {
+                self.cancel_search();
+                self.side = None;
+            }
+            XboardCommand::Go => {
+                self.side = Some(self.board.side_to_move());
+                self.think();
+            }
+            XboardCommand::UserMove(text) => {
+                self.cancel_search();
+                let Some(mv) = self.board.read_move(&text) else {
+                    send(XboardOutput::IllegalMove(text));
+                    return;
+                };
+                self.board.make_move(mv);
+                self.think();
+            }
+            XboardCommand::Level {
+                moves,
+                base,
+                increment,
+            } => {
+                self.time_control.moves = moves;
+                self.time_control.increment = increment;
+                self.time_control.move_time = None;
+                self.time_control.time_left = Some(base);
+            }
+            XboardCommand::St(seconds) => {
+                self.time_control.move_time = Some(Duration::from_secs(seconds.into()));
+            }
+            XboardCommand::Sd(depth) => self.time_control.depth = Some(depth),
+            XboardCommand::Time(centiseconds) => {
+                self.time_control.time_left = Some(Duration::from_millis(centiseconds * 10));
+            }
+            XboardCommand::SetBoard(fen) => {
+                self.cancel_search();
+                match Board::from_fen(&fen) {
+                    Ok(board) => self.board = board,
+                    Err(error) => send(XboardOutput::Error {
+                        kind: error.to_string(),
+                        command: format!("setboard {}", fen),
+                    }),
+                }
+            }
+            XboardCommand::Ping(number) => send(XboardOutput::Pong(number)),
+            XboardCommand::MoveNow => {
+                if let Some(search) = &self.search {
+                    search.hurry();
+                }
+            }
+            XboardCommand::Undo | XboardCommand::Remove => {
+                self.cancel_search();
+                let plies = if command == XboardCommand::Undo { 1 } else { 2 };
+                for _ in 0..plies {
+                    self.board.unmake_move();
+                }
+            }
+            XboardCommand::Post => self.post = true,
+            XboardCommand::NoPost => self.post = false,
+            XboardCommand::Xboard
+            | XboardCommand::Accepted(_)
+            | XboardCommand::Rejected(_)
+            | XboardCommand::Otim(_)
+            | XboardCommand::Hard
+            | XboardCommand::Easy
+            | XboardCommand::Random
+            | XboardCommand::Computer
+            // draw offers are turned down by moving
+            | XboardCommand::Draw
+            | XboardCommand::Quit => {}
+        }
+    }
+
+    /// Starts a search if it is the engine's turn, or claims the result if
+    /// the game is over.
+    fn think(&mut self) {
+        if self.side != Some(self.board.side_to_move()) || self.search.is_some() {
+            return;
+        }
+        if let Some(outcome) = self.board.outcome(true) {
+            send(XboardOutput::Result {
+                result: outcome.result_token().to_string(),
+                comment: outcome.to_string(),
+            });
+            return;
+        }
+
+        self.search_id += 1;
+        let id = self.search_id;
+        let events = self.events.clone();
+        let post = self.post;
+        self.search = Some(BackgroundSearch::start(
+            self.board.clone(),
+            self.time_control.limits(&self.board),
+            false,
+            move |result, elapsed| {
+                if post {
+                    send(XboardOutput::Thinking(thinking(result, elapsed)));
+                }
+            },
+            move |result| {
+                let _ = events.send(Event::SearchDone { id, result });
+            },
+        ));
+    }
+
+    fn search_done(&mut self, result: SearchResult) {
+        stop_search(&mut self.search);
+        let Some(mv) = result.best_move else {
+            return;
+        };
+        self.board.make_move(mv);
+        send(XboardOutput::Move(mv.to_string()));
+        if let Some(outcome) = self.board.outcome(true) {
+            send(XboardOutput::Result {
+                result: outcome.result_token().to_string(),
+                comment: outcome.to_string(),
+            });
+        }
+    }
+
+    /// Stops the search and throws its move away.
+    fn cancel_search(&mut self) {
+        stop_search(&mut self.search);
+        self.search_id += 1;
+    }
+}
+
+fn thinking(result: &SearchResult, elapsed: Duration) -> Thinking {
+    let score = match Score::from_search(result.score) {
+        Score::Centipawns(centipawns) => centipawns,
+        Score::Mate(moves) => XBOARD_MATE * moves.signum() + moves,
+    };
+    Thinking {
+        depth: result.depth,
+        score,
+        time: elapsed.as_millis() as u64 / 10,
+        nodes: result.nodes,
+        pv: result.pv.iter().map(|mv| mv.to_string()).collect(),
+    }
+}
+
+fn send(output: XboardOutput) {
+    println!("{}", output);
+}
@@ -0,0 +1,125 @@
+//! A stand-in UCI engine for trying out the GUI's engine support without a
+//! real engine. It plays the moves given on its command line in order, falling
+//! back to its first legal move once the script runs out or a scripted move
+//! isn't legal.
+//!
+//! `--think MS` makes it wait that long before answering `go`, so `stop` can
+//! be tried out; stopping answers straight away.
+
+use chess_core::{Board, Move};
+use chess_protocol::uci::{EngineMessage, GuiCommand, Info, Score};
+use std::io::{self, BufRead};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let mut think = Duration::ZERO;
+    let mut script = Vec::new();
+    while let Some(arg) = args.next() {
+        if arg == "--think" {
+            let millis = args
+                .next()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0);
+            think = Duration::from_millis(millis);
+        } else {
+            script.push(arg);
+        }
+    }
+
+    let mut board = Board::starting_position();
+    // the move of the `go` being thought about; whoever answers first, the
+    // thinking thread or `stop`, takes it
+    let mut search: Option<Arc<Mutex<Option<Move>>>> = None;
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Some(command) = GuiCommand::parse(&line) else {
+            continue;
+        };
+        match command {
+            GuiCommand::Uci => {
+                send(EngineMessage::IdName("Stand-in".to_string()));
+                send(EngineMessage::IdAuthor("chess".to_string()));
+                send(EngineMessage::UciOk);
+            }
+            GuiCommand::IsReady => send(EngineMessage::ReadyOk),
+            GuiCommand::Position(position) => match position.to_board() {
+                Ok(position) => board = position,
+                Err(error) => send(info_string(&error.to_string())),
+            },
+            GuiCommand::Go(_) => {
+                finish(search.take());
+                let Some(mv) = choose_move(&board, &script) else {
+                    send(EngineMessage::BestMove {
+                        best: "0000".to_string(),
+                        ponder: None,
+                    });
+                    continue;
+                };
+                let best = Arc::new(Mutex::new(Some(mv)));
+                let thread_best = best.clone();
+                thread::spawn(move || {
+                    thread::sleep(think);
+                    answer(&thread_best);
+                });
+                search = Some(best);
+            }
+            GuiCommand::Stop => finish(search.take()),
+            GuiCommand::Quit => return,
+            GuiCommand::Debug(_)
+            | GuiCommand::SetOption { .. }
+            | GuiCommand::UciNewGame
+            | GuiCommand::PonderHit => {}
+        }
+    }
+    finish(search);
+}
+
+/// The next scripted move, counted by how many moves the side to move has
+/// already made in the game.
+fn choose_move(board: &Board, script: &[String]) -> Option<Move> {
+    let moves_made = board.moves().count();
+    let own_moves = moves_made / 2;
+    script
+        .get(own_moves)
+        .and_then(|text| board.parse_move(text))
+        .or_else(|| board.legal_moves().first().copied())
+}
+
+fn finish(search: Option<Arc<Mutex<Option<Move>>>>) {
+    if let Some(best) = search {
+        answer(&best);
+    }
+}
+
+/// Sends the move as `bestmove` unless it has already been sent.
+fn answer(best: &Mutex<Option<Move>>) {
+    if let Some(mv) = best.lock().unwrap().take() {
+        send(EngineMessage::Info(Info {
+            depth: Some(1),
+            score: Some(Score::Centipawns(0)),
+            nodes: Some(1),
+            pv: vec![mv.to_string()],
+            ..Info::default()
+        }));
+        send(EngineMessage::BestMove {
+            best: mv.to_string(),
+            ponder: None,
+        });
+    }
+}
+
+fn info_string(text: &str) -> EngineMessage {
+    EngineMessage::Info(Info {
+        string: Some(text.to_string()),
+        ..Info::default()
+    })
+}
+
+fn send(message: EngineMessage) {
+    println!("{}", message);
+}
@@ -0,0 +1,178 @@
+use crate::inbox::Inbox;
+use crate::net::NetMessage;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TryRecvError, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How many lines can wait to be written before the peer counts as not
+/// keeping up.
+const SEND_QUEUE: usize = 256;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a line can take to be written before the peer counts as gone.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// A connection to another copy of the game. Background threads read the
+/// messages it sends and write the ones sent to it, so neither sending nor
+/// receiving ever waits on the network. The connection is closed when the
+/// handle is dropped, once what was sent on it has been written.
+pub struct Connection {
+    stream: TcpStream,
+    outgoing: SyncSender<String>,
+    incoming: Inbox<NetMessage>,
+}
+
+impl Connection {
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Connection> {
+        let mut last_error = None;
+        for address in address.to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+                Ok(stream) => return Connection::new(stream),
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "the address did not resolve")
+        }))
+    }
+
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream.try_clone()?;
+
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                // lines that aren't messages are skipped, as a newer peer may send some
+                let Some(message) = NetMessage::parse(&line) else {
+                    continue;
+                };
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let (outgoing, lines) = mpsc::sync_channel::<String>(SEND_QUEUE);
+        thread::spawn(move || {
+            for line in lines {
+                if writeln!(writer, "{}", line).is_err() {
+                    break;
+                }
+            }
+            // the handle has been dropped, or the peer is gone; closing the
+            // connection ends the reading thread too
+            let _ = writer.shutdown(Shutdown::Both);
+        });
+
+        Ok(Connection {
+            stream,
+            outgoing,
+            incoming: Inbox::new(incoming),
+        })
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    /// Queues `message` to be sent. Fails when the connection has closed or
+    /// the peer has stopped reading and too much is waiting for it.
+    pub fn send(&self, message: &NetMessage) -> io::Result<()> {
+        match self.outgoing.try_send(message.to_string()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "the peer is not keeping up",
+            )),
+            Err(TrySendError::Disconnected(_)) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the connection is closed",
+            )),
+        }
+    }
+
+    /// The next message the peer has sent, if one has arrived. `Err` means the
+    /// connection has closed.
+    pub fn try_receive(&mut self) -> io::Result<Option<NetMessage>> {
+        match self.incoming.try_recv() {
+            Ok(message) => Ok(Some(message)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "the connection is closed",
+            )),
+        }
+    }
+}
+
+/// Accepts connections on a background thread until it is dropped.
+pub struct Listener {
+    address: SocketAddr,
+    stop: Arc<AtomicBool>,
+    connections: Inbox<Connection>,
+}
+
+impl Listener {
+    /// Listens on `port` on every interface.
+    pub fn bind(port: u16) -> io::Result<Listener> {
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let address = listener.local_addr()?;
+        // polled, so the thread notices when it should stop
+        listener.set_nonblocking(true)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
+        let (sender, connections) = mpsc::channel();
+        thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let connection = stream
+                            .set_nonblocking(false)
+                            .and_then(|()| Connection::new(stream));
+                        if let Ok(connection) = connection {
+                            if sender.send(connection).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_POLL);
+                    }
+                    Err(_) => thread::sleep(ACCEPT_POLL),
+                }
+            }
+        });
+
+        Ok(Listener {
+            address,
+            stop,
+            connections: Inbox::new(connections),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// A connection that has come in since the last call, if there is one.
+    pub fn try_accept(&mut self) -> Option<Connection> {
+        self.connections.try_recv().ok()
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,20 @@
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::Mutex;
+
+/// The receiving end of a channel filled by a background thread. A `Receiver`
+/// is not `Sync`, and the handles holding one are kept in Bevy resources,
+/// which must be; the mutex is never contended, as reading takes `&mut self`.
+pub(crate) struct Inbox<T>(Mutex<Receiver<T>>);
+
+impl<T> Inbox<T> {
+    pub(crate) fn new(receiver: Receiver<T>) -> Inbox<T> {
+        Inbox(Mutex::new(receiver))
+    }
+
+    pub(crate) fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.0
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .try_recv()
+    }
+}
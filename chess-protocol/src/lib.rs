@@ -0,0 +1,19 @@
+//! Talking to chess engines that run as separate programs: the messages of the
+//! Universal Chess Interface and of the XBoard protocol (CECP), and a handle on
+//! an engine process that exchanges them line by line. Also the protocol for
+//! playing another copy of the game over the network, its connections, and
+//! the host, guest and spectator at either end of one.
+
+mod connection;
+mod inbox;
+pub mod net;
+mod peer;
+mod position;
+mod process;
+pub mod uci;
+pub mod xboard;
+
+pub use connection::{Connection, Listener};
+pub use peer::{Peer, PeerEvent, Rematch};
+pub use position::{Position, PositionError};
+pub use process::EngineProcess;
@@ -0,0 +1,438 @@
+//! The protocol two copies of the game use to play each other over a network.
+//! One hosts, the other joins as its guest, and they exchange one message per
+//! line over TCP. Both open with `hello` and the protocol version, and a
+//! connection with a different version is closed.
+//!
+//! The host's copy of the game is the one that counts. It tells the guest which
+//! side to play and the whole game so far with `game`, right after the
+//! handshake and again whenever the guest asks with `resync`, which is how a
+//! guest that lost its connection or disagrees about a move catches up.
+//!
+//! The host takes one guest per game. It sends that guest a `session` token
+//! before the game, and a guest that lost its connection comes back with
+//! `rejoin` and the token instead of `hello`; anyone else saying `hello` once
+//! the game has its guest is turned away.
+//!
+//! Spectators open with `watch` instead of `hello`. The host answers them the
+//! same way, then sends them every move either player makes and how the game
+//! ended if neither the moves nor the clocks say, and ignores anything they
+//! send but `resync`.
+//!
+//! Once a game is over either player can offer a rematch with
+//! `rematch offer`, and the other takes it with `rematch accept` or by
+//! offering one too. The host then sends `game` again with the sides swapped,
+//! from the position the last game started from. Spectators are sent
+//! `rematch accept` and then the new game.
+
+use crate::Position;
+use chess_core::{Bonus, Stage, Team, TimeControl};
+use std::fmt;
+use std::time::Duration;
+
+pub const PROTOCOL_VERSION: u32 = 4;
+/// The port hosts listen on.
+pub const DEFAULT_PORT: u16 = 7878;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetMessage {
+    /// `hello VERSION NAME`, the first line each side sends.
+    Hello {
+        version: u32,
+        name: String,
+    },
+    /// `rejoin VERSION TOKEN NAME`, the first line a guest sends when it
+    /// comes back after losing its connection.
+    Rejoin {
+        version: u32,
+        token: u64,
+        name: String,
+    },
+    /// `session TOKEN`: the token the guest rejoins with, sent to it alone.
+    Session(u64),
+    /// `watch VERSION NAME`, the first line a spectator sends.
+    Watch {
+        version: u32,
+        name: String,
+    },
+    /// `game SIDE CONTROL [WHITE_MS BLACK_MS] DRAWS POSITION`: the guest
+    /// plays `side`, with the time left on both clocks when the game is timed.
+    /// `DRAWS` is `autodraw` when the fifty-move rule and threefold
+    /// repetition end the game on their own, or `claimdraw` when they must be
+    /// claimed.
+    Game {
+        side: Team,
+        time_control: Option<TimeControl>,
+        clocks: Option<(Duration, Duration)>,
+        automatic_draw_claims: bool,
+        position: Position,
+    },
+    /// `move MOVE [MS]`: a move in long algebraic notation, with the time the
+    /// mover had left after making it.
+    Move {
+        mv: String,
+        clock: Option<Duration>,
+    },
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    /// Claims a draw by the fifty-move rule or threefold repetition.
+    ClaimDraw,
+    Resign,
+    /// `resigned SIDE`: tells spectators that `side` resigned.
+    Resigned(Team),
+    /// Asks the host for the whole game again.
+    Resync,
+    /// Offers to play again, with the sides swapped, once the game is over.
+    OfferRematch,
+    AcceptRematch,
+    Error(String),
+}
+
+impl NetMessage {
+    /// Reads one line. Unknown and malformed messages give `None`.
+    pub fn parse(line: &str) -> Option<NetMessage> {
+        let mut words = line.split_whitespace();
+        let message = match words.next()? {
+            "hello" => NetMessage::Hello {
+                version: words.next()?.parse().ok()?,
+                name: words.collect::<Vec<_>>().join(" "),
+            },
+            "rejoin" => NetMessage::Rejoin {
+                version: words.next()?.parse().ok()?,
+                token: parse_token(words.next()?)?,
+                name: words.collect::<Vec<_>>().join(" "),
+            },
+            "session" => NetMessage::Session(parse_token(words.next()?)?),
+            "watch" => NetMessage::Watch {
+                version: words.next()?.parse().ok()?,
+                name: words.collect::<Vec<_>>().join(" "),
+            },
+            "game" => {
+                let side = parse_team(words.next()?)?;
+                let time_control = match words.next()? {
+                    "untimed" => None,
+                    text => Some(parse_time_control(text)?),
+                };
+                let clocks = match time_control {
+                    Some(_) => Some((parse_millis(words.next()?)?, parse_millis(words.next()?)?)),
+                    None => None,
+                };
+                let automatic_draw_claims = match words.next()? {
+                    "autodraw" => true,
+                    "claimdraw" => false,
+                    _ => return None,
+                };
+                NetMessage::Game {
+                    side,
+                    time_control,
+                    clocks,
+                    automatic_draw_claims,
+                    position: Position::parse_words(words)?,
+                }
+            }
+            "move" => NetMessage::Move {
+                mv: words.next()?.to_string(),
+                clock: match words.next() {
+                    Some(millis) => Some(parse_millis(millis)?),
+                    None => None,
+                },
+            },
+            "draw" => match words.next()? {
+                "offer" => NetMessage::OfferDraw,
+                "accept" => NetMessage::AcceptDraw,
+                "decline" => NetMessage::DeclineDraw,
+                "claim" => NetMessage::ClaimDraw,
+                _ => return None,
+            },
+            "resign" => NetMessage::Resign,
+            "resigned" => NetMessage::Resigned(parse_team(words.next()?)?),
+            "resync" => NetMessage::Resync,
+            "rematch" => match words.next()? {
+                "offer" => NetMessage::OfferRematch,
+                "accept" => NetMessage::AcceptRematch,
+                _ => return None,
+            },
+            "error" => NetMessage::Error(words.collect::<Vec<_>>().join(" ")),
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+impl fmt::Display for NetMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetMessage::Hello { version, name } => write!(f, "hello {} {}", version, name),
+            NetMessage::Rejoin {
+                version,
+                token,
+                name,
+            } => write!(f, "rejoin {} {:016x} {}", version, token, name),
+            NetMessage::Session(token) => write!(f, "session {:016x}", token),
+            NetMessage::Watch { version, name } => write!(f, "watch {} {}", version, name),
+            NetMessage::Game {
+                side,
+                time_control,
+                clocks,
+                automatic_draw_claims,
+                position,
+            } => {
+                write!(f, "game {}", team_name(*side))?;
+                match (time_control, clocks) {
+                    (Some(time_control), Some((white, black))) => write!(
+                        f,
+                        " {} {} {}",
+                        TimeControlText(time_control),
+                        white.as_millis(),
+                        black.as_millis()
+                    )?,
+                    _ => write!(f, " untimed")?,
+                }
+                let draws = if *automatic_draw_claims {
+                    "autodraw"
+                } else {
+                    "claimdraw"
+                };
+                write!(f, " {} {}", draws, position)
+            }
+            NetMessage::Move { mv, clock } => {
+                write!(f, "move {}", mv)?;
+                if let Some(clock) = clock {
+                    write!(f, " {}", clock.as_millis())?;
+                }
+                Ok(())
+            }
+            NetMessage::OfferDraw => write!(f, "draw offer"),
+            NetMessage::AcceptDraw => write!(f, "draw accept"),
+            NetMessage::DeclineDraw => write!(f, "draw decline"),
+            NetMessage::ClaimDraw => write!(f, "draw claim"),
+            NetMessage::Resign => write!(f, "resign"),
+            NetMessage::Resigned(side) => write!(f, "resigned {}", team_name(*side)),
+            NetMessage::Resync => write!(f, "resync"),
+            NetMessage::OfferRematch => write!(f, "rematch offer"),
+            NetMessage::AcceptRematch => write!(f, "rematch accept"),
+            NetMessage::Error(text) => write!(f, "error {}", text),
+        }
+    }
+}
+
+fn team_name(team: Team) -> &'static str {
+    match team {
+        Team::White => "white",
+        Team::Black => "black",
+    }
+}
+
+fn parse_team(word: &str) -> Option<Team> {
+    match word {
+        "white" => Some(Team::White),
+        "black" => Some(Team::Black),
+        _ => None,
+    }
+}
+
+fn parse_token(word: &str) -> Option<u64> {
+    u64::from_str_radix(word, 16).ok()
+}
+
+fn parse_millis(word: &str) -> Option<Duration> {
+    word.parse().ok().map(Duration::from_millis)
+}
+
+/// A time control as one word, in seconds: the stages as in PGN's
+/// `TimeControl` tag, "40/5400:1800", then "+" and the increment, "b" and a
+/// Bronstein delay, or "d" and a US delay.
+struct TimeControlText<'a>(&'a TimeControl);
+
+impl fmt::Display for TimeControlText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, stage) in self.0.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, ":")?;
+            }
+            if let Some(moves) = stage.moves {
+                write!(f, "{}/", moves)?;
+            }
+            write!(f, "{}", stage.time.as_secs())?;
+        }
+        match self.0.bonus {
+            Bonus::None => Ok(()),
+            Bonus::Fischer(increment) => write!(f, "+{}", increment.as_secs()),
+            Bonus::Bronstein(delay) => write!(f, "b{}", delay.as_secs()),
+            Bonus::UsDelay(delay) => write!(f, "d{}", delay.as_secs()),
+        }
+    }
+}
+
+fn parse_time_control(word: &str) -> Option<TimeControl> {
+    let (stages, bonus) = match word.find(['+', 'b', 'd']) {
+        Some(index) => {
+            let seconds = Duration::from_secs(word[index + 1..].parse().ok()?);
+            let bonus = match &word[index..=index] {
+                "+" => Bonus::Fischer(seconds),
+                "b" => Bonus::Bronstein(seconds),
+                _ => Bonus::UsDelay(seconds),
+            };
+            (&word[..index], bonus)
+        }
+        None => (word, Bonus::None),
+    };
+    let stages = stages
+        .split(':')
+        .map(|stage| {
+            let (moves, time) = match stage.split_once('/') {
+                Some((moves, time)) => (Some(moves.parse().ok()?), time),
+                None => (None, stage),
+            };
+            Some(Stage {
+                moves,
+                time: Duration::from_secs(time.parse().ok()?),
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(TimeControl { stages, bonus })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(message: NetMessage, line: &str) {
+        assert_eq!(message.to_string(), line);
+        assert_eq!(NetMessage::parse(line), Some(message));
+    }
+
+    #[test]
+    fn handshake_messages_round_trip() {
+        round_trip(
+            NetMessage::Hello {
+                version: 2,
+                name: "Ada Lovelace".to_string(),
+            },
+            "hello 2 Ada Lovelace",
+        );
+        round_trip(
+            NetMessage::Rejoin {
+                version: 2,
+                token: 0xfeed,
+                name: "Ada".to_string(),
+            },
+            "rejoin 2 000000000000feed Ada",
+        );
+        round_trip(NetMessage::Session(u64::MAX), "session ffffffffffffffff");
+        round_trip(
+            NetMessage::Watch {
+                version: 2,
+                name: "Bob".to_string(),
+            },
+            "watch 2 Bob",
+        );
+        round_trip(
+            NetMessage::Error("protocol version 9 is not supported".to_string()),
+            "error protocol version 9 is not supported",
+        );
+    }
+
+    #[test]
+    fn game_messages_round_trip() {
+        round_trip(
+            NetMessage::Game {
+                side: Team::Black,
+                time_control: None,
+                clocks: None,
+                automatic_draw_claims: true,
+                position: Position::default(),
+            },
+            "game black untimed autodraw startpos",
+        );
+
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+        let stages = vec![
+            Stage {
+                moves: Some(40),
+                time: Duration::from_secs(5400),
+            },
+            Stage {
+                moves: None,
+                time: Duration::from_secs(1800),
+            },
+        ];
+        for (bonus, control) in [
+            (Bonus::None, "40/5400:1800"),
+            (Bonus::Fischer(Duration::from_secs(30)), "40/5400:1800+30"),
+            (Bonus::Bronstein(Duration::from_secs(5)), "40/5400:1800b5"),
+            (Bonus::UsDelay(Duration::from_secs(5)), "40/5400:1800d5"),
+        ] {
+            round_trip(
+                NetMessage::Game {
+                    side: Team::White,
+                    time_control: Some(TimeControl {
+                        stages: stages.clone(),
+                        bonus,
+                    }),
+                    clocks: Some((Duration::from_millis(5_399_250), Duration::from_secs(5400))),
+                    automatic_draw_claims: false,
+                    position: Position {
+                        fen: Some(fen.to_string()),
+                        moves: vec!["e2e4".to_string(), "e8d7".to_string()],
+                    },
+                },
+                &format!(
+                    "game white {} 5399250 5400000 claimdraw fen {} moves e2e4 e8d7",
+                    control, fen
+                ),
+            );
+        }
+    }
+
+    #[test]
+    fn play_messages_round_trip() {
+        round_trip(
+            NetMessage::Move {
+                mv: "e7e8q".to_string(),
+                clock: Some(Duration::from_millis(61_500)),
+            },
+            "move e7e8q 61500",
+        );
+        round_trip(
+            NetMessage::Move {
+                mv: "e2e4".to_string(),
+                clock: None,
+            },
+            "move e2e4",
+        );
+        round_trip(NetMessage::OfferDraw, "draw offer");
+        round_trip(NetMessage::AcceptDraw, "draw accept");
+        round_trip(NetMessage::DeclineDraw, "draw decline");
+        round_trip(NetMessage::ClaimDraw, "draw claim");
+        round_trip(NetMessage::Resign, "resign");
+        round_trip(NetMessage::Resigned(Team::White), "resigned white");
+        round_trip(NetMessage::Resync, "resync");
+        round_trip(NetMessage::OfferRematch, "rematch offer");
+        round_trip(NetMessage::AcceptRematch, "rematch accept");
+    }
+
+    #[test]
+    fn unknown_and_malformed_lines_are_skipped() {
+        for line in [
+            "",
+            "frobnicate",
+            "hello two Ada",
+            "rejoin 2 nothex Ada",
+            "session",
+            "game purple untimed startpos",
+            "game white 300 startpos",
+            "game white untimed",
+            "game white untimed startpos",
+            "game white untimed nodraw startpos",
+            "move",
+            "move e2e4 soon",
+            "draw maybe",
+            "rematch",
+            "rematch decline",
+            "resigned nobody",
+        ] {
+            assert_eq!(NetMessage::parse(line), None, "{:?}", line);
+        }
+    }
+}
@@ -0,0 +1,746 @@
+use crate::inbox::Inbox;
+use crate::net::{NetMessage, DEFAULT_PORT, PROTOCOL_VERSION};
+use crate::{Connection, Listener, Position};
+use chess_core::{Board, Clock, Move, Team, TimeControl};
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::io;
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(2);
+/// How long a new connection to the host has to say who it is.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Role {
+    /// Listens for the guest, and for it again when it loses its connection,
+    /// and for spectators.
+    Host(Listener),
+    /// Connects to the host at `address`, and again when the connection is
+    /// lost. A guest that is `watching` is a spectator.
+    Guest { address: String, watching: bool },
+}
+
+/// Someone watching the host's game.
+struct Spectator {
+    connection: Connection,
+    name: String,
+    /// How many moves of the game they have been sent.
+    plies: usize,
+}
+
+/// Where the players are in agreeing to play again once a game is over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rematch {
+    #[default]
+    None,
+    /// This side has offered one.
+    Offered,
+    /// The other player has offered one.
+    Asked,
+    /// Both want one, and the guest or spectator waits for the host to send
+    /// the game.
+    Agreed,
+}
+
+/// What a [`Peer`] has for the game it is part of.
+#[derive(Debug, Clone)]
+pub enum PeerEvent {
+    /// A move by the other player, or by either to a spectator, which is
+    /// legal in the game as it stands, with the time the mover had left.
+    Move { mv: Move, clock: Option<Duration> },
+    /// The whole game from the host, for the guest to replace its own with,
+    /// and the time left on both clocks when it is timed.
+    Game {
+        board: Board,
+        clocks: Option<(Duration, Duration)>,
+    },
+    /// The rematch both players agreed to has started from `board`, with the
+    /// sides swapped and the clocks, when it is timed, showing `clocks`.
+    Rematch {
+        board: Board,
+        clocks: Option<(Duration, Duration)>,
+    },
+    /// Any other message from the other player: a draw offer, an answer to
+    /// one, a claim, a resignation, a rematch offer or an error.
+    Message(NetMessage),
+    /// Who connected, who left and who was turned away.
+    Notice(String),
+    /// Something the other player sent or did that went wrong.
+    Warning(String),
+}
+
+/// One end of a game against another copy of the game over the network: the
+/// host, its guest, or a spectator. The host's copy of the game is the one
+/// that counts. A guest that falls out of step with it, by sending a move
+/// that isn't legal there, is sent the whole game again; a move from the host
+/// that isn't legal on the guest's side has the guest ask for it.
+///
+/// Nothing here waits on the network. The game is polled for what has
+/// arrived and told about the moves made on this side.
+pub struct Peer {
+    role: Role,
+    name: String,
+    /// The connection to the other player, once past the handshake.
+    connection: Option<Connection>,
+    /// The guest's connection while it is in the handshake.
+    handshake: Option<Connection>,
+    /// Connections to the host that haven't said who they are yet, and when
+    /// they were made.
+    pending: Vec<(Connection, Instant)>,
+    /// The token the guest rejoins with, once the host has taken one in.
+    session: Option<u64>,
+    spectators: Vec<Spectator>,
+    /// The guest's attempt to connect, which runs in the background.
+    connecting: Option<Inbox<io::Result<Connection>>>,
+    /// When the guest next tries to connect again after losing the connection.
+    retry_at: Option<Instant>,
+    local_side: Team,
+    time_control: Option<TimeControl>,
+    automatic_draw_claims: bool,
+    opponent_name: String,
+    /// How many moves of the game both sides know about.
+    plies: usize,
+    rematch: Rematch,
+    /// Notices and warnings not yet polled.
+    events: VecDeque<PeerEvent>,
+    ready: bool,
+    status: String,
+}
+
+impl Peer {
+    /// Starts listening on `port` for a guest to play the other side from
+    /// `local_side`, by the draw rules the host plays by. `name` is shown to
+    /// the other player.
+    pub fn host(
+        port: u16,
+        name: &str,
+        local_side: Team,
+        time_control: Option<TimeControl>,
+        automatic_draw_claims: bool,
+    ) -> io::Result<Peer> {
+        let listener = Listener::bind(port)?;
+        let status = format!(
+            "Waiting for an opponent on {}:{}",
+            local_address().unwrap_or_else(|| "port".to_string()),
+            listener.local_addr().port()
+        );
+        let mut peer = Peer::new(Role::Host(listener), name, local_side, status);
+        peer.time_control = time_control;
+        peer.automatic_draw_claims = automatic_draw_claims;
+        Ok(peer)
+    }
+
+    /// Starts connecting to the host at `address`, given as "host" or
+    /// "host:port". The host says which side the guest plays.
+    pub fn join(address: &str, name: &str) -> Peer {
+        Peer::connect_to(address, name, false)
+    }
+
+    /// Starts connecting to the host at `address` to watch its game.
+    pub fn watch(address: &str, name: &str) -> Peer {
+        Peer::connect_to(address, name, true)
+    }
+
+    fn connect_to(address: &str, name: &str, watching: bool) -> Peer {
+        let address = match address.trim() {
+            address if address.contains(':') => address.to_string(),
+            address => format!("{}:{}", address, DEFAULT_PORT),
+        };
+        let status = format!("Connecting to {}", address);
+        let role = Role::Guest { address, watching };
+        let mut peer = Peer::new(role, name, Team::Black, status);
+        peer.connect();
+        peer
+    }
+
+    fn new(role: Role, name: &str, local_side: Team, status: String) -> Peer {
+        Peer {
+            role,
+            name: name.to_string(),
+            connection: None,
+            handshake: None,
+            pending: Vec::new(),
+            session: None,
+            spectators: Vec::new(),
+            connecting: None,
+            retry_at: None,
+            local_side,
+            time_control: None,
+            automatic_draw_claims: false,
+            opponent_name: "?".to_string(),
+            plies: 0,
+            rematch: Rematch::None,
+            events: VecDeque::new(),
+            ready: false,
+            status,
+        }
+    }
+
+    /// Whether the other player has been found and the game can start.
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+
+    /// How finding the other player, or the connection to them, is going.
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host(_))
+    }
+
+    pub fn is_watching(&self) -> bool {
+        matches!(self.role, Role::Guest { watching: true, .. })
+    }
+
+    /// The side played here. The guest learns it from the host.
+    pub fn local_side(&self) -> Team {
+        self.local_side
+    }
+
+    pub fn time_control(&self) -> Option<&TimeControl> {
+        self.time_control.as_ref()
+    }
+
+    /// Whether the fifty-move rule and threefold repetition end the game on
+    /// their own, as the host has it.
+    pub fn automatic_draw_claims(&self) -> bool {
+        self.automatic_draw_claims
+    }
+
+    pub fn opponent_name(&self) -> &str {
+        &self.opponent_name
+    }
+
+    pub fn rematch(&self) -> Rematch {
+        self.rematch
+    }
+
+    pub fn spectators(&self) -> usize {
+        self.spectators.len()
+    }
+
+    /// The port the host listens on.
+    pub fn port(&self) -> Option<u16> {
+        match &self.role {
+            Role::Host(listener) => Some(listener.local_addr().port()),
+            Role::Guest { .. } => None,
+        }
+    }
+
+    /// Sends `message` to the other player, once there is one.
+    pub fn send(&mut self, message: NetMessage) {
+        if let Some(connection) = &self.connection {
+            if let Err(error) = connection.send(&message) {
+                self.events.push_back(PeerEvent::Warning(format!(
+                    "Could not send '{}': {}",
+                    message, error
+                )));
+            }
+        }
+    }
+
+    /// Sends `message` to every spectator.
+    pub fn tell_spectators(&mut self, message: NetMessage) {
+        for spectator in &self.spectators {
+            let _ = spectator.connection.send(&message);
+        }
+    }
+
+    /// Offers the other player a rematch of the game that ended on `board`,
+    /// or accepts theirs. Once both want one the host starts it.
+    pub fn offer_rematch(&mut self, board: &Board) {
+        match self.rematch {
+            Rematch::None => {
+                self.send(NetMessage::OfferRematch);
+                self.rematch = Rematch::Offered;
+            }
+            Rematch::Asked => {
+                self.send(NetMessage::AcceptRematch);
+                self.agree_to_rematch(board);
+            }
+            Rematch::Offered | Rematch::Agreed => {}
+        }
+    }
+
+    /// The next thing to come in for the game being played on `board`, once
+    /// the connection has been looked after: the host takes in whoever has
+    /// connected, and the guest connects to the host, again if the connection
+    /// has been lost. Moves are checked against `board`, so a move returned
+    /// must be made on it before polling again.
+    pub fn poll(&mut self, board: &Board, clock: Option<&Clock>) -> Option<PeerEvent> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(event);
+            }
+            let message = match self.role {
+                Role::Host(_) => {
+                    self.accept_connections(board, clock);
+                    None
+                }
+                Role::Guest { .. } => self.join_host(),
+            };
+            if let Some(event) = self.events.pop_front() {
+                return Some(event);
+            }
+            let message = match message {
+                Some(message) => message,
+                None => match self.connection.as_mut()?.try_receive() {
+                    Ok(Some(message)) => message,
+                    Ok(None) => return None,
+                    Err(_) => {
+                        self.connection_lost();
+                        continue;
+                    }
+                },
+            };
+            if let Some(event) = self.read(message, board, clock) {
+                return Some(event);
+            }
+        }
+    }
+
+    /// Sends the other player the moves made on `board` since they were last
+    /// sent, with the time `clock` shows for this side. Moves made while the
+    /// connection is down wait for it to come back; a guest is sent the whole
+    /// game then instead. The host also keeps its spectators up to date.
+    pub fn send_moves(&mut self, board: &Board, clock: Option<&Clock>) {
+        if self.is_host() {
+            self.serve_spectators(board, clock);
+        }
+
+        let plies = board.moves().count();
+        if plies <= self.plies || self.connection.is_none() {
+            return;
+        }
+        let remaining = clock.map(|clock| clock.remaining(self.local_side));
+        for mv in board.moves().skip(self.plies) {
+            let message = NetMessage::Move {
+                mv: mv.to_string(),
+                clock: remaining,
+            };
+            self.send(message);
+        }
+        self.plies = plies;
+    }
+
+    /// What `message` from the other player means for the game on `board`.
+    /// Messages that are only about the connection are dealt with here.
+    fn read(
+        &mut self,
+        message: NetMessage,
+        board: &Board,
+        clock: Option<&Clock>,
+    ) -> Option<PeerEvent> {
+        match message {
+            NetMessage::Move {
+                mv,
+                clock: remaining,
+            } => {
+                let mover = board.side_to_move();
+                let in_step = (self.is_watching() || mover == self.local_side.opponent())
+                    && board.moves().count() == self.plies;
+                let Some(mv) = board.parse_move(&mv).filter(|_| in_step) else {
+                    let resync = match self.role {
+                        Role::Host(_) => self.game_message(board, clock),
+                        Role::Guest { .. } => NetMessage::Resync,
+                    };
+                    self.send(resync);
+                    return Some(PeerEvent::Warning(format!(
+                        "{} sent '{}', which isn't a legal move here",
+                        self.opponent_name, mv
+                    )));
+                };
+                self.plies += 1;
+                Some(PeerEvent::Move {
+                    mv,
+                    clock: remaining,
+                })
+            }
+            NetMessage::Game { .. } if self.is_host() => {
+                // the host's game is the one that counts, so a guest can't replace it
+                self.send(NetMessage::Error(
+                    "only the host sends the game".to_string(),
+                ));
+                Some(PeerEvent::Warning(format!(
+                    "{} sent a game to the host",
+                    self.opponent_name
+                )))
+            }
+            NetMessage::Game {
+                side,
+                time_control,
+                clocks,
+                automatic_draw_claims,
+                position,
+            } => match position.to_board() {
+                Ok(board) => {
+                    self.local_side = side;
+                    self.time_control = time_control;
+                    self.automatic_draw_claims = automatic_draw_claims;
+                    self.plies = board.moves().count();
+                    self.ready = true;
+                    if self.rematch == Rematch::Agreed {
+                        self.rematch = Rematch::None;
+                        return Some(PeerEvent::Rematch { board, clocks });
+                    }
+                    Some(PeerEvent::Game { board, clocks })
+                }
+                Err(error) => {
+                    self.send(NetMessage::Error(format!("bad game: {}", error)));
+                    Some(PeerEvent::Warning(format!(
+                        "The host sent a game that can't be played: {}",
+                        error
+                    )))
+                }
+            },
+            NetMessage::OfferRematch if !self.is_watching() => match self.rematch {
+                // both offered at once, which is as good as accepting
+                Rematch::Offered => {
+                    self.agree_to_rematch(board);
+                    None
+                }
+                Rematch::None | Rematch::Asked => {
+                    self.rematch = Rematch::Asked;
+                    Some(PeerEvent::Message(NetMessage::OfferRematch))
+                }
+                Rematch::Agreed => None,
+            },
+            NetMessage::AcceptRematch => {
+                if self.rematch == Rematch::Offered || self.is_watching() {
+                    self.agree_to_rematch(board);
+                }
+                None
+            }
+            NetMessage::Resync => {
+                if self.is_host() {
+                    let game = self.game_message(board, clock);
+                    self.send(game);
+                }
+                None
+            }
+            NetMessage::Hello { .. }
+            | NetMessage::OfferRematch
+            | NetMessage::Rejoin { .. }
+            | NetMessage::Watch { .. }
+            | NetMessage::Session(_) => None,
+            message => Some(PeerEvent::Message(message)),
+        }
+    }
+
+    /// The host starts the rematch from where the game on `board` started,
+    /// playing the other side, and sends it to the guest and the spectators.
+    /// The guest and spectators wait for it.
+    fn agree_to_rematch(&mut self, board: &Board) {
+        if !self.is_host() {
+            self.rematch = Rematch::Agreed;
+            return;
+        }
+        let mut start = board.clone();
+        while start.unmake_move().is_some() {}
+        self.local_side = self.local_side.opponent();
+        self.plies = 0;
+        self.rematch = Rematch::None;
+        let game = self.game_message(&start, None);
+        self.send(game.clone());
+        self.tell_spectators(NetMessage::AcceptRematch);
+        self.tell_spectators(game);
+        for spectator in &mut self.spectators {
+            spectator.plies = 0;
+        }
+        let clocks = self
+            .time_control
+            .as_ref()
+            .map(|time_control| (time_control.starting_time(), time_control.starting_time()));
+        self.events.push_back(PeerEvent::Rematch {
+            board: start,
+            clocks,
+        });
+    }
+
+    /// The whole game as the host has it, for the guest and spectators.
+    fn game_message(&self, board: &Board, clock: Option<&Clock>) -> NetMessage {
+        let clocks = self.time_control.as_ref().map(|time_control| match clock {
+            Some(clock) => (clock.remaining(Team::White), clock.remaining(Team::Black)),
+            None => (time_control.starting_time(), time_control.starting_time()),
+        });
+        NetMessage::Game {
+            side: self.local_side.opponent(),
+            time_control: self.time_control.clone(),
+            clocks,
+            automatic_draw_claims: self.automatic_draw_claims,
+            position: Position::from_board(board),
+        }
+    }
+
+    /// Takes in whoever has connected and said who they are: the guest, or
+    /// the guest again if it lost its connection, or a spectator. Both are
+    /// sent the game. Once there is a guest, only one with its session token
+    /// is taken in as the guest; connections that say anything else, or
+    /// nothing for too long, are dropped.
+    fn accept_connections(&mut self, board: &Board, clock: Option<&Clock>) {
+        let Role::Host(listener) = &mut self.role else {
+            return;
+        };
+        while let Some(connection) = listener.try_accept() {
+            self.pending.push((connection, Instant::now()));
+        }
+        for (mut connection, connected_at) in std::mem::take(&mut self.pending) {
+            let (version, name, token, watching) = match connection.try_receive() {
+                Ok(Some(NetMessage::Hello { version, name })) => (version, name, None, false),
+                Ok(Some(NetMessage::Rejoin {
+                    version,
+                    token,
+                    name,
+                })) => (version, name, Some(token), false),
+                Ok(Some(NetMessage::Watch { version, name })) => (version, name, None, true),
+                Ok(None) if connected_at.elapsed() < HANDSHAKE_TIMEOUT => {
+                    self.pending.push((connection, connected_at));
+                    continue;
+                }
+                _ => continue,
+            };
+            if version != PROTOCOL_VERSION {
+                let _ = connection.send(&NetMessage::Error(format!(
+                    "protocol version {} is not supported",
+                    version
+                )));
+                continue;
+            }
+            if !watching && token != self.session {
+                let refusal = match token {
+                    None => "a game is already being played",
+                    Some(_) => "that is not the guest of this game",
+                };
+                self.events.push_back(PeerEvent::Notice(format!(
+                    "Turned away {}: {}",
+                    name, refusal
+                )));
+                let _ = connection.send(&NetMessage::Error(refusal.to_string()));
+                continue;
+            }
+            let _ = connection.send(&NetMessage::Hello {
+                version: PROTOCOL_VERSION,
+                name: self.name.clone(),
+            });
+            if watching {
+                let _ = connection.send(&self.game_message(board, clock));
+                self.events
+                    .push_back(PeerEvent::Notice(format!("{} is watching", name)));
+                self.spectators.push(Spectator {
+                    connection,
+                    name,
+                    plies: board.moves().count(),
+                });
+                continue;
+            }
+            let session = *self.session.get_or_insert_with(session_token);
+            let _ = connection.send(&NetMessage::Session(session));
+            let _ = connection.send(&self.game_message(board, clock));
+            // with the token it is the same guest, so it replaces a connection
+            // that is still open: a connection lost without being closed can
+            // look open here for a long time
+            self.connection = Some(connection);
+            self.opponent_name = name;
+            self.plies = board.moves().count();
+            self.ready = true;
+            self.status = format!("Playing {}", self.opponent_name);
+        }
+    }
+
+    /// Sends each spectator the moves they haven't seen, with the time the
+    /// mover had left, and the whole game again when they ask for it.
+    /// Spectators that have gone, or can't keep up, are dropped rather than
+    /// waited for.
+    fn serve_spectators(&mut self, board: &Board, clock: Option<&Clock>) {
+        let plies = board.moves().count();
+        let game = self.game_message(board, clock);
+        let side_to_move = board.side_to_move();
+        let events = &mut self.events;
+        self.spectators.retain_mut(|spectator| {
+            let mut resync = false;
+            loop {
+                match spectator.connection.try_receive() {
+                    Ok(Some(NetMessage::Resync)) => resync = true,
+                    Ok(Some(_)) => {}
+                    Ok(None) => break,
+                    Err(_) => {
+                        events.push_back(PeerEvent::Notice(format!(
+                            "{} stopped watching",
+                            spectator.name
+                        )));
+                        return false;
+                    }
+                }
+            }
+            let sent = if resync || spectator.plies > plies {
+                spectator.connection.send(&game)
+            } else {
+                board
+                    .moves()
+                    .enumerate()
+                    .skip(spectator.plies)
+                    .try_for_each(|(ply, mv)| {
+                        // the last move was the other side's, the one before it this side's
+                        let mover = if (plies - ply) % 2 == 1 {
+                            side_to_move.opponent()
+                        } else {
+                            side_to_move
+                        };
+                        spectator.connection.send(&NetMessage::Move {
+                            mv: mv.to_string(),
+                            clock: clock.map(|clock| clock.remaining(mover)),
+                        })
+                    })
+            };
+            spectator.plies = plies;
+            match sent {
+                Ok(()) => true,
+                Err(error) => {
+                    events.push_back(PeerEvent::Notice(format!(
+                        "Dropped {}, who was watching: {}",
+                        spectator.name, error
+                    )));
+                    false
+                }
+            }
+        });
+    }
+
+    /// Starts connecting to the host in the background.
+    fn connect(&mut self) {
+        let Role::Guest { address, .. } = &self.role else {
+            return;
+        };
+        let address = address.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(Connection::connect(address));
+        });
+        self.connecting = Some(Inbox::new(receiver));
+        self.retry_at = None;
+    }
+
+    /// Follows the guest's attempt to connect, and says hello once it has.
+    /// Returns the game the host sends back.
+    fn join_host(&mut self) -> Option<NetMessage> {
+        if self
+            .retry_at
+            .is_some_and(|retry_at| Instant::now() >= retry_at)
+        {
+            self.connect();
+        }
+        if let Some(connecting) = &mut self.connecting {
+            match connecting.try_recv() {
+                Ok(Ok(connection)) => {
+                    let (version, name) = (PROTOCOL_VERSION, self.name.clone());
+                    let _ = connection.send(&match self.session {
+                        _ if self.is_watching() => NetMessage::Watch { version, name },
+                        Some(token) => NetMessage::Rejoin {
+                            version,
+                            token,
+                            name,
+                        },
+                        None => NetMessage::Hello { version, name },
+                    });
+                    self.handshake = Some(connection);
+                    self.connecting = None;
+                    self.status = "Waiting for the host".to_string();
+                }
+                Ok(Err(error)) => {
+                    self.connecting = None;
+                    self.connection_failed(format!("Could not connect: {}", error));
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.connecting = None,
+            }
+        }
+
+        let handshake = self.handshake.as_mut()?;
+        match handshake.try_receive() {
+            Ok(Some(NetMessage::Hello { version, name })) => {
+                if version != PROTOCOL_VERSION {
+                    self.handshake = None;
+                    self.status = format!("The host uses protocol version {}", version);
+                } else {
+                    self.opponent_name = name;
+                }
+                None
+            }
+            Ok(Some(NetMessage::Session(token))) => {
+                self.session = Some(token);
+                None
+            }
+            Ok(Some(game @ NetMessage::Game { .. })) => {
+                self.connection = self.handshake.take();
+                self.status = if self.is_watching() {
+                    format!("Watching {}'s game", self.opponent_name)
+                } else {
+                    format!("Playing {}", self.opponent_name)
+                };
+                Some(game)
+            }
+            Ok(Some(NetMessage::Error(error))) => {
+                self.handshake = None;
+                self.status = format!("The host refused: {}", error);
+                None
+            }
+            Ok(_) => None,
+            Err(_) => {
+                self.handshake = None;
+                self.connection_failed("The host closed the connection".to_string());
+                None
+            }
+        }
+    }
+
+    /// Before the game the guest gives up; during it, it tries again.
+    fn connection_failed(&mut self, status: String) {
+        if self.ready {
+            self.retry_at = Some(Instant::now() + RECONNECT_INTERVAL);
+            self.status = format!("{}, trying again", status);
+        } else {
+            self.status = status;
+        }
+    }
+
+    fn connection_lost(&mut self) {
+        self.connection = None;
+        match self.role {
+            Role::Host(_) => {
+                self.status = format!("Lost {}, waiting for them to reconnect", self.opponent_name);
+            }
+            Role::Guest { .. } => {
+                self.connection_failed("Lost the connection to the host".to_string())
+            }
+        }
+        self.events
+            .push_back(PeerEvent::Notice(self.status.clone()));
+    }
+}
+
+/// A token no one else on the network can guess, to tell the guest apart
+/// from anyone else who connects. `RandomState` is seeded randomly for each
+/// process.
+fn session_token() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    hasher.finish()
+}
+
+/// The address other machines on the network can reach this one on. Nothing
+/// is sent; connecting a UDP socket only picks the interface a packet would go
+/// out on.
+fn local_address() -> Option<String> {
+    let socket = std::net::UdpSocket::bind(("0.0.0.0", 0)).ok()?;
+    socket.connect(("192.0.2.1", 9)).ok()?;
+    Some(socket.local_addr().ok()?.ip().to_string())
+}
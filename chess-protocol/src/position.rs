@@ -0,0 +1,90 @@
+use chess_core::{Board, FenError, STARTING_FEN};
+use std::fmt;
+
+/// A position as engines are sent it: a starting position and the moves
+/// played since, in long algebraic notation.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Position {
+    /// `None` for the standard starting position.
+    pub fen: Option<String>,
+    pub moves: Vec<String>,
+}
+
+impl Position {
+    /// The position on `board`, sent with the moves that led to it so the
+    /// engine can see repetitions.
+    pub fn from_board(board: &Board) -> Position {
+        let mut start = board.clone();
+        while start.unmake_move().is_some() {}
+        let fen = start.to_fen();
+        Position {
+            fen: (fen != STARTING_FEN).then_some(fen),
+            moves: board.moves().map(|mv| mv.to_string()).collect(),
+        }
+    }
+
+    /// Reads a position written as it follows `position` in UCI: "startpos",
+    /// or "fen" and a FEN, then "moves" and the moves if there are any.
+    pub(crate) fn parse_words<'a>(mut words: impl Iterator<Item = &'a str>) -> Option<Position> {
+        let fen = match words.next()? {
+            "startpos" => None,
+            "fen" => {
+                let fields: Vec<&str> =
+                    words.by_ref().take_while(|word| *word != "moves").collect();
+                Some(fields.join(" "))
+            }
+            _ => return None,
+        };
+        // after "startpos" the "moves" keyword is still to come
+        let moves = words
+            .skip_while(|word| *word == "moves")
+            .map(str::to_string)
+            .collect();
+        Some(Position { fen, moves })
+    }
+
+    pub fn to_board(&self) -> Result<Board, PositionError> {
+        let mut board = match &self.fen {
+            Some(fen) => Board::from_fen(fen).map_err(PositionError::InvalidFen)?,
+            None => Board::starting_position(),
+        };
+        for text in &self.moves {
+            let mv = board
+                .parse_move(text)
+                .ok_or_else(|| PositionError::IllegalMove(text.clone()))?;
+            board.make_move(mv);
+        }
+        Ok(board)
+    }
+}
+
+/// Written the way `parse_words` reads it, as in "startpos moves e2e4 e7e5".
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.fen {
+            Some(fen) => write!(f, "fen {}", fen)?,
+            None => write!(f, "startpos")?,
+        }
+        if !self.moves.is_empty() {
+            write!(f, " moves {}", self.moves.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionError {
+    InvalidFen(FenError),
+    IllegalMove(String),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PositionError::InvalidFen(error) => write!(f, "invalid FEN: {}", error),
+            PositionError::IllegalMove(text) => write!(f, "'{}' is not a legal move", text),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
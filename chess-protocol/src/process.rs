@@ -0,0 +1,79 @@
+use crate::inbox::Inbox;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+/// An engine running as a child process. Lines are written to its standard
+/// input, and a background thread collects the lines it prints so they can be
+/// read without blocking. The process is killed when the handle is dropped.
+pub struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Inbox<String>,
+}
+
+impl EngineProcess {
+    pub fn launch(path: &Path) -> io::Result<EngineProcess> {
+        EngineProcess::launch_with_args(path, &[])
+    }
+
+    /// Launches the engine with command-line arguments, for the engines that
+    /// take their settings that way.
+    pub fn launch_with_args(path: &Path, args: &[&str]) -> io::Result<EngineProcess> {
+        let mut child = Command::new(path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(EngineProcess {
+            child,
+            stdin,
+            lines: Inbox::new(lines),
+        })
+    }
+
+    pub fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()
+    }
+
+    /// The next line the engine has printed, if one has arrived. `Err` means
+    /// the engine closed its output, which usually means it has exited.
+    pub fn try_read_line(&mut self) -> Result<Option<String>, io::Error> {
+        match self.lines.try_recv() {
+            Ok(line) => Ok(Some(line)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the engine closed its output",
+            )),
+        }
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        // engines are told to quit by the protocol before this; this is for the
+        // ones that don't listen
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
@@ -0,0 +1,522 @@
+//! The Universal Chess Interface. Commands go from the GUI to the engine and
+//! messages come back; both can be read from and written as protocol lines, so
+//! the same types serve a GUI driving an engine and an engine answering a GUI.
+//! Moves are in the long algebraic notation `Move` is displayed in.
+
+use crate::Position;
+use chess_core::{is_mate_score, MATE_SCORE};
+use std::fmt;
+
+/// What a `go` command asks for. Times are in milliseconds; anything left out
+/// is up to the engine.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GoParams {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<u64>,
+    pub infinite: bool,
+}
+
+/// A command from the GUI to the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuiCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: Option<String> },
+    UciNewGame,
+    Position(Position),
+    Go(GoParams),
+    Stop,
+    PonderHit,
+    Quit,
+}
+
+impl GuiCommand {
+    /// Reads one line sent to an engine. Unknown commands and malformed ones
+    /// give `None`, and the protocol says engines should ignore them.
+    pub fn parse(line: &str) -> Option<GuiCommand> {
+        let mut words = line.split_whitespace();
+        let command = match words.next()? {
+            "uci" => GuiCommand::Uci,
+            "debug" => GuiCommand::Debug(words.next() != Some("off")),
+            "isready" => GuiCommand::IsReady,
+            "setoption" => {
+                let words: Vec<&str> = words.collect();
+                let name_start = words.iter().position(|word| *word == "name")? + 1;
+                let value_start = words.iter().position(|word| *word == "value");
+                let name_end = value_start.unwrap_or(words.len());
+                GuiCommand::SetOption {
+                    name: words.get(name_start..name_end)?.join(" "),
+                    value: value_start.map(|start| words[start + 1..].join(" ")),
+                }
+            }
+            "ucinewgame" => GuiCommand::UciNewGame,
+            "position" => GuiCommand::Position(Position::parse_words(words)?),
+            "go" => GuiCommand::Go(parse_go(words)),
+            "stop" => GuiCommand::Stop,
+            "ponderhit" => GuiCommand::PonderHit,
+            "quit" => GuiCommand::Quit,
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+fn parse_go<'a>(mut words: impl Iterator<Item = &'a str>) -> GoParams {
+    let mut params = GoParams::default();
+    while let Some(word) = words.next() {
+        if word == "infinite" {
+            params.infinite = true;
+            continue;
+        }
+        let Some(value) = words.next() else {
+            break;
+        };
+        match word {
+            "wtime" => params.wtime = parse_time(value),
+            "btime" => params.btime = parse_time(value),
+            "winc" => params.winc = parse_time(value),
+            "binc" => params.binc = parse_time(value),
+            "movestogo" => params.movestogo = value.parse().ok(),
+            "depth" => params.depth = value.parse().ok(),
+            "nodes" => params.nodes = value.parse().ok(),
+            "movetime" => params.movetime = parse_time(value),
+            _ => {}
+        }
+    }
+    params
+}
+
+// some GUIs send a negative time once the clock has run out
+fn parse_time(value: &str) -> Option<u64> {
+    value.parse::<i64>().ok().map(|time| time.max(0) as u64)
+}
+
+impl fmt::Display for GuiCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GuiCommand::Uci => write!(f, "uci"),
+            GuiCommand::Debug(on) => write!(f, "debug {}", if *on { "on" } else { "off" }),
+            GuiCommand::IsReady => write!(f, "isready"),
+            GuiCommand::SetOption { name, value } => {
+                write!(f, "setoption name {}", name)?;
+                if let Some(value) = value {
+                    write!(f, " value {}", value)?;
+                }
+                Ok(())
+            }
+            GuiCommand::UciNewGame => write!(f, "ucinewgame"),
+            GuiCommand::Position(position) => write!(f, "position {}", position),
+            GuiCommand::Go(params) => {
+                write!(f, "go")?;
+                for (name, value) in [
+                    ("wtime", params.wtime),
+                    ("btime", params.btime),
+                    ("winc", params.winc),
+                    ("binc", params.binc),
+                    ("movestogo", params.movestogo.map(u64::from)),
+                    ("depth", params.depth.map(u64::from)),
+                    ("nodes", params.nodes),
+                    ("movetime", params.movetime),
+                ] {
+                    if let Some(value) = value {
+                        write!(f, " {} {}", name, value)?;
+                    }
+                }
+                if params.infinite {
+                    write!(f, " infinite")?;
+                }
+                Ok(())
+            }
+            GuiCommand::Stop => write!(f, "stop"),
+            GuiCommand::PonderHit => write!(f, "ponderhit"),
+            GuiCommand::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+/// A score from the engine's point of view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    Centipawns(i32),
+    /// Mate in this many moves, negative when the engine is getting mated.
+    Mate(i32),
+}
+
+impl Score {
+    /// The UCI form of a score from the built-in search, which counts mates in
+    /// plies from the root.
+    pub fn from_search(score: i32) -> Score {
+        if !is_mate_score(score) {
+            return Score::Centipawns(score);
+        }
+        let plies = MATE_SCORE - score.abs();
+        let moves = (plies + 1) / 2;
+        Score::Mate(if score > 0 { moves } else { -moves })
+    }
+}
+
+/// The fields of an `info` line this client cares about.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub time: Option<u64>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub score: Option<Score>,
+    pub pv: Vec<String>,
+    pub string: Option<String>,
+}
+
+impl Info {
+    fn parse<'a>(mut words: impl Iterator<Item = &'a str>) -> Info {
+        let mut info = Info::default();
+        while let Some(word) = words.next() {
+            match word {
+                "depth" => info.depth = words.next().and_then(|value| value.parse().ok()),
+                "seldepth" => info.seldepth = words.next().and_then(|value| value.parse().ok()),
+                "time" => info.time = words.next().and_then(|value| value.parse().ok()),
+                "nodes" => info.nodes = words.next().and_then(|value| value.parse().ok()),
+                "nps" => info.nps = words.next().and_then(|value| value.parse().ok()),
+                "score" => {
+                    let kind = words.next();
+                    let value = words.next().and_then(|value| value.parse().ok());
+                    info.score = match (kind, value) {
+                        (Some("cp"), Some(value)) => Some(Score::Centipawns(value)),
+                        (Some("mate"), Some(value)) => Some(Score::Mate(value)),
+                        _ => None,
+                    };
+                }
+                // pv and string run to the end of the line
+                "pv" => {
+                    info.pv = words.by_ref().map(str::to_string).collect();
+                }
+                "string" => {
+                    info.string = Some(words.by_ref().collect::<Vec<_>>().join(" "));
+                }
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+impl fmt::Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "info")?;
+        for (name, value) in [
+            ("depth", self.depth.map(u64::from)),
+            ("seldepth", self.seldepth.map(u64::from)),
+        ] {
+            if let Some(value) = value {
+                write!(f, " {} {}", name, value)?;
+            }
+        }
+        match self.score {
+            Some(Score::Centipawns(value)) => write!(f, " score cp {}", value)?,
+            Some(Score::Mate(moves)) => write!(f, " score mate {}", moves)?,
+            None => {}
+        }
+        for (name, value) in [
+            ("nodes", self.nodes),
+            ("nps", self.nps),
+            ("time", self.time),
+        ] {
+            if let Some(value) = value {
+                write!(f, " {} {}", name, value)?;
+            }
+        }
+        if !self.pv.is_empty() {
+            write!(f, " pv {}", self.pv.join(" "))?;
+        }
+        if let Some(string) = &self.string {
+            write!(f, " string {}", string)?;
+        }
+        Ok(())
+    }
+}
+
+/// A message from the engine to the GUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineMessage {
+    IdName(String),
+    IdAuthor(String),
+    UciOk,
+    ReadyOk,
+    BestMove {
+        best: String,
+        ponder: Option<String>,
+    },
+    Info(Info),
+    /// An `option` line, kept as the text after "option".
+    Option(String),
+}
+
+impl EngineMessage {
+    /// Reads one line printed by an engine. Lines the protocol doesn't define
+    /// give `None`.
+    pub fn parse(line: &str) -> Option<EngineMessage> {
+        let mut words = line.split_whitespace();
+        let message = match words.next()? {
+            "id" => {
+                let field = words.next()?;
+                let value = words.collect::<Vec<_>>().join(" ");
+                match field {
+                    "name" => EngineMessage::IdName(value),
+                    "author" => EngineMessage::IdAuthor(value),
+                    _ => return None,
+                }
+            }
+            "uciok" => EngineMessage::UciOk,
+            "readyok" => EngineMessage::ReadyOk,
+            "bestmove" => {
+                let best = words.next()?.to_string();
+                let ponder = match words.next() {
+                    Some("ponder") => words.next().map(str::to_string),
+                    _ => None,
+                };
+                EngineMessage::BestMove { best, ponder }
+            }
+            "info" => EngineMessage::Info(Info::parse(words)),
+            "option" => EngineMessage::Option(words.collect::<Vec<_>>().join(" ")),
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+impl fmt::Display for EngineMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineMessage::IdName(name) => write!(f, "id name {}", name),
+            EngineMessage::IdAuthor(author) => write!(f, "id author {}", author),
+            EngineMessage::UciOk => write!(f, "uciok"),
+            EngineMessage::ReadyOk => write!(f, "readyok"),
+            EngineMessage::BestMove { best, ponder } => {
+                write!(f, "bestmove {}", best)?;
+                if let Some(ponder) = ponder {
+                    write!(f, " ponder {}", ponder)?;
+                }
+                Ok(())
+            }
+            EngineMessage::Info(info) => write!(f, "{}", info),
+            EngineMessage::Option(text) => write!(f, "option {}", text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_round_trip(command: GuiCommand, line: &str) {
+        assert_eq!(command.to_string(), line);
+        assert_eq!(GuiCommand::parse(line), Some(command));
+    }
+
+    fn message_round_trip(message: EngineMessage, line: &str) {
+        assert_eq!(message.to_string(), line);
+        assert_eq!(EngineMessage::parse(line), Some(message));
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        command_round_trip(GuiCommand::Uci, "uci");
+        command_round_trip(GuiCommand::Debug(true), "debug on");
+        command_round_trip(GuiCommand::Debug(false), "debug off");
+        command_round_trip(GuiCommand::IsReady, "isready");
+        command_round_trip(
+            GuiCommand::SetOption {
+                name: "Hash".to_string(),
+                value: Some("64".to_string()),
+            },
+            "setoption name Hash value 64",
+        );
+        command_round_trip(
+            GuiCommand::SetOption {
+                name: "Clear Hash".to_string(),
+                value: None,
+            },
+            "setoption name Clear Hash",
+        );
+        command_round_trip(GuiCommand::UciNewGame, "ucinewgame");
+        command_round_trip(
+            GuiCommand::Position(Position::default()),
+            "position startpos",
+        );
+        command_round_trip(
+            GuiCommand::Position(Position {
+                fen: Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string()),
+                moves: vec!["e2e4".to_string(), "e8d7".to_string()],
+            }),
+            "position fen 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1 moves e2e4 e8d7",
+        );
+        command_round_trip(GuiCommand::Stop, "stop");
+        command_round_trip(GuiCommand::PonderHit, "ponderhit");
+        command_round_trip(GuiCommand::Quit, "quit");
+    }
+
+    #[test]
+    fn go_round_trips() {
+        command_round_trip(GuiCommand::Go(GoParams::default()), "go");
+        command_round_trip(
+            GuiCommand::Go(GoParams {
+                wtime: Some(300_000),
+                btime: Some(295_500),
+                winc: Some(2_000),
+                binc: Some(2_000),
+                movestogo: Some(40),
+                ..GoParams::default()
+            }),
+            "go wtime 300000 btime 295500 winc 2000 binc 2000 movestogo 40",
+        );
+        command_round_trip(
+            GuiCommand::Go(GoParams {
+                depth: Some(6),
+                nodes: Some(100_000),
+                movetime: Some(1_500),
+                ..GoParams::default()
+            }),
+            "go depth 6 nodes 100000 movetime 1500",
+        );
+        command_round_trip(
+            GuiCommand::Go(GoParams {
+                infinite: true,
+                ..GoParams::default()
+            }),
+            "go infinite",
+        );
+    }
+
+    #[test]
+    fn reads_go_in_any_order() {
+        assert_eq!(
+            GuiCommand::parse("go infinite searchmoves e2e4 btime -20 wtime 100"),
+            Some(GuiCommand::Go(GoParams {
+                wtime: Some(100),
+                // a clock that has run out may be sent negative
+                btime: Some(0),
+                infinite: true,
+                ..GoParams::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn unknown_and_malformed_commands_are_ignored() {
+        for line in [
+            "",
+            "frobnicate",
+            "setoption",
+            "setoption value 3",
+            "position",
+            "position somewhere",
+        ] {
+            assert_eq!(GuiCommand::parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        message_round_trip(
+            EngineMessage::IdName("Stand In 1.0".to_string()),
+            "id name Stand In 1.0",
+        );
+        message_round_trip(
+            EngineMessage::IdAuthor("A. N. Other".to_string()),
+            "id author A. N. Other",
+        );
+        message_round_trip(EngineMessage::UciOk, "uciok");
+        message_round_trip(EngineMessage::ReadyOk, "readyok");
+        message_round_trip(
+            EngineMessage::BestMove {
+                best: "e2e4".to_string(),
+                ponder: Some("e7e5".to_string()),
+            },
+            "bestmove e2e4 ponder e7e5",
+        );
+        message_round_trip(
+            EngineMessage::BestMove {
+                best: "e7e8q".to_string(),
+                ponder: None,
+            },
+            "bestmove e7e8q",
+        );
+        message_round_trip(
+            EngineMessage::Option("name Hash type spin default 16 min 1 max 1024".to_string()),
+            "option name Hash type spin default 16 min 1 max 1024",
+        );
+    }
+
+    #[test]
+    fn info_round_trips() {
+        message_round_trip(
+            EngineMessage::Info(Info {
+                depth: Some(12),
+                seldepth: Some(18),
+                score: Some(Score::Mate(-3)),
+                nodes: Some(1_000_000),
+                nps: Some(500_000),
+                time: Some(2_000),
+                pv: vec!["e2e4".to_string(), "e7e5".to_string()],
+                string: None,
+            }),
+            "info depth 12 seldepth 18 score mate -3 nodes 1000000 nps 500000 time 2000 pv e2e4 e7e5",
+        );
+        message_round_trip(
+            EngineMessage::Info(Info {
+                score: Some(Score::Centipawns(-25)),
+                ..Info::default()
+            }),
+            "info score cp -25",
+        );
+        message_round_trip(
+            EngineMessage::Info(Info {
+                string: Some("book move".to_string()),
+                ..Info::default()
+            }),
+            "info string book move",
+        );
+    }
+
+    #[test]
+    fn info_fields_it_does_not_keep_are_skipped() {
+        assert_eq!(
+            EngineMessage::parse("info depth 3 currmove e2e4 currmovenumber 1 hashfull 10"),
+            Some(EngineMessage::Info(Info {
+                depth: Some(3),
+                ..Info::default()
+            }))
+        );
+        assert_eq!(
+            EngineMessage::parse("info score upperbound 30 depth 2"),
+            Some(EngineMessage::Info(Info {
+                depth: Some(2),
+                ..Info::default()
+            }))
+        );
+    }
+
+    #[test]
+    fn unknown_and_malformed_messages_are_ignored() {
+        for line in ["", "frobnicate", "id", "id colour red", "bestmove"] {
+            assert_eq!(EngineMessage::parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn search_scores_count_mates_in_moves() {
+        assert_eq!(Score::from_search(35), Score::Centipawns(35));
+        assert_eq!(Score::from_search(MATE_SCORE - 1), Score::Mate(1));
+        assert_eq!(Score::from_search(MATE_SCORE - 3), Score::Mate(2));
+        assert_eq!(Score::from_search(-MATE_SCORE + 2), Score::Mate(-1));
+        assert_eq!(Score::from_search(-MATE_SCORE + 4), Score::Mate(-2));
+    }
+}
@@ -0,0 +1,517 @@
+//! The Chess Engine Communication Protocol used by XBoard and WinBoard, in its
+//! protocol version 2 form. As with UCI, commands and engine output can both be
+//! read from and written as protocol lines. Moves are in the same long
+//! algebraic notation, though engines may ask for SAN with `feature san=1`.
+
+use std::fmt;
+use std::time::Duration;
+
+/// A command from the GUI to the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XboardCommand {
+    Xboard,
+    Protover(u32),
+    Accepted(String),
+    Rejected(String),
+    New,
+    Force,
+    Go,
+    /// A move for the side to move, sent with `usermove` once the engine has
+    /// asked for that feature and as a bare move otherwise.
+    UserMove(String),
+    /// `level MPS BASE INC`: `moves` moves in `base`, or all of them when
+    /// `moves` is 0, with `increment` added after every move.
+    Level {
+        moves: u32,
+        base: Duration,
+        increment: Duration,
+    },
+    /// A fixed time for every move, in seconds.
+    St(u32),
+    /// A depth limit in plies.
+    Sd(u32),
+    /// The engine's remaining time in centiseconds.
+    Time(u64),
+    /// The opponent's remaining time in centiseconds.
+    Otim(u64),
+    SetBoard(String),
+    Ping(u32),
+    /// `?`: move now with the best move found so far.
+    MoveNow,
+    /// The opponent offers a draw. The engine accepts with `offer draw`.
+    Draw,
+    Result {
+        result: String,
+        comment: String,
+    },
+    Undo,
+    Remove,
+    Post,
+    NoPost,
+    Hard,
+    Easy,
+    Random,
+    Computer,
+    Quit,
+}
+
+impl XboardCommand {
+    /// Reads one line sent to an engine. Commands engines may ignore and
+    /// unknown ones give `None`.
+    pub fn parse(line: &str) -> Option<XboardCommand> {
+        let line = line.trim();
+        let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let command = match word {
+            "xboard" => XboardCommand::Xboard,
+            "protover" => XboardCommand::Protover(rest.parse().ok()?),
+            "accepted" => XboardCommand::Accepted(rest.to_string()),
+            "rejected" => XboardCommand::Rejected(rest.to_string()),
+            "new" => XboardCommand::New,
+            "force" => XboardCommand::Force,
+            "go" => XboardCommand::Go,
+            "usermove" => XboardCommand::UserMove(rest.to_string()),
+            "level" => {
+                let mut fields = rest.split_whitespace();
+                XboardCommand::Level {
+                    moves: fields.next()?.parse().ok()?,
+                    base: parse_base_time(fields.next()?)?,
+                    increment: parse_seconds(fields.next()?)?,
+                }
+            }
+            "st" => XboardCommand::St(rest.parse().ok()?),
+            "sd" => XboardCommand::Sd(rest.parse().ok()?),
+            "time" => XboardCommand::Time(parse_centiseconds(rest)?),
+            "otim" => XboardCommand::Otim(parse_centiseconds(rest)?),
+            "setboard" => XboardCommand::SetBoard(rest.to_string()),
+            "ping" => XboardCommand::Ping(rest.parse().ok()?),
+            "?" => XboardCommand::MoveNow,
+            "draw" => XboardCommand::Draw,
+            "result" => {
+                let (result, comment) = rest.split_once(' ').unwrap_or((rest, ""));
+                XboardCommand::Result {
+                    result: result.to_string(),
+                    comment: comment.trim().trim_matches(['{', '}']).to_string(),
+                }
+            }
+            "undo" => XboardCommand::Undo,
+            "remove" => XboardCommand::Remove,
+            "post" => XboardCommand::Post,
+            "nopost" => XboardCommand::NoPost,
+            "hard" => XboardCommand::Hard,
+            "easy" => XboardCommand::Easy,
+            "random" => XboardCommand::Random,
+            "computer" => XboardCommand::Computer,
+            "quit" => XboardCommand::Quit,
+            _ if rest.is_empty() && looks_like_move(word) => {
+                XboardCommand::UserMove(word.to_string())
+            }
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+/// Whether `word` could be a move in coordinate notation, which is how moves
+/// arrive before the engine has asked for `usermove`.
+fn looks_like_move(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    (4..=5).contains(&bytes.len())
+        && bytes[0].is_ascii_lowercase()
+        && bytes[1].is_ascii_digit()
+        && bytes[2].is_ascii_lowercase()
+        && bytes[3].is_ascii_digit()
+}
+
+/// Reads the base time of `level`, written as minutes or "minutes:seconds".
+fn parse_base_time(text: &str) -> Option<Duration> {
+    let (minutes, seconds) = text.split_once(':').unwrap_or((text, "0"));
+    let seconds = minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+fn parse_seconds(text: &str) -> Option<Duration> {
+    text.parse::<f64>()
+        .ok()
+        .filter(|seconds| *seconds >= 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+// the clock can be negative once a side has overstepped it
+fn parse_centiseconds(text: &str) -> Option<u64> {
+    text.parse::<i64>().ok().map(|time| time.max(0) as u64)
+}
+
+impl fmt::Display for XboardCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XboardCommand::Xboard => write!(f, "xboard"),
+            XboardCommand::Protover(version) => write!(f, "protover {}", version),
+            XboardCommand::Accepted(feature) => write!(f, "accepted {}", feature),
+            XboardCommand::Rejected(feature) => write!(f, "rejected {}", feature),
+            XboardCommand::New => write!(f, "new"),
+            XboardCommand::Force => write!(f, "force"),
+            XboardCommand::Go => write!(f, "go"),
+            XboardCommand::UserMove(text) => write!(f, "usermove {}", text),
+            XboardCommand::Level {
+                moves,
+                base,
+                increment,
+            } => {
+                let seconds = base.as_secs();
+                write!(f, "level {} {}", moves, seconds / 60)?;
+                if seconds % 60 != 0 {
+                    write!(f, ":{:02}", seconds % 60)?;
+                }
+                write!(f, " {}", increment.as_secs_f64())
+            }
+            XboardCommand::St(seconds) => write!(f, "st {}", seconds),
+            XboardCommand::Sd(depth) => write!(f, "sd {}", depth),
+            XboardCommand::Time(centiseconds) => write!(f, "time {}", centiseconds),
+            XboardCommand::Otim(centiseconds) => write!(f, "otim {}", centiseconds),
+            XboardCommand::SetBoard(fen) => write!(f, "setboard {}", fen),
+            XboardCommand::Ping(number) => write!(f, "ping {}", number),
+            XboardCommand::MoveNow => write!(f, "?"),
+            XboardCommand::Draw => write!(f, "draw"),
+            XboardCommand::Result { result, comment } => {
+                write!(f, "result {} {{{}}}", result, comment)
+            }
+            XboardCommand::Undo => write!(f, "undo"),
+            XboardCommand::Remove => write!(f, "remove"),
+            XboardCommand::Post => write!(f, "post"),
+            XboardCommand::NoPost => write!(f, "nopost"),
+            XboardCommand::Hard => write!(f, "hard"),
+            XboardCommand::Easy => write!(f, "easy"),
+            XboardCommand::Random => write!(f, "random"),
+            XboardCommand::Computer => write!(f, "computer"),
+            XboardCommand::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+/// A line of thinking output, sent after `post`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Thinking {
+    pub depth: u32,
+    /// Centipawns from the engine's point of view.
+    pub score: i32,
+    /// Centiseconds spent so far.
+    pub time: u64,
+    pub nodes: u64,
+    pub pv: Vec<String>,
+}
+
+/// Something the engine prints for the GUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XboardOutput {
+    /// `feature` pairs in the order given, with quotes taken off the values.
+    Feature(Vec<(String, String)>),
+    Move(String),
+    Pong(u32),
+    IllegalMove(String),
+    Error {
+        kind: String,
+        command: String,
+    },
+    Resign,
+    OfferDraw,
+    /// A claimed result such as `1-0 {White mates}`.
+    Result {
+        result: String,
+        comment: String,
+    },
+    Thinking(Thinking),
+}
+
+impl XboardOutput {
+    /// Reads one line printed by an engine. Anything else engines print,
+    /// such as debugging lines starting with '#', gives `None`.
+    pub fn parse(line: &str) -> Option<XboardOutput> {
+        let line = line.trim();
+        let (word, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let output = match word {
+            "feature" => XboardOutput::Feature(parse_features(rest)),
+            "move" => XboardOutput::Move(rest.to_string()),
+            "pong" => XboardOutput::Pong(rest.parse().ok()?),
+            "resign" => XboardOutput::Resign,
+            "offer" if rest == "draw" => XboardOutput::OfferDraw,
+            "1-0" | "0-1" | "1/2-1/2" => XboardOutput::Result {
+                result: word.to_string(),
+                comment: rest.trim_matches(['{', '}']).to_string(),
+            },
+            _ if line.starts_with("Illegal move") => {
+                let text = line.rsplit_once(':').map_or("", |(_, text)| text);
+                XboardOutput::IllegalMove(text.trim().to_string())
+            }
+            _ if line.starts_with("Error") => {
+                let (kind, command) = line.split_once(':').unwrap_or((line, ""));
+                let kind = kind.trim_start_matches("Error").trim();
+                XboardOutput::Error {
+                    kind: kind.trim_matches(['(', ')']).to_string(),
+                    command: command.trim().to_string(),
+                }
+            }
+            _ => XboardOutput::Thinking(parse_thinking(line)?),
+        };
+        Some(output)
+    }
+}
+
+fn parse_features(text: &str) -> Vec<(String, String)> {
+    let mut features = Vec::new();
+    let mut rest = text.trim_start();
+    while let Some((name, after)) = rest.split_once('=') {
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => after.split_once(' ').unwrap_or((after, "")),
+        };
+        features.push((name.trim().to_string(), value.to_string()));
+        rest = after.trim_start();
+    }
+    features
+}
+
+fn parse_thinking(line: &str) -> Option<Thinking> {
+    let mut fields = line.split_whitespace();
+    Some(Thinking {
+        depth: fields.next()?.parse().ok()?,
+        score: fields.next()?.parse().ok()?,
+        time: fields.next()?.parse().ok()?,
+        nodes: fields.next()?.parse().ok()?,
+        pv: fields.map(str::to_string).collect(),
+    })
+}
+
+impl fmt::Display for XboardOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            XboardOutput::Feature(features) => {
+                write!(f, "feature")?;
+                for (name, value) in features {
+                    if value.parse::<i64>().is_ok() {
+                        write!(f, " {}={}", name, value)?;
+                    } else {
+                        write!(f, " {}=\"{}\"", name, value)?;
+                    }
+                }
+                Ok(())
+            }
+            XboardOutput::Move(text) => write!(f, "move {}", text),
+            XboardOutput::Pong(number) => write!(f, "pong {}", number),
+            XboardOutput::IllegalMove(text) => write!(f, "Illegal move: {}", text),
+            XboardOutput::Error { kind, command } => write!(f, "Error ({}): {}", kind, command),
+            XboardOutput::Resign => write!(f, "resign"),
+            XboardOutput::OfferDraw => write!(f, "offer draw"),
+            XboardOutput::Result { result, comment } => write!(f, "{} {{{}}}", result, comment),
+            XboardOutput::Thinking(thinking) => {
+                write!(
+                    f,
+                    "{} {} {} {}",
+                    thinking.depth, thinking.score, thinking.time, thinking.nodes
+                )?;
+                for mv in &thinking.pv {
+                    write!(f, " {}", mv)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_round_trip(command: XboardCommand, line: &str) {
+        assert_eq!(command.to_string(), line);
+        assert_eq!(XboardCommand::parse(line), Some(command));
+    }
+
+    fn output_round_trip(output: XboardOutput, line: &str) {
+        assert_eq!(output.to_string(), line);
+        assert_eq!(XboardOutput::parse(line), Some(output));
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        for (command, line) in [
+            (XboardCommand::Xboard, "xboard"),
+            (XboardCommand::New, "new"),
+            (XboardCommand::Force, "force"),
+            (XboardCommand::Go, "go"),
+            (XboardCommand::MoveNow, "?"),
+            (XboardCommand::Draw, "draw"),
+            (XboardCommand::Undo, "undo"),
+            (XboardCommand::Remove, "remove"),
+            (XboardCommand::Post, "post"),
+            (XboardCommand::NoPost, "nopost"),
+            (XboardCommand::Hard, "hard"),
+            (XboardCommand::Easy, "easy"),
+            (XboardCommand::Random, "random"),
+            (XboardCommand::Computer, "computer"),
+            (XboardCommand::Quit, "quit"),
+        ] {
+            command_round_trip(command, line);
+        }
+        command_round_trip(XboardCommand::Protover(2), "protover 2");
+        command_round_trip(
+            XboardCommand::Accepted("usermove".to_string()),
+            "accepted usermove",
+        );
+        command_round_trip(XboardCommand::Rejected("san".to_string()), "rejected san");
+        command_round_trip(
+            XboardCommand::UserMove("e7e8q".to_string()),
+            "usermove e7e8q",
+        );
+        command_round_trip(XboardCommand::Ping(7), "ping 7");
+        command_round_trip(
+            XboardCommand::SetBoard("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1".to_string()),
+            "setboard 4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+        );
+        command_round_trip(
+            XboardCommand::Result {
+                result: "1-0".to_string(),
+                comment: "White mates".to_string(),
+            },
+            "result 1-0 {White mates}",
+        );
+    }
+
+    #[test]
+    fn time_commands_round_trip() {
+        command_round_trip(
+            XboardCommand::Level {
+                moves: 40,
+                base: Duration::from_secs(330),
+                increment: Duration::from_secs(2),
+            },
+            "level 40 5:30 2",
+        );
+        command_round_trip(
+            XboardCommand::Level {
+                moves: 0,
+                base: Duration::from_secs(180),
+                increment: Duration::from_millis(500),
+            },
+            "level 0 3 0.5",
+        );
+        command_round_trip(XboardCommand::St(30), "st 30");
+        command_round_trip(XboardCommand::Sd(8), "sd 8");
+        command_round_trip(XboardCommand::Time(12_345), "time 12345");
+        command_round_trip(XboardCommand::Otim(600), "otim 600");
+        // a clock that has run out may be sent negative
+        assert_eq!(
+            XboardCommand::parse("time -40"),
+            Some(XboardCommand::Time(0))
+        );
+    }
+
+    #[test]
+    fn bare_moves_are_user_moves() {
+        for text in ["e2e4", "e7e8q"] {
+            assert_eq!(
+                XboardCommand::parse(text),
+                Some(XboardCommand::UserMove(text.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_and_malformed_commands_are_ignored() {
+        for line in [
+            "",
+            "frobnicate",
+            "protover",
+            "protover two",
+            "level 40 5:30",
+            "level forty 5 0",
+            "level 40 5:xx 0",
+            "level 40 5 -1",
+            "st soon",
+            "sd",
+            "time later",
+            "ping",
+            "e2e4 e7e5",
+            "e2",
+        ] {
+            assert_eq!(XboardCommand::parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn output_round_trips() {
+        output_round_trip(
+            XboardOutput::Feature(vec![
+                ("myname".to_string(), "Stand In 1.0".to_string()),
+                ("ping".to_string(), "1".to_string()),
+                ("usermove".to_string(), "1".to_string()),
+                ("done".to_string(), "1".to_string()),
+            ]),
+            "feature myname=\"Stand In 1.0\" ping=1 usermove=1 done=1",
+        );
+        output_round_trip(XboardOutput::Move("g8f6".to_string()), "move g8f6");
+        output_round_trip(XboardOutput::Pong(3), "pong 3");
+        output_round_trip(
+            XboardOutput::IllegalMove("e2e5".to_string()),
+            "Illegal move: e2e5",
+        );
+        output_round_trip(
+            XboardOutput::Error {
+                kind: "unknown command".to_string(),
+                command: "frobnicate".to_string(),
+            },
+            "Error (unknown command): frobnicate",
+        );
+        output_round_trip(XboardOutput::Resign, "resign");
+        output_round_trip(XboardOutput::OfferDraw, "offer draw");
+        output_round_trip(
+            XboardOutput::Result {
+                result: "1/2-1/2".to_string(),
+                comment: "Draw by repetition".to_string(),
+            },
+            "1/2-1/2 {Draw by repetition}",
+        );
+        output_round_trip(
+            XboardOutput::Thinking(Thinking {
+                depth: 5,
+                score: -34,
+                time: 120,
+                nodes: 45_000,
+                pv: vec!["e2e4".to_string(), "e7e5".to_string()],
+            }),
+            "5 -34 120 45000 e2e4 e7e5",
+        );
+    }
+
+    #[test]
+    fn reads_the_other_forms_engines_print() {
+        assert_eq!(
+            XboardOutput::parse("feature  done=0 sigint=0   variants=\"normal\""),
+            Some(XboardOutput::Feature(vec![
+                ("done".to_string(), "0".to_string()),
+                ("sigint".to_string(), "0".to_string()),
+                ("variants".to_string(), "normal".to_string()),
+            ]))
+        );
+        assert_eq!(
+            XboardOutput::parse("Illegal move (not legal): e2e5"),
+            Some(XboardOutput::IllegalMove("e2e5".to_string()))
+        );
+    }
+
+    #[test]
+    fn other_output_is_ignored() {
+        for line in [
+            "",
+            "# debugging output",
+            "pong",
+            "pong soon",
+            "offer",
+            "hello there",
+            "5 -34 120",
+        ] {
+            assert_eq!(XboardOutput::parse(line), None, "{:?}", line);
+        }
+    }
+}
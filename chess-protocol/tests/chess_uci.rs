@@ -0,0 +1,65 @@
+//! Drives the built-in search's UCI engine through `EngineProcess`.
+
+use chess_protocol::uci::{EngineMessage, GoParams, GuiCommand};
+use chess_protocol::{EngineProcess, Position};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PATIENCE: Duration = Duration::from_secs(10);
+
+fn send(engine: &mut EngineProcess, command: GuiCommand) {
+    engine.send(&command.to_string()).unwrap();
+}
+
+fn best_move(engine: &mut EngineProcess) -> String {
+    let deadline = Instant::now() + PATIENCE;
+    while Instant::now() < deadline {
+        match engine.try_read_line().unwrap() {
+            Some(line) => {
+                if let Some(EngineMessage::BestMove { best, .. }) = EngineMessage::parse(&line) {
+                    return best;
+                }
+            }
+            None => thread::sleep(Duration::from_millis(5)),
+        }
+    }
+    panic!("the engine did not move in time");
+}
+
+#[test]
+fn an_infinite_search_waits_for_stop() {
+    let mut engine =
+        EngineProcess::launch(Path::new(env!("CARGO_BIN_EXE_chess-uci"))).expect("it starts");
+    // a mate in one, which the search is done with at once
+    send(
+        &mut engine,
+        GuiCommand::Position(Position {
+            fen: Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".to_string()),
+            moves: Vec::new(),
+        }),
+    );
+    send(
+        &mut engine,
+        GuiCommand::Go(GoParams {
+            infinite: true,
+            ..GoParams::default()
+        }),
+    );
+    thread::sleep(Duration::from_millis(300));
+    while let Some(line) = engine.try_read_line().unwrap() {
+        assert!(
+            !line.starts_with("bestmove"),
+            "answered before stop: {}",
+            line
+        );
+    }
+
+    send(&mut engine, GuiCommand::Stop);
+    assert_eq!(best_move(&mut engine), "a1a8");
+
+    // without infinite it answers as soon as it is done
+    send(&mut engine, GuiCommand::Go(GoParams::default()));
+    assert_eq!(best_move(&mut engine), "a1a8");
+    send(&mut engine, GuiCommand::Quit);
+}
@@ -0,0 +1,442 @@
+//! Both ends of a network game in one process, over 127.0.0.1.
+
+use chess_core::{Board, Team};
+use chess_protocol::net::{NetMessage, PROTOCOL_VERSION};
+use chess_protocol::{Connection, Listener, Peer, PeerEvent, Position, Rematch};
+use std::io::Write;
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PATIENCE: Duration = Duration::from_secs(10);
+const SHORT_WAIT: Duration = Duration::from_millis(5);
+
+fn accept(listener: &mut Listener) -> Connection {
+    let deadline = Instant::now() + PATIENCE;
+    loop {
+        if let Some(connection) = listener.try_accept() {
+            return connection;
+        }
+        assert!(Instant::now() < deadline, "the connection was not accepted");
+        thread::sleep(SHORT_WAIT);
+    }
+}
+
+/// Polls `peer` until nothing more has come in, playing the moves and games
+/// it returns on `board` the way the game does.
+fn poll(peer: &mut Peer, board: &mut Board) -> Vec<PeerEvent> {
+    let mut events = Vec::new();
+    while let Some(event) = peer.poll(board, None) {
+        match &event {
+            PeerEvent::Move { mv, .. } => board.make_move(*mv),
+            PeerEvent::Game { board: game, .. } | PeerEvent::Rematch { board: game, .. } => {
+                *board = game.clone()
+            }
+            _ => {}
+        }
+        events.push(event);
+    }
+    peer.send_moves(board, None);
+    events
+}
+
+fn hello(name: &str) -> NetMessage {
+    NetMessage::Hello {
+        version: PROTOCOL_VERSION,
+        name: name.to_string(),
+    }
+}
+
+/// A host playing White from the starting position, and the boards it and its
+/// guest play on.
+struct Game {
+    host: Peer,
+    host_board: Board,
+    host_events: Vec<PeerEvent>,
+    guest: Option<Peer>,
+    guest_board: Board,
+    guest_events: Vec<PeerEvent>,
+}
+
+impl Game {
+    fn host() -> Game {
+        Game {
+            host: Peer::host(0, "Host", Team::White, None, false).unwrap(),
+            host_board: Board::starting_position(),
+            host_events: Vec::new(),
+            guest: None,
+            guest_board: Board::starting_position(),
+            guest_events: Vec::new(),
+        }
+    }
+
+    /// A host and a guest that have been through the handshake.
+    fn start() -> Game {
+        let mut game = Game::host();
+        game.guest = Some(Peer::join(&game.address(), "Guest"));
+        game.run_until(|game| game.host.is_ready() && game.guest().is_ready());
+        game
+    }
+
+    fn address(&self) -> String {
+        format!("127.0.0.1:{}", self.host.port().unwrap())
+    }
+
+    fn guest(&self) -> &Peer {
+        self.guest.as_ref().unwrap()
+    }
+
+    fn connect(&self) -> Connection {
+        Connection::connect(self.address()).unwrap()
+    }
+
+    /// Polls both sides until `done`.
+    fn run_until(&mut self, mut done: impl FnMut(&Game) -> bool) {
+        let deadline = Instant::now() + PATIENCE;
+        while !done(self) {
+            assert!(Instant::now() < deadline, "the peers never got there");
+            let events = poll(&mut self.host, &mut self.host_board);
+            self.host_events.extend(events);
+            if let Some(guest) = &mut self.guest {
+                let events = poll(guest, &mut self.guest_board);
+                self.guest_events.extend(events);
+            }
+            thread::sleep(SHORT_WAIT);
+        }
+    }
+
+    /// Polls the host until `connection` has been sent something.
+    fn reply(&mut self, connection: &mut Connection) -> NetMessage {
+        let deadline = Instant::now() + PATIENCE;
+        loop {
+            if let Some(message) = connection.try_receive().unwrap() {
+                return message;
+            }
+            assert!(Instant::now() < deadline, "the host did not answer");
+            let events = poll(&mut self.host, &mut self.host_board);
+            self.host_events.extend(events);
+            thread::sleep(SHORT_WAIT);
+        }
+    }
+
+    /// Polls the host until it has closed `connection`.
+    fn wait_for_close(&mut self, connection: &mut Connection) {
+        let deadline = Instant::now() + PATIENCE;
+        while connection.try_receive().is_ok() {
+            assert!(Instant::now() < deadline, "the connection stayed open");
+            let events = poll(&mut self.host, &mut self.host_board);
+            self.host_events.extend(events);
+            thread::sleep(SHORT_WAIT);
+        }
+    }
+
+    /// Plays `mv` on the host's board, or the guest's.
+    fn play(&mut self, host: bool, mv: &str) {
+        let board = if host {
+            &mut self.host_board
+        } else {
+            &mut self.guest_board
+        };
+        board.make_move(board.parse_move(mv).unwrap());
+    }
+}
+
+fn is_warning(event: &PeerEvent) -> bool {
+    matches!(event, PeerEvent::Warning(_))
+}
+
+fn is_rematch(event: &PeerEvent) -> bool {
+    matches!(event, PeerEvent::Rematch { .. })
+}
+
+#[test]
+fn handshake_and_moves() {
+    let mut game = Game::start();
+    assert_eq!(game.guest().local_side(), Team::Black);
+    assert_eq!(game.guest().opponent_name(), "Host");
+    assert_eq!(game.host.opponent_name(), "Guest");
+    assert!(matches!(
+        game.guest_events.as_slice(),
+        [PeerEvent::Game { clocks: None, .. }]
+    ));
+
+    for (ply, mv) in ["e2e4", "e7e5", "g1f3"].into_iter().enumerate() {
+        game.play(ply % 2 == 0, mv);
+        game.run_until(|game| {
+            game.host_board.moves().count() == ply + 1
+                && game.guest_board.moves().count() == ply + 1
+        });
+    }
+    assert_eq!(game.host_board.to_fen(), game.guest_board.to_fen());
+    assert!(!game.host_events.iter().any(is_warning));
+    assert!(!game.guest_events.iter().any(is_warning));
+}
+
+#[test]
+fn spectators_are_sent_both_players_moves() {
+    let mut game = Game::start();
+    game.play(true, "d2d4");
+    game.run_until(|game| game.guest_board.moves().count() == 1);
+
+    let mut spectator = Peer::watch(&game.address(), "Spectator");
+    let mut spectator_board = Board::starting_position();
+    game.run_until(|game| {
+        poll(&mut spectator, &mut spectator_board);
+        game.host.spectators() == 1 && spectator.is_ready()
+    });
+    assert_eq!(spectator_board.moves().count(), 1);
+
+    game.play(false, "d7d5");
+    game.run_until(|_| {
+        poll(&mut spectator, &mut spectator_board);
+        spectator_board.moves().count() == 2
+    });
+    assert_eq!(spectator_board.to_fen(), game.host_board.to_fen());
+}
+
+#[test]
+fn a_second_hello_is_turned_away() {
+    let mut game = Game::start();
+    let mut stranger = game.connect();
+    stranger.send(&hello("Stranger")).unwrap();
+    assert_eq!(
+        game.reply(&mut stranger),
+        NetMessage::Error("a game is already being played".to_string())
+    );
+    game.wait_for_close(&mut stranger);
+
+    // the guest is still the one being played
+    assert_eq!(game.host.opponent_name(), "Guest");
+    game.play(true, "e2e4");
+    game.run_until(|game| game.guest_board.moves().count() == 1);
+}
+
+#[test]
+fn only_the_guest_can_rejoin() {
+    let mut game = Game::host();
+    let mut guest = game.connect();
+    guest.send(&hello("Guest")).unwrap();
+    assert_eq!(game.reply(&mut guest), hello("Host"));
+    let NetMessage::Session(token) = game.reply(&mut guest) else {
+        panic!("the host did not send the session token");
+    };
+    assert!(matches!(game.reply(&mut guest), NetMessage::Game { .. }));
+
+    let mut impostor = game.connect();
+    impostor
+        .send(&NetMessage::Rejoin {
+            version: PROTOCOL_VERSION,
+            token: token.wrapping_add(1),
+            name: "Impostor".to_string(),
+        })
+        .unwrap();
+    assert_eq!(
+        game.reply(&mut impostor),
+        NetMessage::Error("that is not the guest of this game".to_string())
+    );
+    game.wait_for_close(&mut impostor);
+
+    drop(guest);
+    let mut guest = game.connect();
+    guest
+        .send(&NetMessage::Rejoin {
+            version: PROTOCOL_VERSION,
+            token,
+            name: "Guest".to_string(),
+        })
+        .unwrap();
+    assert_eq!(game.reply(&mut guest), hello("Host"));
+    assert_eq!(game.reply(&mut guest), NetMessage::Session(token));
+    assert!(matches!(game.reply(&mut guest), NetMessage::Game { .. }));
+    assert_eq!(game.host.opponent_name(), "Guest");
+}
+
+#[test]
+fn a_move_out_of_turn_sends_the_game_again() {
+    let mut game = Game::start();
+    // the guest, playing Black, moves for White
+    game.play(false, "e2e4");
+    game.run_until(|game| game.guest_events.len() == 2);
+
+    assert!(game.host_events.iter().any(is_warning));
+    assert_eq!(game.host_board.moves().count(), 0);
+    assert!(matches!(game.guest_events[1], PeerEvent::Game { .. }));
+    assert_eq!(game.guest_board.to_fen(), game.host_board.to_fen());
+
+    // and the game goes on from the host's position
+    game.play(true, "e2e4");
+    game.run_until(|game| game.guest_board.moves().count() == 1);
+    game.play(false, "c7c5");
+    game.run_until(|game| game.host_board.moves().count() == 2);
+}
+
+#[test]
+fn the_guest_cannot_send_the_game() {
+    let mut game = Game::host();
+    let mut guest = game.connect();
+    guest.send(&hello("Guest")).unwrap();
+    assert_eq!(game.reply(&mut guest), hello("Host"));
+    assert!(matches!(game.reply(&mut guest), NetMessage::Session(_)));
+    assert!(matches!(game.reply(&mut guest), NetMessage::Game { .. }));
+
+    let mut board = Board::starting_position();
+    board.make_move(board.parse_move("f2f3").unwrap());
+    guest
+        .send(&NetMessage::Game {
+            side: Team::White,
+            time_control: None,
+            clocks: None,
+            automatic_draw_claims: true,
+            position: Position::from_board(&board),
+        })
+        .unwrap();
+    assert_eq!(
+        game.reply(&mut guest),
+        NetMessage::Error("only the host sends the game".to_string())
+    );
+    assert!(game.host_events.iter().any(is_warning));
+    assert_eq!(game.host_board.moves().count(), 0);
+    assert_eq!(game.host.local_side(), Team::White);
+    assert!(!game.host.automatic_draw_claims());
+}
+
+#[test]
+fn a_rematch_swaps_the_sides() {
+    let mut game = Game::start();
+    game.play(true, "f2f3");
+    game.run_until(|game| game.guest_board.moves().count() == 1);
+    game.play(false, "e7e5");
+    game.run_until(|game| game.host_board.moves().count() == 2);
+
+    let guest = game.guest.as_mut().unwrap();
+    guest.offer_rematch(&game.guest_board);
+    assert_eq!(guest.rematch(), Rematch::Offered);
+    game.run_until(|game| game.host.rematch() == Rematch::Asked);
+    assert!(game
+        .host_events
+        .iter()
+        .any(|event| matches!(event, PeerEvent::Message(NetMessage::OfferRematch))));
+
+    game.host.offer_rematch(&game.host_board);
+    game.run_until(|game| game.guest_events.iter().any(is_rematch));
+    assert!(game.host_events.iter().any(is_rematch));
+    assert_eq!(game.host.local_side(), Team::Black);
+    assert_eq!(game.guest().local_side(), Team::White);
+    assert_eq!(game.host.rematch(), Rematch::None);
+    assert_eq!(game.guest().rematch(), Rematch::None);
+    assert_eq!(
+        game.host_board.to_fen(),
+        Board::starting_position().to_fen()
+    );
+    assert_eq!(game.guest_board.to_fen(), game.host_board.to_fen());
+
+    // the guest opens the rematch
+    game.play(false, "e2e4");
+    game.run_until(|game| game.host_board.moves().count() == 1);
+    game.play(true, "e7e5");
+    game.run_until(|game| game.guest_board.moves().count() == 2);
+    assert!(!game.host_events.iter().any(is_warning));
+    assert!(!game.guest_events.iter().any(is_warning));
+}
+
+#[test]
+fn rematches_offered_at_once_are_sent_to_spectators() {
+    let mut game = Game::start();
+    let mut spectator = Peer::watch(&game.address(), "Spectator");
+    let mut spectator_board = Board::starting_position();
+    let mut spectator_events = Vec::new();
+    game.run_until(|game| {
+        spectator_events.extend(poll(&mut spectator, &mut spectator_board));
+        game.host.spectators() == 1 && spectator.is_ready()
+    });
+    game.play(true, "d2d4");
+    game.run_until(|game| game.guest_board.moves().count() == 1);
+
+    game.host.offer_rematch(&game.host_board);
+    let guest = game.guest.as_mut().unwrap();
+    guest.offer_rematch(&game.guest_board);
+    game.run_until(|game| {
+        spectator_events.extend(poll(&mut spectator, &mut spectator_board));
+        game.guest_events.iter().any(is_rematch) && spectator_events.iter().any(is_rematch)
+    });
+    assert_eq!(game.host.local_side(), Team::Black);
+    assert_eq!(game.guest().local_side(), Team::White);
+    assert_eq!(spectator_board.moves().count(), 0);
+
+    game.play(false, "c2c4");
+    game.run_until(|_| {
+        poll(&mut spectator, &mut spectator_board);
+        spectator_board.moves().count() == 1
+    });
+    assert_eq!(spectator_board.to_fen(), game.guest_board.to_fen());
+}
+
+#[test]
+fn the_host_refuses_other_versions() {
+    let mut game = Game::host();
+    let mut guest = game.connect();
+    guest
+        .send(&NetMessage::Hello {
+            version: PROTOCOL_VERSION + 1,
+            name: "Future".to_string(),
+        })
+        .unwrap();
+    assert_eq!(
+        game.reply(&mut guest),
+        NetMessage::Error(format!(
+            "protocol version {} is not supported",
+            PROTOCOL_VERSION + 1
+        ))
+    );
+    game.wait_for_close(&mut guest);
+    assert!(!game.host.is_ready());
+}
+
+#[test]
+fn the_guest_refuses_other_versions() {
+    let mut listener = Listener::bind(0).unwrap();
+    let address = format!("127.0.0.1:{}", listener.local_addr().port());
+    let mut guest = Peer::join(&address, "Guest");
+    let mut board = Board::starting_position();
+    let deadline = Instant::now() + PATIENCE;
+    let host = loop {
+        poll(&mut guest, &mut board);
+        if let Some(host) = listener.try_accept() {
+            break host;
+        }
+        assert!(Instant::now() < deadline, "the guest did not connect");
+        thread::sleep(SHORT_WAIT);
+    };
+    host.send(&NetMessage::Hello {
+        version: PROTOCOL_VERSION + 1,
+        name: "Future".to_string(),
+    })
+    .unwrap();
+
+    let deadline = Instant::now() + PATIENCE;
+    while !guest.status().contains("protocol version") {
+        assert!(Instant::now() < deadline, "the guest did not refuse");
+        poll(&mut guest, &mut board);
+        thread::sleep(SHORT_WAIT);
+    }
+    assert!(!guest.is_ready());
+}
+
+#[test]
+fn lines_that_are_not_messages_are_skipped() {
+    let mut listener = Listener::bind(0).unwrap();
+    let mut stream = TcpStream::connect(("127.0.0.1", listener.local_addr().port())).unwrap();
+    let mut host = accept(&mut listener);
+    writeln!(stream, "greetings from the future").unwrap();
+    writeln!(stream, "resync").unwrap();
+
+    let deadline = Instant::now() + PATIENCE;
+    let message = loop {
+        if let Some(message) = host.try_receive().unwrap() {
+            break message;
+        }
+        assert!(Instant::now() < deadline, "nothing arrived");
+        thread::sleep(SHORT_WAIT);
+    };
+    assert_eq!(message, NetMessage::Resync);
+}
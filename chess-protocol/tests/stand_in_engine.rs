@@ -0,0 +1,139 @@
+//! Drives the stand-in engine through `EngineProcess` the way the GUI does.
+
+use chess_core::Board;
+use chess_protocol::uci::{EngineMessage, GoParams, GuiCommand};
+use chess_protocol::{EngineProcess, Position};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PATIENCE: Duration = Duration::from_secs(10);
+
+fn launch(args: &[&str]) -> EngineProcess {
+    EngineProcess::launch_with_args(Path::new(env!("CARGO_BIN_EXE_stand-in-engine")), args)
+        .expect("the stand-in engine starts")
+}
+
+fn send(engine: &mut EngineProcess, command: GuiCommand) {
+    engine.send(&command.to_string()).unwrap();
+}
+
+/// Reads messages until one `is_wanted`, and returns it.
+fn wait_for(
+    engine: &mut EngineProcess,
+    is_wanted: impl Fn(&EngineMessage) -> bool,
+) -> EngineMessage {
+    let deadline = Instant::now() + PATIENCE;
+    while Instant::now() < deadline {
+        match engine.try_read_line().unwrap() {
+            Some(line) => {
+                if let Some(message) = EngineMessage::parse(&line).filter(&is_wanted) {
+                    return message;
+                }
+            }
+            None => thread::sleep(Duration::from_millis(5)),
+        }
+    }
+    panic!("the engine did not answer in time");
+}
+
+fn best_move(engine: &mut EngineProcess) -> String {
+    match wait_for(engine, |message| {
+        matches!(message, EngineMessage::BestMove { .. })
+    }) {
+        EngineMessage::BestMove { best, .. } => best,
+        _ => unreachable!(),
+    }
+}
+
+fn position(moves: &[&str]) -> GuiCommand {
+    GuiCommand::Position(Position {
+        fen: None,
+        moves: moves.iter().map(|mv| mv.to_string()).collect(),
+    })
+}
+
+#[test]
+fn plays_its_script_then_its_first_legal_move() {
+    let mut engine = launch(&["e2e4", "g1f3", "e1e3"]);
+    send(&mut engine, GuiCommand::Uci);
+    assert_eq!(
+        wait_for(&mut engine, |message| matches!(
+            message,
+            EngineMessage::IdName(_)
+        )),
+        EngineMessage::IdName("Stand-in".to_string())
+    );
+    wait_for(&mut engine, |message| *message == EngineMessage::UciOk);
+    send(&mut engine, GuiCommand::IsReady);
+    wait_for(&mut engine, |message| *message == EngineMessage::ReadyOk);
+
+    send(&mut engine, position(&[]));
+    send(&mut engine, GuiCommand::Go(GoParams::default()));
+    assert_eq!(best_move(&mut engine), "e2e4");
+
+    send(&mut engine, position(&["e2e4", "e7e5"]));
+    send(&mut engine, GuiCommand::Go(GoParams::default()));
+    assert_eq!(best_move(&mut engine), "g1f3");
+
+    // "e1e3" is not legal, so it falls back to a legal move
+    let moves = ["e2e4", "e7e5", "g1f3", "b8c6"];
+    send(&mut engine, position(&moves));
+    send(&mut engine, GuiCommand::Go(GoParams::default()));
+    let fallback = best_move(&mut engine);
+    let mut board = Board::starting_position();
+    for mv in moves {
+        board.make_move(board.parse_move(mv).unwrap());
+    }
+    assert!(board.parse_move(&fallback).is_some());
+
+    send(&mut engine, GuiCommand::Quit);
+    let deadline = Instant::now() + PATIENCE;
+    while engine.try_read_line().is_ok() {
+        assert!(Instant::now() < deadline, "the engine did not quit");
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[test]
+fn stop_answers_straight_away() {
+    let mut engine = launch(&["--think", "60000", "d2d4"]);
+    send(&mut engine, GuiCommand::Uci);
+    wait_for(&mut engine, |message| *message == EngineMessage::UciOk);
+
+    send(&mut engine, position(&[]));
+    send(
+        &mut engine,
+        GuiCommand::Go(GoParams {
+            infinite: true,
+            ..GoParams::default()
+        }),
+    );
+    thread::sleep(Duration::from_millis(200));
+    while let Some(line) = engine.try_read_line().unwrap() {
+        assert!(
+            !line.starts_with("bestmove"),
+            "answered before stop: {}",
+            line
+        );
+    }
+
+    send(&mut engine, GuiCommand::Stop);
+    assert_eq!(best_move(&mut engine), "d2d4");
+
+    // the move is only sent once, even when stopped again
+    send(&mut engine, GuiCommand::Stop);
+    send(&mut engine, GuiCommand::IsReady);
+    let deadline = Instant::now() + PATIENCE;
+    loop {
+        assert!(
+            Instant::now() < deadline,
+            "the engine did not answer isready"
+        );
+        match engine.try_read_line().unwrap() {
+            Some(line) if line == "readyok" => break,
+            Some(line) => assert!(!line.starts_with("bestmove"), "answered twice: {}", line),
+            None => thread::sleep(Duration::from_millis(5)),
+        }
+    }
+}
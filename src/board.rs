@@ -1,8 +1,9 @@
+use crate::pieces::Team;
 use crate::util::transform_mouse_coords;
-use crate::{get_possible_moves_for_piece, Entity, GameState, KingData, Piece, PieceType, Team};
 use bevy::color::palettes::css;
 use bevy::ecs::component::Component;
-use bevy::prelude::{Color, Vec2};
+use bevy::prelude::{Color, Entity, Vec2};
+use chess_core::Square;
 
 pub(crate) const TILE_SIZE: Vec2 = Vec2::new(80., 80.);
 pub(crate) const HALF_TILE: f32 = TILE_SIZE.x / 2.;
@@ -14,7 +15,6 @@ pub const BOARD_DIMENSION: f32 = 640.0;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Tile {
-    pub(crate) team: Team,
     pub(crate) position: Position,
     pub(crate) piece: Option<Entity>,
 }
@@ -45,7 +45,6 @@ pub struct Position {
 
 pub fn init_board() -> [[Tile; 8]; 8] {
     [[Tile {
-        team: Team::None,
         position: Position {
             position_label: PositionLabel {
                 col_label: ColLabel::A,
@@ -57,48 +56,6 @@ pub fn init_board() -> [[Tile; 8]; 8] {
     }; 8]; 8]
 }
 
-pub fn default_king_data() -> KingData {
-    KingData {
-        position: Position {
-            position_label: PositionLabel {
-                col_label: ColLabel::A,
-                row_label: 0,
-            },
-            coordinates: Vec2::ZERO,
-        },
-        available_moves: Vec::new(),
-    }
-}
-
-pub fn init_king_positions(
-    piece_type: PieceType,
-    team: Team,
-    game_state: &mut GameState,
-    pos: Position,
-) -> bool {
-    if piece_type == PieceType::King {
-        if team == Team::White {
-            game_state.white_king_data.position = pos;
-        } else {
-            game_state.black_king_data.position = pos;
-        }
-        return true;
-    }
-    false
-}
-
-pub fn update_king_data(piece: &Piece, game_state: &mut GameState, pos: Position) {
-    if init_king_positions(piece.piece_type, piece.team, game_state, pos) {
-        if piece.team == Team::White {
-            game_state.white_king_data.available_moves =
-                get_possible_moves_for_piece(piece, &game_state.board);
-        } else {
-            game_state.black_king_data.available_moves =
-                get_possible_moves_for_piece(piece, &game_state.board);
-        }
-    }
-}
-
 pub fn get_tile_color(row: &u8, column: &u8) -> Color {
     if row.is_multiple_of(2) {
         if column.is_multiple_of(2) {
@@ -112,13 +69,28 @@ pub fn get_tile_color(row: &u8, column: &u8) -> Color {
     TILE_DARK
 }
 
-pub fn index_for_pos(pos_label: PositionLabel) -> (usize, usize) {
-    (
-        (pos_label.row_label - 1) as usize,
-        pos_label.col_label as usize,
+/// Where the centre of the tile in `row` and `column` is drawn when `bottom`'s
+/// side of the board is at the bottom of the screen.
+pub fn tile_coordinates(row: u8, column: u8, bottom: Team) -> Vec2 {
+    let (row, column) = match bottom {
+        Team::White => (row, column),
+        Team::Black => (NUM_ROWS - 1 - row, NUM_COLUMNS - 1 - column),
+    };
+    let offset: f32 = -(BOARD_DIMENSION / 2.) + HALF_TILE;
+    Vec2::new(
+        offset + column as f32 * TILE_SIZE.x,
+        offset + row as f32 * TILE_SIZE.y,
     )
 }
 
+pub fn square_for_label(pos_label: PositionLabel) -> Square {
+    Square::new(pos_label.col_label as u8, pos_label.row_label - 1)
+}
+
+pub fn index_for_square(square: Square) -> (usize, usize) {
+    (square.rank() as usize, square.file() as usize)
+}
+
 pub fn get_pos_label(row: u8, column: &u8) -> (ColLabel, u8) {
     let column_position: ColLabel = match column {
         0 => ColLabel::A,
@@ -151,21 +123,3 @@ pub fn check_bounds(x_coord: f32, y_coord: f32, mouse_coords: Vec2) -> bool {
     }
     false
 }
-
-pub fn simulate_move(
-    board: &mut [[Tile; 8]; 8],
-    entity: Entity,
-    team: Team,
-    piece_pos: PositionLabel,
-    goal_pos: PositionLabel,
-) {
-    let (old_row, old_col) = index_for_pos(piece_pos);
-    let (new_row, new_col) = index_for_pos(goal_pos);
-    let new_tile: &mut Tile = &mut board[new_row][new_col];
-
-    new_tile.team = team;
-    new_tile.piece = Option::from(entity);
-
-    board[old_row][old_col].team = Team::None;
-    board[old_row][old_col].piece = None;
-}
@@ -0,0 +1,315 @@
+use crate::game::{GamePhase, GameResult, GameStatus};
+use crate::network::NetworkGame;
+use crate::{GameState, TEXT_COLOR};
+use bevy::prelude::*;
+use chess_core::{Bonus, Clock, Stage, Team, TimeControl};
+use std::time::Duration;
+
+const RUNNING_CLOCK_COLOR: Color = Color::srgb(0.35, 0.75, 0.35);
+const FLAGGED_CLOCK_COLOR: Color = Color::srgb(0.86, 0.08, 0.24);
+// under this much time the clock shows tenths of a second
+const LOW_TIME: Duration = Duration::from_secs(10);
+
+/// The time control new games are played with, picked in the settings.
+#[derive(Resource, Component, Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum ClockSetting {
+    #[default]
+    Untimed,
+    SuddenDeath,
+    Blitz,
+    Rapid,
+    Bronstein,
+    UsDelay,
+    Classical,
+}
+
+impl ClockSetting {
+    pub const ALL: [ClockSetting; 7] = [
+        ClockSetting::Untimed,
+        ClockSetting::SuddenDeath,
+        ClockSetting::Blitz,
+        ClockSetting::Rapid,
+        ClockSetting::Bronstein,
+        ClockSetting::UsDelay,
+        ClockSetting::Classical,
+    ];
+
+    pub fn time_control(self) -> Option<TimeControl> {
+        let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+        let seconds = Duration::from_secs;
+        let control = match self {
+            ClockSetting::Untimed => return None,
+            ClockSetting::SuddenDeath => TimeControl::sudden_death(minutes(10)),
+            ClockSetting::Blitz => {
+                TimeControl::sudden_death(minutes(3)).with_bonus(Bonus::Fischer(seconds(2)))
+            }
+            ClockSetting::Rapid => {
+                TimeControl::sudden_death(minutes(15)).with_bonus(Bonus::Fischer(seconds(10)))
+            }
+            ClockSetting::Bronstein => {
+                TimeControl::sudden_death(minutes(5)).with_bonus(Bonus::Bronstein(seconds(3)))
+            }
+            ClockSetting::UsDelay => {
+                TimeControl::sudden_death(minutes(5)).with_bonus(Bonus::UsDelay(seconds(5)))
+            }
+            ClockSetting::Classical => TimeControl {
+                stages: vec![
+                    Stage {
+                        moves: Some(40),
+                        time: minutes(90),
+                    },
+                    Stage {
+                        moves: None,
+                        time: minutes(30),
+                    },
+                ],
+                bonus: Bonus::Fischer(seconds(30)),
+            },
+        };
+        Some(control)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ClockSetting::Untimed => "Untimed",
+            ClockSetting::SuddenDeath => "10 min",
+            ClockSetting::Blitz => "3 min + 2 s",
+            ClockSetting::Rapid => "15 min + 10 s",
+            ClockSetting::Bronstein => "5 min, 3 s Bronstein",
+            ClockSetting::UsDelay => "5 min, 5 s delay",
+            ClockSetting::Classical => "40/90 min, 30 min + 30 s",
+        }
+    }
+}
+
+/// The clocks of a timed game, present while it is being played.
+#[derive(Resource)]
+pub struct GameClock {
+    pub(crate) clock: Clock,
+    /// How many moves had been played when the clock was last looked at, so
+    /// new moves can press it.
+    plies: usize,
+    /// Times to put on the clock once the moves so far have pressed it.
+    corrections: Vec<(Team, Duration)>,
+}
+
+impl GameClock {
+    /// Sets the time `team` has left, after the clock has been pressed for any
+    /// move it hasn't seen yet.
+    pub(crate) fn correct(&mut self, team: Team, remaining: Duration) {
+        self.corrections.push((team, remaining));
+    }
+}
+
+/// The systems that run the clock, for others that read it after it has seen
+/// the latest move or set it before.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClockSystems;
+
+#[derive(Component)]
+struct ClockText(Team);
+
+pub fn clock_plugin(app: &mut App) {
+    app.init_resource::<ClockSetting>()
+        .add_systems(OnEnter(GameStatus::Game), clock_setup)
+        .add_systems(
+            Update,
+            (clock_system, clock_text_system)
+                .chain()
+                .in_set(ClockSystems)
+                // the other player's copy of a game over the network doesn't
+                // pause with this one, so neither do its clocks
+                .run_if(
+                    in_state(GameStatus::Game)
+                        .and(resource_exists::<GameClock>)
+                        .and(in_state(GamePhase::Playing).or(resource_exists::<NetworkGame>)),
+                ),
+        )
+        .add_systems(OnExit(GameStatus::GameOver), remove_clock);
+}
+
+fn clock_setup(
+    mut commands: Commands,
+    setting: Res<ClockSetting>,
+    network_game: Option<Res<NetworkGame>>,
+) {
+    // a game over the network is played with the host's time control
+    let control = match network_game {
+        Some(network_game) => network_game.time_control().cloned(),
+        None => setting.time_control(),
+    };
+    let Some(control) = control else {
+        commands.remove_resource::<GameClock>();
+        return;
+    };
+    commands.insert_resource(GameClock {
+        clock: Clock::new(control),
+        plies: 0,
+        corrections: Vec::new(),
+    });
+
+    // Black's clock sits level with the top of the board and White's with the bottom
+    for (team, node) in [
+        (
+            Team::Black,
+            Node {
+                top: px(60),
+                ..default()
+            },
+        ),
+        (
+            Team::White,
+            Node {
+                bottom: px(60),
+                ..default()
+            },
+        ),
+    ] {
+        commands.spawn((
+            DespawnOnExit(GameStatus::Game),
+            ClockText(team),
+            Text::new(""),
+            TextFont {
+                font_size: 40.0,
+                ..default()
+            },
+            TextColor(TEXT_COLOR),
+            Node {
+                position_type: PositionType::Absolute,
+                right: px(40),
+                ..node
+            },
+        ));
+    }
+}
+
+/// Runs the clock of the side to move on Bevy's `Time`, presses it for each
+/// move made since the last frame, and ends the game when a flag falls.
+fn clock_system(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut game_clock: ResMut<GameClock>,
+    game_state: Res<GameState>,
+    mut next_status: ResMut<NextState<GameStatus>>,
+) {
+    let board = &game_state.board;
+    let plies = board.moves().count();
+    let side_to_move = board.side_to_move();
+    // the last move was the other side's, the one before it this side's
+    for ply in (0..plies.saturating_sub(game_clock.plies)).rev() {
+        let mover = if ply % 2 == 0 {
+            side_to_move.opponent()
+        } else {
+            side_to_move
+        };
+        game_clock.clock.press(mover);
+    }
+    game_clock.plies = plies;
+    for (team, remaining) in std::mem::take(&mut game_clock.corrections) {
+        game_clock.clock.set_remaining(team, remaining);
+    }
+
+    if let Some(flagged) = game_clock.clock.tick(side_to_move, time.delta()) {
+        let outcome = board.timeout_outcome(flagged);
+        info!("{}", outcome);
+        commands.insert_resource(GameResult(outcome));
+        next_status.set(GameStatus::GameOver);
+    }
+}
+
+fn clock_text_system(
+    game_clock: Res<GameClock>,
+    game_state: Res<GameState>,
+    mut query: Query<(&ClockText, &mut Text, &mut TextColor)>,
+) {
+    let clock = &game_clock.clock;
+    for (ClockText(team), mut text, mut color) in &mut query {
+        let remaining = clock.remaining(*team);
+        text.0 = format_time(remaining);
+        let delay = clock.delay_left(*team);
+        if !delay.is_zero() && *team == game_state.board.side_to_move() {
+            text.0
+                .push_str(&format!(" ({}s)", delay.as_secs_f32().ceil()));
+        }
+        color.0 = if clock.flagged() == Some(*team) {
+            FLAGGED_CLOCK_COLOR
+        } else if *team == game_state.board.side_to_move() {
+            RUNNING_CLOCK_COLOR
+        } else {
+            TEXT_COLOR
+        };
+    }
+}
+
+/// "1:05:00", "4:59", or "0:09.3" when time is short.
+fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else if time < LOW_TIME {
+        format!("{}:{:02}.{}", minutes, seconds, time.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+fn remove_clock(mut commands: Commands) {
+    commands.remove_resource::<GameClock>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::init_board;
+    use crate::game::PieceInput;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+    use chess_core::{Board, GameOutcome, WinReason};
+
+    #[test]
+    fn a_falling_flag_ends_the_game() {
+        let step = Duration::from_secs(60);
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .init_state::<GameStatus>()
+            .add_sub_state::<GamePhase>()
+            .insert_resource(ClockSetting::SuddenDeath)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(step))
+            .insert_resource(GameState {
+                board: Board::starting_position(),
+                tiles: init_board(),
+                highlight_coords: Vec2::ZERO,
+                piece_input: PieceInput::Idle,
+                pending_promotion: None,
+                undone_moves: Vec::new(),
+            })
+            .add_plugins(clock_plugin);
+        app.world_mut()
+            .resource_mut::<Time<Virtual>>()
+            .set_max_delta(step);
+        app.world_mut()
+            .resource_mut::<NextState<GameStatus>>()
+            .set(GameStatus::Game);
+
+        // ten minutes for White, who never moves
+        for _ in 0..12 {
+            app.update();
+        }
+        assert_eq!(
+            *app.world().resource::<State<GameStatus>>().get(),
+            GameStatus::GameOver
+        );
+        assert_eq!(
+            app.world().resource::<GameResult>().0,
+            GameOutcome::Decisive {
+                winner: Team::Black,
+                reason: WinReason::Timeout,
+            }
+        );
+        assert_eq!(
+            app.world().resource::<GameClock>().clock.flagged(),
+            Some(Team::White)
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use crate::external::ExternalEngine;
+use crate::game::{
+    respawn_pieces, DrawRules, GamePhase, GameStatus, ImageCache, PlayerKind, Players,
+};
+use crate::offer::{DrawOffer, Offer};
+use crate::takeback::Takeback;
+use crate::{GameState, Piece, TEXT_COLOR};
+use bevy::prelude::*;
+use bevy::tasks::futures::check_ready;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use chess_core::{search_until_stopped, Board, SearchLimits, SearchResult};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The computer takes a draw when its search has it at least this far behind,
+/// in centipawns.
+const DRAW_ACCEPT_SCORE: i32 = -25;
+
+/// How strongly the computer plays. The easier levels search to a fixed depth,
+/// the harder ones for a fixed time on every move.
+#[derive(Resource, Component, Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Medium,
+        Difficulty::Hard,
+        Difficulty::Expert,
+    ];
+
+    pub fn limits(self) -> SearchLimits {
+        match self {
+            Difficulty::Easy => SearchLimits::depth(1),
+            Difficulty::Medium => SearchLimits::depth(3),
+            Difficulty::Hard => SearchLimits::time(Duration::from_secs(1)),
+            Difficulty::Expert => SearchLimits::time(Duration::from_secs(3)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::Expert => "Expert",
+        }
+    }
+}
+
+/// A search running on the async compute pool for the computer's next move,
+/// along with the position it was started from. Removing the resource cancels
+/// the search.
+#[derive(Resource)]
+struct EngineSearch {
+    task: Task<SearchResult>,
+    stop: Arc<AtomicBool>,
+    hash: u64,
+    ply: usize,
+}
+
+impl EngineSearch {
+    /// Whether the search was started from the position on `board`. Undo and
+    /// redo can change the position while the search is still running.
+    fn is_for(&self, board: &Board) -> bool {
+        self.hash == board.hash() && self.ply == board.moves().count()
+    }
+}
+
+impl Drop for EngineSearch {
+    fn drop(&mut self) {
+        // dropping the task only cancels it between polls, so the search itself
+        // has to be told to stop
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[derive(Component)]
+struct ThinkingText;
+
+pub fn engine_plugin(app: &mut App) {
+    app.init_resource::<Difficulty>()
+        .add_systems(OnEnter(GameStatus::Game), thinking_text_setup)
+        .add_systems(
+            Update,
+            (
+                cancel_search_on_takeback,
+                poll_search_system,
+                start_search_system,
+                thinking_text_system,
+            )
+                .chain()
+                .run_if(in_state(GamePhase::Playing)),
+        )
+        .add_systems(OnExit(GameStatus::Game), cancel_search);
+}
+
+fn thinking_text_setup(mut commands: Commands) {
+    commands.spawn((
+        DespawnOnExit(GameStatus::Game),
+        ThinkingText,
+        Text::new("Thinking…"),
+        TextFont {
+            font_size: 28.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            left: px(20),
+            top: px(20),
+            ..default()
+        },
+        Visibility::Hidden,
+    ));
+}
+
+/// Starts a search on the async compute pool whenever it is the computer's turn
+/// in a game that is still going, unless an external engine is playing instead.
+fn start_search_system(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    difficulty: Res<Difficulty>,
+    draw_rules: Res<DrawRules>,
+    engine_search: Option<Res<EngineSearch>>,
+    external_engine: Option<Res<ExternalEngine>>,
+) {
+    let board = &game_state.board;
+    if engine_search.is_some()
+        || external_engine.is_some()
+        || players.kind(board.side_to_move()) != PlayerKind::Computer
+        || game_state.pending_promotion.is_some()
+        || board.outcome(draw_rules.automatic_draw_claims).is_some()
+    {
+        return;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let limits = difficulty.limits();
+    let task_board = board.clone();
+    let task_stop = stop.clone();
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { search_until_stopped(&task_board, limits, &task_stop, |_| {}) });
+    commands.insert_resource(EngineSearch {
+        task,
+        stop,
+        hash: board.hash(),
+        ply: board.moves().count(),
+    });
+}
+
+/// Plays the computer's move once its search has finished, unless the position
+/// has changed since the search started. A draw offered to the computer is
+/// answered then, from what the search made of the position.
+fn poll_search_system(
+    mut commands: Commands,
+    mut game_state: ResMut<GameState>,
+    engine_search: Option<ResMut<EngineSearch>>,
+    draw_offer: Option<Res<DrawOffer>>,
+    mut offer_writer: MessageWriter<Offer>,
+    image_cache: Res<ImageCache>,
+    query_pieces: Query<Entity, With<Piece>>,
+) {
+    let Some(mut engine_search) = engine_search else {
+        return;
+    };
+    let Some(result) = check_ready(&mut engine_search.task) else {
+        return;
+    };
+    commands.remove_resource::<EngineSearch>();
+
+    let Some(mv) = result.best_move else {
+        return;
+    };
+    if !engine_search.is_for(&game_state.board) {
+        return;
+    }
+    let side = game_state.board.side_to_move();
+    if draw_offer.is_some_and(|offer| offer.by == side.opponent()) {
+        if result.score <= DRAW_ACCEPT_SCORE {
+            offer_writer.write(Offer::AcceptDraw(side));
+            return;
+        }
+        offer_writer.write(Offer::DeclineDraw(side));
+    }
+    info!(
+        "Computer plays {} (score {}, depth {}, {} nodes)",
+        game_state.board.san(mv),
+        result.score,
+        result.depth,
+        result.nodes
+    );
+    game_state.play_move(mv);
+    respawn_pieces(&mut commands, &image_cache, &mut game_state, &query_pieces);
+}
+
+fn cancel_search_on_takeback(mut commands: Commands, mut takeback_reader: MessageReader<Takeback>) {
+    if takeback_reader.read().count() > 0 {
+        commands.remove_resource::<EngineSearch>();
+    }
+}
+
+fn cancel_search(mut commands: Commands) {
+    commands.remove_resource::<EngineSearch>();
+}
+
+fn thinking_text_system(
+    engine_search: Option<Res<EngineSearch>>,
+    external_engine: Option<Res<ExternalEngine>>,
+    mut query: Query<&mut Visibility, With<ThinkingText>>,
+) {
+    let thinking =
+        engine_search.is_some() || external_engine.is_some_and(|engine| engine.is_thinking());
+    for mut visibility in &mut query {
+        *visibility = if thinking {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
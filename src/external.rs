@@ -0,0 +1,525 @@
+use crate::engine::Difficulty;
+use crate::game::{
+    respawn_pieces, DrawRules, GamePhase, GameStatus, ImageCache, PlayerKind, Players,
+};
+use crate::offer::{DrawOffer, Offer};
+use crate::takeback::Takeback;
+use crate::{GameState, Piece};
+use bevy::prelude::*;
+use chess_core::{Board, Move, SearchLimits, Team};
+use chess_protocol::uci::{EngineMessage, GoParams, GuiCommand};
+use chess_protocol::xboard::{XboardCommand, XboardOutput};
+use chess_protocol::{EngineProcess, Position};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long a CECP engine gets to send its features before it is taken to be
+/// one that predates them.
+const FEATURE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long a UCI engine gets to answer `uci` with `uciok` before it is given
+/// up on.
+const UCIOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The protocols engine programs are driven over.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EngineProtocol {
+    Uci,
+    /// The Chess Engine Communication Protocol of XBoard and WinBoard.
+    Xboard,
+}
+
+/// An engine program playing the computer's side, in place of the built-in
+/// search. It lives from the menu that launches it until the player goes back
+/// to the menu, so rematches reuse it.
+#[derive(Resource)]
+pub struct ExternalEngine {
+    process: EngineProcess,
+    pub(crate) name: String,
+    protocol: ProtocolState,
+    ready: bool,
+    /// The hash and ply of the position the engine is searching, if it is.
+    search: Option<(u64, usize)>,
+    /// Whether a search was stopped and its move, which is for a position
+    /// that is gone, may yet arrive. No new search starts until it has.
+    stopping: bool,
+}
+
+/// What a line from the engine means for the game.
+enum EngineReply {
+    Move(String),
+    Resign,
+    OfferDraw,
+}
+
+enum ProtocolState {
+    Uci(UciState),
+    Xboard(XboardState),
+}
+
+/// How far a UCI engine is through starting up. Until it has answered `uci`
+/// with `uciok` it may not be sent anything else, so a new game started
+/// before then waits for it.
+struct UciState {
+    /// When to give up on `uciok`, until it arrives.
+    uciok_deadline: Option<Instant>,
+    /// Whether `ucinewgame` is waiting for `uciok`.
+    new_game: bool,
+}
+
+/// What a CECP engine has said it supports, and the game as it knows it.
+/// Unlike UCI, CECP engines keep the game themselves and are sent one move at
+/// a time. They are kept in force mode and told to `go` on their turns.
+struct XboardState {
+    /// When to stop waiting for `feature done=1`, unless the engine asked for
+    /// more time with `done=0`.
+    feature_deadline: Option<Instant>,
+    usermove: bool,
+    ping: bool,
+    /// The position the engine has, or `None` if it has to be set up again.
+    known: Option<Position>,
+    last_ping: u32,
+}
+
+impl ExternalEngine {
+    pub fn launch(path: &Path, protocol: EngineProtocol) -> io::Result<ExternalEngine> {
+        let mut process = EngineProcess::launch(path)?;
+        let protocol = match protocol {
+            EngineProtocol::Uci => {
+                process.send(&GuiCommand::Uci.to_string())?;
+                ProtocolState::Uci(UciState {
+                    uciok_deadline: Some(Instant::now() + UCIOK_TIMEOUT),
+                    new_game: false,
+                })
+            }
+            EngineProtocol::Xboard => {
+                process.send(&XboardCommand::Xboard.to_string())?;
+                process.send(&XboardCommand::Protover(2).to_string())?;
+                ProtocolState::Xboard(XboardState {
+                    feature_deadline: Some(Instant::now() + FEATURE_TIMEOUT),
+                    usermove: false,
+                    ping: false,
+                    known: None,
+                    last_ping: 0,
+                })
+            }
+        };
+        let name = path.file_stem().map_or_else(
+            || "Engine".to_string(),
+            |stem| stem.to_string_lossy().into(),
+        );
+        Ok(ExternalEngine {
+            process,
+            name,
+            protocol,
+            ready: false,
+            search: None,
+            stopping: false,
+        })
+    }
+
+    pub fn is_thinking(&self) -> bool {
+        self.search.is_some()
+    }
+
+    fn is_ready(&self) -> bool {
+        match &self.protocol {
+            ProtocolState::Xboard(xboard) if !self.ready => xboard
+                .feature_deadline
+                .is_some_and(|deadline| Instant::now() >= deadline),
+            _ => self.ready,
+        }
+    }
+
+    /// Whether the engine never finished starting up.
+    fn timed_out(&self) -> bool {
+        match &self.protocol {
+            ProtocolState::Uci(uci) => uci
+                .uciok_deadline
+                .is_some_and(|deadline| Instant::now() >= deadline),
+            ProtocolState::Xboard(_) => false,
+        }
+    }
+
+    fn send(&mut self, command: impl ToString) {
+        let line = command.to_string();
+        if let Err(error) = self.process.send(&line) {
+            warn!("Could not send '{}' to {}: {}", line, self.name, error);
+        }
+    }
+
+    fn new_game(&mut self) {
+        match &mut self.protocol {
+            ProtocolState::Uci(uci) => {
+                self.ready = false;
+                if uci.uciok_deadline.is_some() {
+                    uci.new_game = true;
+                } else {
+                    self.send(GuiCommand::UciNewGame);
+                    self.send(GuiCommand::IsReady);
+                }
+            }
+            ProtocolState::Xboard(xboard) => xboard.known = None,
+        }
+    }
+
+    /// Asks for a move in the position on `board`.
+    fn start_search(&mut self, board: &Board, limits: SearchLimits) {
+        match self.protocol {
+            ProtocolState::Uci(_) => {
+                self.send(GuiCommand::Position(Position::from_board(board)));
+                self.send(GuiCommand::Go(go_params(limits)));
+            }
+            ProtocolState::Xboard(_) => {
+                self.send_position(board);
+                match limits.time {
+                    Some(time) => {
+                        let seconds = time.as_secs_f32().ceil().max(1.0) as u32;
+                        self.send(XboardCommand::St(seconds));
+                    }
+                    None => self.send(XboardCommand::Sd(limits.depth)),
+                }
+                self.send(XboardCommand::Go);
+            }
+        }
+        self.search = Some(position_key(board));
+    }
+
+    /// Brings a CECP engine's game up to `board`: just the new moves if it
+    /// has the game so far, otherwise the whole game from a new one.
+    fn send_position(&mut self, board: &Board) {
+        let ProtocolState::Xboard(xboard) = &mut self.protocol else {
+            return;
+        };
+        let position = Position::from_board(board);
+        let known_moves = match &xboard.known {
+            Some(known)
+                if known.fen == position.fen && position.moves.starts_with(&known.moves) =>
+            {
+                Some(known.moves.len())
+            }
+            _ => None,
+        };
+        let mut commands = Vec::new();
+        if known_moves.is_none() {
+            commands.push(XboardCommand::New);
+            commands.push(XboardCommand::Force);
+            if let Some(fen) = &position.fen {
+                commands.push(XboardCommand::SetBoard(fen.clone()));
+            }
+        }
+        for text in &position.moves[known_moves.unwrap_or(0)..] {
+            commands.push(XboardCommand::UserMove(text.clone()));
+        }
+        let usermove = xboard.usermove;
+        xboard.known = Some(position);
+
+        for command in commands {
+            match command {
+                // engines that haven't asked for `usermove` take bare moves
+                XboardCommand::UserMove(text) if !usermove => self.send(text),
+                command => self.send(command),
+            }
+        }
+    }
+
+    fn cancel_search(&mut self) {
+        if self.search.take().is_none() {
+            return;
+        }
+        match &mut self.protocol {
+            ProtocolState::Uci(_) => {
+                self.send(GuiCommand::Stop);
+                self.stopping = true;
+            }
+            ProtocolState::Xboard(xboard) => {
+                // force mode stops the search, but the engine may have moved
+                // first, so its game is set up again next time
+                xboard.known = None;
+                let ping = xboard.ping.then(|| {
+                    xboard.last_ping += 1;
+                    xboard.last_ping
+                });
+                self.send(XboardCommand::Force);
+                // without `ping` there is no telling when a late move has been
+                // and gone, and the position check has to catch it
+                if let Some(number) = ping {
+                    self.send(XboardCommand::Ping(number));
+                    self.stopping = true;
+                }
+            }
+        }
+    }
+
+    /// Tells the engine its opponent offers a draw. UCI has no way to, so UCI
+    /// engines turn every offer down by moving.
+    fn offer_draw(&mut self) {
+        if let ProtocolState::Xboard(_) = self.protocol {
+            self.send(XboardCommand::Draw);
+        }
+    }
+
+    /// Acts on a line the engine printed. Returns the move it played, if the
+    /// line was one and isn't from a search that was stopped, or what it said
+    /// about ending the game.
+    fn read_reply(&mut self, line: &str) -> Option<EngineReply> {
+        match &mut self.protocol {
+            ProtocolState::Uci(uci) => match EngineMessage::parse(line)? {
+                EngineMessage::IdName(name) => self.name = name,
+                EngineMessage::UciOk if uci.uciok_deadline.is_some() => {
+                    uci.uciok_deadline = None;
+                    if std::mem::take(&mut uci.new_game) {
+                        self.send(GuiCommand::UciNewGame);
+                        self.send(GuiCommand::IsReady);
+                    }
+                }
+                EngineMessage::ReadyOk => self.ready = true,
+                EngineMessage::Info(info) => debug!("{}: {}", self.name, info),
+                EngineMessage::BestMove { best, .. } => {
+                    if self.stopping {
+                        self.stopping = false;
+                        return None;
+                    }
+                    return Some(EngineReply::Move(best));
+                }
+                _ => {}
+            },
+            ProtocolState::Xboard(xboard) => match XboardOutput::parse(line)? {
+                XboardOutput::Feature(features) => {
+                    let mut replies = Vec::new();
+                    for (name, value) in features {
+                        match name.as_str() {
+                            "myname" => self.name = value.clone(),
+                            "usermove" => xboard.usermove = value == "1",
+                            "ping" => xboard.ping = value == "1",
+                            "done" if value == "1" => self.ready = true,
+                            "done" => xboard.feature_deadline = None,
+                            _ => {}
+                        }
+                        // moves are always sent in coordinate notation
+                        replies.push(if name == "san" && value == "1" {
+                            XboardCommand::Rejected(name)
+                        } else {
+                            XboardCommand::Accepted(name)
+                        });
+                    }
+                    for reply in replies {
+                        self.send(reply);
+                    }
+                }
+                XboardOutput::Pong(number) => {
+                    if number == xboard.last_ping {
+                        self.stopping = false;
+                    }
+                }
+                XboardOutput::Move(text) => {
+                    if self.stopping {
+                        return None;
+                    }
+                    // keep the engine from carrying on by itself
+                    self.send(XboardCommand::Force);
+                    return Some(EngineReply::Move(text));
+                }
+                XboardOutput::IllegalMove(text) => {
+                    warn!("{} rejected the move {}", self.name, text);
+                    xboard.known = None;
+                }
+                XboardOutput::Error { kind, command } => {
+                    warn!("{} could not run '{}': {}", self.name, command, kind);
+                }
+                XboardOutput::Resign => {
+                    info!("{} resigns", self.name);
+                    return Some(EngineReply::Resign);
+                }
+                XboardOutput::OfferDraw => {
+                    info!("{} offers a draw", self.name);
+                    return Some(EngineReply::OfferDraw);
+                }
+                XboardOutput::Result { result, comment } => {
+                    debug!("{} claims {} {{{}}}", self.name, result, comment);
+                }
+                XboardOutput::Thinking(_) => debug!("{}: {}", self.name, line),
+            },
+        }
+        None
+    }
+
+    /// Notes that the move the engine sent has been played, so it isn't sent
+    /// back to a CECP engine, or that it wasn't, so the engine's game has to
+    /// be set up again.
+    fn engine_moved(&mut self, mv: Option<Move>) {
+        if let ProtocolState::Xboard(xboard) = &mut self.protocol {
+            match (mv, &mut xboard.known) {
+                (Some(mv), Some(known)) => known.moves.push(mv.to_string()),
+                _ => xboard.known = None,
+            }
+        }
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        match self.protocol {
+            ProtocolState::Uci(_) => self.send(GuiCommand::Quit),
+            ProtocolState::Xboard(_) => self.send(XboardCommand::Quit),
+        }
+    }
+}
+
+pub fn external_engine_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameStatus::Game), new_game_system)
+        .add_systems(
+            Update,
+            (
+                cancel_search_on_takeback,
+                engine_reply_system,
+                offer_draw_system,
+                start_search_system,
+            )
+                .chain()
+                .run_if(in_state(GamePhase::Playing).and(resource_exists::<ExternalEngine>)),
+        )
+        .add_systems(OnExit(GameStatus::Game), cancel_search)
+        .add_systems(OnEnter(GameStatus::Menu), quit_engine);
+}
+
+fn new_game_system(engine: Option<ResMut<ExternalEngine>>) {
+    if let Some(mut engine) = engine {
+        engine.new_game();
+    }
+}
+
+/// Reads what the engine has printed and plays its move when one arrives for
+/// the position on the board. Resignations and draw offers are passed on for
+/// the computer's side.
+fn engine_reply_system(
+    mut commands: Commands,
+    mut engine: ResMut<ExternalEngine>,
+    mut game_state: ResMut<GameState>,
+    players: Res<Players>,
+    mut offer_writer: MessageWriter<Offer>,
+    image_cache: Res<ImageCache>,
+    query_pieces: Query<Entity, With<Piece>>,
+) {
+    if engine.timed_out() {
+        warn!("{} did not answer 'uci'", engine.name);
+        commands.remove_resource::<ExternalEngine>();
+        return;
+    }
+    let computer = [Team::White, Team::Black]
+        .into_iter()
+        .find(|side| players.kind(*side) == PlayerKind::Computer);
+    loop {
+        let line = match engine.process.try_read_line() {
+            Ok(Some(line)) => line,
+            Ok(None) => return,
+            Err(error) => {
+                warn!("{} stopped responding: {}", engine.name, error);
+                commands.remove_resource::<ExternalEngine>();
+                return;
+            }
+        };
+        let best = match (engine.read_reply(&line), computer) {
+            (Some(EngineReply::Move(best)), _) => best,
+            (Some(EngineReply::Resign), Some(side)) => {
+                offer_writer.write(Offer::Resign(side));
+                continue;
+            }
+            (Some(EngineReply::OfferDraw), Some(side)) => {
+                offer_writer.write(Offer::Draw(side));
+                continue;
+            }
+            _ => continue,
+        };
+        // the reply to the one search running, for whatever position
+        let searched = engine.search.take();
+        if searched != Some(position_key(&game_state.board)) {
+            engine.engine_moved(None);
+            continue;
+        }
+        let Some(mv) = game_state.board.read_move(&best) else {
+            warn!("{} played an illegal move: {}", engine.name, best);
+            engine.engine_moved(None);
+            continue;
+        };
+        info!("{} plays {}", engine.name, game_state.board.san(mv));
+        engine.engine_moved(Some(mv));
+        game_state.play_move(mv);
+        respawn_pieces(&mut commands, &image_cache, &mut game_state, &query_pieces);
+    }
+}
+
+/// Passes a draw offered to the computer on to the engine, which accepts by
+/// offering one back.
+fn offer_draw_system(
+    mut engine: ResMut<ExternalEngine>,
+    draw_offer: Option<Res<DrawOffer>>,
+    players: Res<Players>,
+) {
+    let Some(draw_offer) = draw_offer else {
+        return;
+    };
+    if draw_offer.is_added() && players.kind(draw_offer.by.opponent()) == PlayerKind::Computer {
+        engine.offer_draw();
+    }
+}
+
+/// Sends the engine the position and asks for a move whenever it is the
+/// computer's turn.
+fn start_search_system(
+    mut engine: ResMut<ExternalEngine>,
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    difficulty: Res<Difficulty>,
+    draw_rules: Res<DrawRules>,
+) {
+    let board = &game_state.board;
+    if !engine.is_ready()
+        || engine.stopping
+        || engine.search.is_some()
+        || players.kind(board.side_to_move()) != PlayerKind::Computer
+        || game_state.pending_promotion.is_some()
+        || board.outcome(draw_rules.automatic_draw_claims).is_some()
+    {
+        return;
+    }
+
+    engine.start_search(board, difficulty.limits());
+}
+
+fn position_key(board: &Board) -> (u64, usize) {
+    (board.hash(), board.moves().count())
+}
+
+/// The same depth or time the built-in search would get at this difficulty.
+fn go_params(limits: SearchLimits) -> GoParams {
+    match limits.time {
+        Some(time) => GoParams {
+            movetime: Some(time.as_millis() as u64),
+            ..default()
+        },
+        None => GoParams {
+            depth: Some(limits.depth),
+            ..default()
+        },
+    }
+}
+
+fn cancel_search_on_takeback(
+    mut engine: ResMut<ExternalEngine>,
+    mut takeback_reader: MessageReader<Takeback>,
+) {
+    if takeback_reader.read().count() > 0 {
+        engine.cancel_search();
+    }
+}
+
+fn cancel_search(engine: Option<ResMut<ExternalEngine>>) {
+    if let Some(mut engine) = engine {
+        engine.cancel_search();
+    }
+}
+
+fn quit_engine(mut commands: Commands) {
+    commands.remove_resource::<ExternalEngine>();
+}
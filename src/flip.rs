@@ -0,0 +1,131 @@
+use crate::board::tile_coordinates;
+use crate::game::{
+    show_position, spawn_coordinate_labels, BoardOrientation, CoordinateLabel, GamePhase,
+    GameStatus, ImageCache,
+};
+use crate::history::{shown_board, HistoryView};
+use crate::menu::{HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON};
+use crate::{GameState, Piece, TEXT_COLOR};
+use bevy::prelude::*;
+
+/// Turns the board round. The Flip button and the B key write these.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlipBoard;
+
+#[derive(Component)]
+struct FlipButton;
+
+pub fn flip_plugin(app: &mut App) {
+    app.add_message::<FlipBoard>()
+        .add_systems(OnEnter(GameStatus::Game), flip_button_setup)
+        .add_systems(
+            Update,
+            (flip_keyboard_system, flip_button_system, apply_flip_system)
+                .chain()
+                .run_if(in_state(GamePhase::Playing)),
+        );
+}
+
+fn flip_button_setup(mut commands: Commands) {
+    // just below Undo and Redo
+    commands.spawn((
+        DespawnOnExit(GameStatus::Game),
+        Node {
+            position_type: PositionType::Absolute,
+            right: px(10),
+            top: px(440),
+            width: px(200),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            Button,
+            FlipButton,
+            Node {
+                width: px(180),
+                height: px(50),
+                margin: UiRect::all(px(8)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(NORMAL_BUTTON),
+            children![(
+                Text::new("Flip board"),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            )],
+        )],
+    ));
+}
+
+fn flip_keyboard_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut flip_writer: MessageWriter<FlipBoard>,
+) {
+    if keys.just_pressed(KeyCode::KeyB) {
+        flip_writer.write(FlipBoard);
+    }
+}
+
+fn flip_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &FlipButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut flip_writer: MessageWriter<FlipBoard>,
+) {
+    for (interaction, _, mut background_color) in &mut interaction_query {
+        *background_color = match *interaction {
+            Interaction::Pressed => {
+                flip_writer.write(FlipBoard);
+                PRESSED_BUTTON.into()
+            }
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+/// Moves every square to the opposite side of the board and puts the pieces
+/// and labels back on them. The checkerboard looks the same either way up, so
+/// the tile sprites stay where they are.
+#[allow(clippy::too_many_arguments)]
+fn apply_flip_system(
+    mut commands: Commands,
+    mut flip_reader: MessageReader<FlipBoard>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut game_state: ResMut<GameState>,
+    history_view: Option<Res<HistoryView>>,
+    image_cache: Res<ImageCache>,
+    query_pieces: Query<Entity, With<Piece>>,
+    query_labels: Query<Entity, With<CoordinateLabel>>,
+) {
+    // two flips in one frame cancel out
+    if flip_reader.read().count().is_multiple_of(2) || game_state.pending_promotion.is_some() {
+        return;
+    }
+    orientation.0 = orientation.0.opponent();
+
+    for (row, tiles) in game_state.tiles.iter_mut().enumerate() {
+        for (column, tile) in tiles.iter_mut().enumerate() {
+            tile.position.coordinates = tile_coordinates(row as u8, column as u8, orientation.0);
+        }
+    }
+    for entity in &query_labels {
+        commands.entity(entity).despawn();
+    }
+    spawn_coordinate_labels(&mut commands, &game_state.tiles, GameStatus::Game);
+
+    let board = shown_board(&game_state.board, history_view.as_deref());
+    show_position(
+        &mut commands,
+        &image_cache,
+        &mut game_state,
+        &query_pieces,
+        &board,
+    );
+}
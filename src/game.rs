@@ -1,27 +1,31 @@
 use crate::board::{
-    check_bounds, default_king_data, get_pos_label, get_tile_color, index_for_pos, init_board,
-    init_king_positions, update_king_data, Position, PositionLabel, Tile, BOARD_DIMENSION,
-    HALF_TILE, NUM_COLUMNS, NUM_ROWS, TILE_SIZE,
+    check_bounds, get_pos_label, get_tile_color, index_for_square, init_board, square_for_label,
+    tile_coordinates, Position, PositionLabel, Tile, BOARD_DIMENSION, HALF_TILE, NUM_COLUMNS,
+    NUM_ROWS, TILE_SIZE,
 };
-use crate::check::{check_checkmate, prevent_check};
-use crate::pieces::{get_possible_moves_for_piece, init_piece_data, Team};
-use crate::util::load_image;
-use crate::{GameState, Light, Piece, Selected};
-use bevy::app::{App, FixedUpdate, Update};
+use crate::history::HistoryView;
+use crate::network::NetworkGame;
+use crate::pieces::{get_piece_image, PieceType, Team};
+use crate::util::{copy_to_clipboard, load_image, transform_mouse_coords};
+use crate::{GameState, Light, Piece};
+use bevy::app::{App, FixedUpdate, Startup, Update};
 use bevy::asset::{AssetServer, Handle};
 use bevy::color::Color;
 use bevy::image::Image;
 use bevy::input::ButtonInput;
+use bevy::log::{info, warn};
 use bevy::math::{Vec2, Vec3};
 use bevy::prelude::{
-    default, Commands, Entity, MouseButton, OnEnter, Query, Res, ResMut, Resource, Sprite,
-    Transform, Window, With, Without,
+    default, Commands, Component, DespawnOnExit, Entity, KeyCode, MouseButton, NextState, OnEnter,
+    OnExit, Query, Res, ResMut, Resource, Sprite, Text2d, TextColor, TextFont, Transform, Window,
+    With,
+};
+use bevy::prelude::{
+    in_state, not, resource_exists, AppExtStates, IntoScheduleConfigs, StateSet, States, SubStates,
 };
-use bevy::prelude::{in_state, IntoScheduleConfigs, States};
 use bevy::window::PrimaryWindow;
+use chess_core::{Board, GameOutcome, Move};
 use std::borrow::Borrow;
-use std::borrow::BorrowMut;
-use std::process;
 
 #[derive(Resource)]
 pub struct ImageCache {
@@ -45,26 +49,178 @@ pub enum GameStatus {
     Splash,
     Menu,
     Game,
+    GameOver,
+    Replay,
+}
+
+/// Whether the game is being played or has been paused from the keyboard.
+/// Input and the clocks stop while it is paused, except that the clocks of a
+/// game over the network keep running.
+#[derive(SubStates, Clone, Copy, Default, Eq, PartialEq, Debug, Hash)]
+#[source(GameStatus = GameStatus::Game)]
+pub enum GamePhase {
+    #[default]
+    Playing,
+    Paused,
+}
+
+/// With `automatic_draw_claims` the fifty-move rule and threefold repetition end the
+/// game as soon as they apply. Otherwise they must be claimed, and only the
+/// seventy-five-move rule and fivefold repetition are enforced automatically.
+/// A game over the network is played with the host's rules.
+#[derive(Resource, Component, Clone, Copy, Eq, PartialEq, Debug)]
+pub struct DrawRules {
+    pub(crate) automatic_draw_claims: bool,
+}
+
+impl Default for DrawRules {
+    fn default() -> Self {
+        DrawRules {
+            automatic_draw_claims: true,
+        }
+    }
+}
+
+/// The position new games start from. The menu replaces it when a FEN is loaded.
+#[derive(Resource, Default)]
+pub struct StartingPosition(pub Board);
+
+/// Who makes the moves for one side.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum PlayerKind {
+    #[default]
+    Human,
+    Computer,
+    /// Another copy of the game, over the network.
+    Remote,
+}
+
+/// Who plays each side of the next or current game. Both sides are human by
+/// default, taking turns at the same board.
+#[derive(Resource, Clone, Copy, Default, Debug)]
+pub struct Players {
+    pub white: PlayerKind,
+    pub black: PlayerKind,
+}
+
+impl Players {
+    /// A game against the computer with `human` playing one side.
+    pub fn against_computer(human: Team) -> Players {
+        match human {
+            Team::White => Players {
+                white: PlayerKind::Human,
+                black: PlayerKind::Computer,
+            },
+            Team::Black => Players {
+                white: PlayerKind::Computer,
+                black: PlayerKind::Human,
+            },
+        }
+    }
+
+    pub fn kind(&self, team: Team) -> PlayerKind {
+        match team {
+            Team::White => self.white,
+            Team::Black => self.black,
+        }
+    }
+
+    pub fn is_human(&self, team: Team) -> bool {
+        self.kind(team) == PlayerKind::Human
+    }
+
+    /// The side to show at the bottom of the board: the human's, or White's
+    /// when both or neither side is human.
+    pub fn viewing_side(&self) -> Team {
+        if self.is_human(Team::Black) && !self.is_human(Team::White) {
+            Team::Black
+        } else {
+            Team::White
+        }
+    }
+}
+
+/// The side whose pieces start at the bottom of the screen.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoardOrientation(pub Team);
+
+impl Default for BoardOrientation {
+    fn default() -> Self {
+        BoardOrientation(Team::White)
+    }
+}
+
+/// How the finished game ended; present from game over until the game is left.
+#[derive(Resource, Debug)]
+pub struct GameResult(pub GameOutcome);
+
+const COORDINATE_LABEL_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.8);
+
+/// How far the cursor has to move with the button down on a piece before the
+/// piece is dragged rather than clicked.
+const DRAG_THRESHOLD: f32 = 4.0;
+
+/// Where the player is in moving a piece with the mouse. Clicking a piece and
+/// then its destination and dragging it there both go through these states.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub(crate) enum PieceInput {
+    #[default]
+    Idle,
+    /// The button went down on `piece` at `press` and is still down. The
+    /// piece follows the cursor once it is `dragging`.
+    Held {
+        piece: Entity,
+        press: Vec2,
+        dragging: bool,
+    },
+    /// `piece` was clicked and waits for a click on where it should go.
+    Selected { piece: Entity },
 }
 
+impl PieceInput {
+    /// The piece picked up or selected, if there is one.
+    pub(crate) fn piece(self) -> Option<Entity> {
+        match self {
+            PieceInput::Idle => None,
+            PieceInput::Held { piece, .. } | PieceInput::Selected { piece } => Some(piece),
+        }
+    }
+}
+
+#[derive(Component)]
+pub(crate) struct BoardTile;
+
+/// A rank number or file letter on the edge of the board.
+#[derive(Component)]
+pub(crate) struct CoordinateLabel;
+
 pub fn game_plugin(app: &mut App) {
-    app.add_systems(
-        OnEnter(GameStatus::Game),
-        (load_sprites, setup_game).chain(),
-    )
-    .add_systems(
-        FixedUpdate,
-        (
-            select_piece_system,
-            cleanup_select_system,
-            prevent_check_system,
-            handle_move_system,
-            enforce_checkmate_system,
+    app.init_resource::<DrawRules>()
+        .init_resource::<StartingPosition>()
+        .init_resource::<Players>()
+        .init_resource::<BoardOrientation>()
+        .add_sub_state::<GamePhase>()
+        .add_systems(Startup, load_sprites)
+        .add_systems(OnEnter(GameStatus::Game), setup_game)
+        .add_systems(
+            Update,
+            (
+                // an earlier position being looked at can't be played on
+                piece_input_system.run_if(not(resource_exists::<HistoryView>)),
+                cleanup_select_system,
+            )
+                .chain()
+                .run_if(in_state(GamePhase::Playing)),
+        )
+        .add_systems(
+            FixedUpdate,
+            enforce_game_outcome_system.run_if(in_state(GameStatus::Game)),
         )
-            .chain()
-            .run_if(in_state(GameStatus::Game)),
-    )
-    .add_systems(Update, bevy::window::close_when_requested);
+        .add_systems(Update, copy_fen_system.run_if(in_state(GamePhase::Playing)))
+        .add_systems(OnExit(GameStatus::GameOver), teardown_game)
+        // a game given up on for the menu never reaches game over
+        .add_systems(OnEnter(GameStatus::Menu), teardown_game)
+        .add_systems(Update, bevy::window::close_when_requested);
 }
 
 fn load_sprites(mut commands: Commands, asset_server: Res<AssetServer>) {
@@ -84,38 +240,59 @@ fn load_sprites(mut commands: Commands, asset_server: Res<AssetServer>) {
     });
 }
 
-fn setup_game(mut commands: Commands, image_cache: Res<ImageCache>) {
-    let mut game_state = GameState {
-        turn: Team::White,
+fn setup_game(
+    mut commands: Commands,
+    image_cache: Res<ImageCache>,
+    starting_position: Res<StartingPosition>,
+    players: Res<Players>,
+    mut orientation: ResMut<BoardOrientation>,
+) {
+    let board = starting_position.0.clone();
+    orientation.0 = players.viewing_side();
+    let tiles = spawn_board(
+        &mut commands,
+        &image_cache,
+        &board,
+        orientation.0,
+        GameStatus::Game,
+    );
+
+    commands.insert_resource(GameState {
+        board,
+        tiles,
         highlight_coords: Vec2::ZERO,
-        selected_piece: None,
-        board: init_board(),
-        white_king_data: default_king_data(),
-        black_king_data: default_king_data(),
-    };
+        piece_input: PieceInput::Idle,
+        pending_promotion: None,
+        undone_moves: Vec::new(),
+    });
+}
 
-    // commands.spawn(Camera2d::default()).insert(MainCamera);
+/// Spawns the tiles, their labels and a sprite for every piece on `board`
+/// with `bottom`'s side of the board at the bottom of the screen, returning the
+/// tiles with their positions and pieces filled in. They are all despawned on
+/// leaving `scope`.
+pub(crate) fn spawn_board(
+    commands: &mut Commands,
+    image_cache: &ImageCache,
+    board: &Board,
+    bottom: Team,
+    scope: GameStatus,
+) -> [[Tile; 8]; 8] {
+    let mut tiles = init_board();
 
     for row in 0..NUM_ROWS {
         for column in 0..NUM_COLUMNS {
-            let offset: f32 = -(BOARD_DIMENSION / 2.) + HALF_TILE;
-            let tile_position = Vec2::new(
-                offset + column as f32 * TILE_SIZE.x,
-                offset + row as f32 * TILE_SIZE.y,
-            );
+            let tile_position = tile_coordinates(row, column, bottom);
 
             let (col_label, row_label) = get_pos_label(row, &column);
             let position_label = PositionLabel {
                 col_label,
                 row_label,
             };
-            let current_pos = Position {
-                position_label,
-                coordinates: tile_position,
-            };
-            // println!("Current position: {:?}", current_pos);
 
             commands.spawn((
+                DespawnOnExit(scope),
+                BoardTile,
                 Sprite {
                     color: get_tile_color(&row, &column),
                     ..default()
@@ -127,212 +304,432 @@ fn setup_game(mut commands: Commands, image_cache: Res<ImageCache>) {
                 },
             ));
 
-            if !(2..=5).contains(&row) {
-                let (handle, team, piece_type) = init_piece_data(image_cache.borrow(), current_pos);
-                let piece_id: Entity = commands
+            tiles[row as usize][column as usize].position = Position {
+                position_label,
+                coordinates: tile_position,
+            };
+        }
+    }
+
+    spawn_coordinate_labels(commands, &tiles, scope);
+    spawn_pieces(commands, image_cache, board, &mut tiles, scope);
+    tiles
+}
+
+/// Writes the rank numbers down the left edge of the board and the file
+/// letters along the bottom, in the corners of the tiles there.
+pub(crate) fn spawn_coordinate_labels(
+    commands: &mut Commands,
+    tiles: &[[Tile; 8]; 8],
+    scope: GameStatus,
+) {
+    let edge = -(BOARD_DIMENSION / 2.) + HALF_TILE;
+    for tile in tiles.iter().flatten() {
+        let Position {
+            position_label,
+            coordinates,
+        } = tile.position;
+        let mut labels = Vec::new();
+        if coordinates.x == edge {
+            labels.push((
+                position_label.row_label.to_string(),
+                coordinates + Vec2::new(-HALF_TILE + 8., HALF_TILE - 10.),
+            ));
+        }
+        if coordinates.y == edge {
+            let file = (b'a' + position_label.col_label as u8) as char;
+            labels.push((
+                file.to_string(),
+                coordinates + Vec2::new(HALF_TILE - 8., -HALF_TILE + 10.),
+            ));
+        }
+        for (text, position) in labels {
+            commands.spawn((
+                DespawnOnExit(scope),
+                CoordinateLabel,
+                Text2d::new(text),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(COORDINATE_LABEL_COLOR),
+                Transform::from_translation(position.extend(2.0)),
+            ));
+        }
+    }
+}
+
+/// Replaces every piece sprite with fresh ones matching the board, for when the
+/// position changes by more than one move the player made on the board.
+pub(crate) fn respawn_pieces(
+    commands: &mut Commands,
+    image_cache: &ImageCache,
+    game_state: &mut GameState,
+    query_pieces: &Query<Entity, With<Piece>>,
+) {
+    let board = game_state.board.clone();
+    show_position(commands, image_cache, game_state, query_pieces, &board);
+}
+
+/// Puts the pieces of `board` on the tiles in place of the ones there, which
+/// may be a position other than the game's own.
+pub(crate) fn show_position(
+    commands: &mut Commands,
+    image_cache: &ImageCache,
+    game_state: &mut GameState,
+    query_pieces: &Query<Entity, With<Piece>>,
+    board: &Board,
+) {
+    deselect(game_state);
+    for entity in query_pieces.iter() {
+        commands.entity(entity).despawn();
+    }
+    spawn_pieces(
+        commands,
+        image_cache,
+        board,
+        &mut game_state.tiles,
+        GameStatus::Game,
+    );
+}
+
+/// Spawns a sprite for every piece on `board` and records it on its tile.
+/// Sprites already on the tiles are forgotten, not despawned.
+pub(crate) fn spawn_pieces(
+    commands: &mut Commands,
+    image_cache: &ImageCache,
+    board: &Board,
+    tiles: &mut [[Tile; 8]; 8],
+    scope: GameStatus,
+) {
+    for tile in tiles.iter_mut().flatten() {
+        let position = tile.position;
+        tile.piece = board
+            .piece_at(square_for_label(position.position_label))
+            .map(|(team, piece_type)| {
+                commands
                     .spawn((
-                        Sprite::from_image(handle),
-                        Transform::from_translation(tile_position.extend(999.0)),
+                        DespawnOnExit(scope),
+                        Sprite::from_image(get_piece_image(image_cache, team, piece_type)),
+                        Transform::from_translation(position.coordinates.extend(999.0)),
                         Piece {
-                            // name: name.to_string(),
-                            position: current_pos,
+                            position,
                             team,
                             piece_type,
                             available_moves: Vec::new(),
                         },
                     ))
-                    .id();
-
-                init_king_positions(piece_type, team, &mut game_state, current_pos);
-                game_state.board[row as usize][column as usize] = Tile {
-                    position: current_pos,
-                    team,
-                    piece: Option::from(piece_id),
-                };
-            } else {
-                game_state.board[row as usize][column as usize] = Tile {
-                    position: current_pos,
-                    team: Team::None,
-                    piece: None,
-                };
-            }
-        }
+                    .id()
+            });
     }
-
-    commands.insert_resource(game_state);
 }
 
-fn select_piece_system(
+/// Moves pieces for the player with the mouse, by clicking a piece and then
+/// where it should go or by dragging it there. A press on one of the pieces
+/// of the side to move picks it up; releasing the button without having moved
+/// the cursor leaves it selected, releasing it elsewhere drops it.
+fn piece_input_system(
     buttons: Res<ButtonInput<MouseButton>>,
     query_windows: Query<&Window, With<PrimaryWindow>>,
     mut commands: Commands,
     mut game_state: ResMut<GameState>,
-    mut query_unselected: Query<(Entity, &mut Piece), Without<Selected>>,
-    mut query_selected: Query<Entity, With<Selected>>,
+    players: Res<Players>,
+    mut query_pieces: Query<(&mut Piece, &mut Transform)>,
 ) {
-    let mouse_pos = query_windows.single().unwrap().cursor_position();
-
-    if mouse_pos.is_none() {
+    if game_state.pending_promotion.is_some() || !players.is_human(game_state.board.side_to_move())
+    {
         return;
     }
+    let mouse_pos = query_windows.single().unwrap().cursor_position();
 
-    if buttons.just_pressed(MouseButton::Left) {
-        for (entity, mut piece) in query_unselected.iter_mut() {
-            let piece_coords = piece.position.coordinates;
-            let in_bounds: bool = check_bounds(piece_coords.x, piece_coords.y, mouse_pos.unwrap());
-            if in_bounds
-                && piece.team == game_state.turn
-                && game_state.highlight_coords != piece_coords
-            {
-                commands.spawn((
-                    (
-                        Sprite {
-                            color: Color::srgba(0.12, 1.0, 0.06, 0.7),
-                            custom_size: Some(Vec2::new(TILE_SIZE.x, TILE_SIZE.y)),
-                            ..default()
-                        },
-                        Transform::from_translation(piece_coords.extend(0.0)),
-                    ),
-                    Light {
-                        coordinates: piece_coords,
-                    },
-                ));
-                game_state.highlight_coords = piece_coords;
-                game_state.selected_piece = Option::from(entity);
-                for ent in query_selected.iter_mut() {
-                    commands.entity(ent).remove::<Selected>();
+    match game_state.piece_input {
+        PieceInput::Idle | PieceInput::Selected { .. }
+            if buttons.just_pressed(MouseButton::Left) =>
+        {
+            let Some(mouse_pos) = mouse_pos else {
+                return;
+            };
+            let tile = tile_at(&game_state, mouse_pos);
+            if let (PieceInput::Selected { piece }, Some(tile)) = (game_state.piece_input, tile) {
+                if try_move(
+                    &mut commands,
+                    &mut game_state,
+                    &mut query_pieces,
+                    piece,
+                    tile.position,
+                ) {
+                    return;
+                }
+            }
+
+            let side_to_move = game_state.board.side_to_move();
+            let pressed = tile.and_then(|tile| tile.piece).filter(|entity| {
+                query_pieces
+                    .get(*entity)
+                    .is_ok_and(|(piece, _)| piece.team == side_to_move)
+            });
+            match pressed {
+                Some(entity) => {
+                    let (mut piece, _) = query_pieces.get_mut(entity).unwrap();
+                    select_piece(&mut commands, &mut game_state, &mut piece);
+                    game_state.piece_input = PieceInput::Held {
+                        piece: entity,
+                        press: mouse_pos,
+                        dragging: false,
+                    };
+                }
+                None => deselect(&mut game_state),
+            }
+        }
+        PieceInput::Held {
+            piece,
+            press,
+            dragging,
+        } => {
+            if !buttons.pressed(MouseButton::Left) {
+                drop_piece(
+                    &mut commands,
+                    &mut game_state,
+                    &mut query_pieces,
+                    piece,
+                    dragging,
+                    mouse_pos,
+                );
+                return;
+            }
+            let Some(mouse_pos) = mouse_pos else {
+                return;
+            };
+            let dragging = dragging || mouse_pos.distance(press) > DRAG_THRESHOLD;
+            if dragging {
+                if let Ok((_, mut transform)) = query_pieces.get_mut(piece) {
+                    transform.translation = transform_mouse_coords(mouse_pos).extend(1000.0);
                 }
-                commands.entity(entity).insert(Selected);
-                piece.available_moves = get_possible_moves_for_piece(&piece, &game_state.board);
-                break;
             }
+            game_state.piece_input = PieceInput::Held {
+                piece,
+                press,
+                dragging,
+            };
         }
+        _ => {}
     }
 }
 
-fn prevent_check_system(
-    mut query_selected: Query<&mut Piece, With<Selected>>,
-    query_unselected: Query<&mut Piece, Without<Selected>>,
-    game_state: Res<GameState>,
+/// Lets go of the held piece. Without a drag it stays selected for a click on
+/// its destination; dragged to one of them it moves there, and anywhere else,
+/// outside the window included, it goes back to its tile.
+fn drop_piece(
+    commands: &mut Commands,
+    game_state: &mut GameState,
+    query_pieces: &mut Query<(&mut Piece, &mut Transform)>,
+    entity: Entity,
+    dragging: bool,
+    drop_pos: Option<Vec2>,
 ) {
-    if game_state.selected_piece.is_none() {
+    if !dragging {
+        game_state.piece_input = PieceInput::Selected { piece: entity };
         return;
     }
-
-    let selected_entity: Entity = game_state.selected_piece.unwrap();
-    if let Ok(mut selected_piece) = query_selected.get_mut(selected_entity) {
-        let king_pos: Position = if game_state.turn == Team::White {
-            game_state.white_king_data.position
-        } else {
-            game_state.black_king_data.position
-        };
-        for enemy_piece in query_unselected.iter() {
-            prevent_check(
-                selected_piece.borrow_mut(),
-                selected_entity,
-                enemy_piece,
-                king_pos,
-                &game_state,
-            );
+    let target = drop_pos
+        .and_then(|drop_pos| tile_at(game_state, drop_pos))
+        .map(|tile| tile.position);
+    if let Some(target) = target {
+        if try_move(commands, game_state, query_pieces, entity, target) {
+            return;
         }
     }
+
+    let Ok((piece, mut transform)) = query_pieces.get_mut(entity) else {
+        deselect(game_state);
+        return;
+    };
+    transform.translation = piece.position.coordinates.extend(999.0);
+    // put back where it was picked up, it is as good as clicked
+    if target == Some(piece.position) {
+        game_state.piece_input = PieceInput::Selected { piece: entity };
+    } else {
+        deselect(game_state);
+    }
 }
 
-fn enforce_checkmate_system(
-    game_state: Res<GameState>,
-    query_unselected: Query<(Entity, &mut Piece), Without<Selected>>,
-) {
-    if game_state.selected_piece.is_some() {
+/// Highlights `piece`'s tile and works out where it can move.
+fn select_piece(commands: &mut Commands, game_state: &mut GameState, piece: &mut Piece) {
+    let piece_coords = piece.position.coordinates;
+    piece.available_moves = get_available_moves(game_state, piece.position);
+    if game_state.highlight_coords == piece_coords {
         return;
     }
+    game_state.highlight_coords = piece_coords;
 
-    let is_checkmate: bool = if game_state.turn == Team::White {
-        check_checkmate(
-            game_state.turn,
-            game_state.white_king_data.position,
-            game_state.board,
-            query_unselected,
-        )
-    } else {
-        check_checkmate(
-            game_state.turn,
-            game_state.black_king_data.position,
-            game_state.board,
-            query_unselected,
+    commands.spawn((
+        DespawnOnExit(GameStatus::Game),
+        Sprite {
+            color: Color::srgba(0.12, 1.0, 0.06, 0.7),
+            custom_size: Some(Vec2::new(TILE_SIZE.x, TILE_SIZE.y)),
+            ..default()
+        },
+        Transform::from_translation(piece_coords.extend(0.6)),
+        Light {
+            coordinates: piece_coords,
+        },
+    ));
+}
+
+fn deselect(game_state: &mut GameState) {
+    game_state.piece_input = PieceInput::Idle;
+    game_state.highlight_coords = Vec2::ZERO;
+}
+
+/// The tile under the cursor at `mouse_pos`, in window coordinates.
+fn tile_at(game_state: &GameState, mouse_pos: Vec2) -> Option<Tile> {
+    game_state.tiles.iter().flatten().copied().find(|tile| {
+        check_bounds(
+            tile.position.coordinates.x,
+            tile.position.coordinates.y,
+            mouse_pos,
         )
-    };
+    })
+}
 
-    if is_checkmate {
-        println!("Checkmate!");
-        process::exit(0x00);
-    };
+/// The tiles the piece on `position` can legally move to.
+fn get_available_moves(game_state: &GameState, position: Position) -> Vec<Position> {
+    let from = square_for_label(position.position_label);
+    let mut available_moves: Vec<Position> = Vec::new();
+    for mv in game_state.board.legal_moves() {
+        if mv.from != from {
+            continue;
+        }
+        let (row, col) = index_for_square(mv.to);
+        let target = game_state.tiles[row][col].position;
+        // the four promotions share one destination tile
+        if !available_moves.contains(&target) {
+            available_moves.push(target);
+        }
+    }
+    available_moves
 }
 
-fn handle_move_system(
-    buttons: Res<ButtonInput<MouseButton>>,
-    // mouse_coords: Res<Mouse>,
-    query_windows: Query<&Window, With<PrimaryWindow>>,
+fn enforce_game_outcome_system(
     mut commands: Commands,
-    mut query: Query<(Entity, &mut Piece, &mut Transform), With<Selected>>,
-    mut game_state: ResMut<GameState>,
+    mut next_status: ResMut<NextState<GameStatus>>,
+    game_state: Res<GameState>,
+    draw_rules: Res<DrawRules>,
+    network_game: Option<Res<NetworkGame>>,
 ) {
-    let mouse_pos = query_windows.single().unwrap().cursor_position();
-    if mouse_pos.is_none()
-        || !buttons.just_pressed(MouseButton::Left)
-        || game_state.selected_piece.is_none()
-    {
+    if game_state.piece_input != PieceInput::Idle || game_state.pending_promotion.is_some() {
         return;
     }
 
-    if let Ok((entity, mut piece, mut transform)) =
-        query.get_mut(game_state.selected_piece.unwrap())
-    {
-        for position in &piece.available_moves {
-            if check_bounds(
-                position.coordinates.x,
-                position.coordinates.y,
-                mouse_pos.unwrap(),
-            ) {
-                let delta: Vec2 = Vec2::new(
-                    position.coordinates.x - piece.position.coordinates.x,
-                    position.coordinates.y - piece.position.coordinates.y,
-                );
-                transform.translation.x += delta.x;
-                transform.translation.y += delta.y;
-                transform.translation.z = 999f32;
-
-                let (old_row, old_col) = index_for_pos(piece.position.position_label);
-                let (new_row, new_col) = index_for_pos(position.position_label);
-                let new_pos: Position = game_state.board[new_row][new_col].position;
-                update_king_data(&piece, &mut game_state, new_pos);
-                let new_tile: &mut Tile = &mut game_state.board[new_row][new_col];
-
-                // capture piece if tile contains enemy
-                if let Some(piece) = new_tile.piece {
-                    commands.entity(piece).despawn();
-                }
+    let automatic_draw_claims = match network_game {
+        Some(network_game) => network_game.automatic_draw_claims(),
+        None => draw_rules.automatic_draw_claims,
+    };
+    if let Some(outcome) = game_state.board.outcome(automatic_draw_claims) {
+        info!("{}", outcome);
+        commands.insert_resource(GameResult(outcome));
+        next_status.set(GameStatus::GameOver);
+    }
+}
 
-                new_tile.team = piece.team;
-                new_tile.piece = Option::from(entity);
+fn copy_fen_system(keys: Res<ButtonInput<KeyCode>>, game_state: Res<GameState>) {
+    if !keys.just_pressed(KeyCode::KeyF) || game_state.pending_promotion.is_some() {
+        return;
+    }
 
-                game_state.board[old_row][old_col].team = Team::None;
-                game_state.board[old_row][old_col].piece = None;
+    let fen = game_state.board.to_fen();
+    info!("FEN: {}", fen);
+    if let Err(error) = copy_to_clipboard(fen) {
+        warn!("Could not copy the FEN to the clipboard: {}", error);
+    }
+}
+
+/// Drops what is left of the last game once it has been left, so the next one
+/// starts from nothing. Its entities go by themselves on leaving the game.
+fn teardown_game(mut commands: Commands) {
+    commands.remove_resource::<GameState>();
+    commands.remove_resource::<GameResult>();
+}
+
+/// Moves `entity` to `target` if that is one of its legal moves, returning
+/// whether it did.
+fn try_move(
+    commands: &mut Commands,
+    game_state: &mut GameState,
+    query_pieces: &mut Query<(&mut Piece, &mut Transform)>,
+    entity: Entity,
+    target: Position,
+) -> bool {
+    let Ok((piece, _)) = query_pieces.get(entity) else {
+        return false;
+    };
+    if !piece.available_moves.contains(&target) {
+        return false;
+    }
+
+    // a promotion is looked up as a queen and corrected once the piece is chosen
+    let from = square_for_label(piece.position.position_label);
+    let to = square_for_label(target.position_label);
+    let Some(mv) = game_state
+        .board
+        .find_move(from, to, None)
+        .or_else(|| game_state.board.find_move(from, to, Some(PieceType::Queen)))
+    else {
+        return false;
+    };
+
+    move_piece_entities(commands, game_state, query_pieces, mv);
 
-                game_state.highlight_coords = Vec2::ZERO;
-                game_state.selected_piece = None;
-                game_state.turn = if game_state.turn == Team::White {
-                    Team::Black
-                } else {
-                    Team::White
-                };
+    let (mut piece, mut transform) = query_pieces.get_mut(entity).unwrap();
+    transform.translation = target.coordinates.extend(999.0);
+    piece.position = target;
+    piece.available_moves = Vec::new();
+    deselect(game_state);
 
-                piece.position = *position;
-                piece.available_moves = Vec::new();
-                commands.entity(entity).remove::<Selected>();
-                break;
+    // the move is made once the promotion piece has been chosen
+    if mv.promotion.is_some() {
+        game_state.pending_promotion = Some(mv);
+    } else {
+        info!("{:?} {:?} {}", piece.team, mv.piece, mv);
+        game_state.play_move(mv);
+    }
+    true
+}
+
+/// Brings the tiles in line with `mv`: despawns whatever it captures, slides the
+/// castling rook across and records the moving piece on its new tile. The moving
+/// piece's own sprite is left to the caller.
+fn move_piece_entities(
+    commands: &mut Commands,
+    game_state: &mut GameState,
+    query_pieces: &mut Query<(&mut Piece, &mut Transform)>,
+    mv: Move,
+) {
+    if let Some(captured_square) = mv.captured_square() {
+        let (row, col) = index_for_square(captured_square);
+        if let Some(captured_piece) = game_state.tiles[row][col].piece.take() {
+            commands.entity(captured_piece).despawn();
+        }
+    }
+
+    if let Some((rook_from, rook_to)) = mv.castling_rook() {
+        let (old_row, old_col) = index_for_square(rook_from);
+        let (new_row, new_col) = index_for_square(rook_to);
+        if let Some(rook_entity) = game_state.tiles[old_row][old_col].piece.take() {
+            let rook_pos: Position = game_state.tiles[new_row][new_col].position;
+            if let Ok((mut rook, mut rook_transform)) = query_pieces.get_mut(rook_entity) {
+                rook_transform.translation.x = rook_pos.coordinates.x;
+                rook_transform.translation.y = rook_pos.coordinates.y;
+                rook.position = rook_pos;
             }
+            game_state.tiles[new_row][new_col].piece = Option::from(rook_entity);
         }
     }
+
+    let (old_row, old_col) = index_for_square(mv.from);
+    let (new_row, new_col) = index_for_square(mv.to);
+    let moving_piece = game_state.tiles[old_row][old_col].piece.take();
+    game_state.tiles[new_row][new_col].piece = moving_piece;
 }
 
 fn cleanup_select_system(
@@ -343,7 +740,6 @@ fn cleanup_select_system(
     for (entity, highlight) in query.iter() {
         if highlight.coordinates != game_state.highlight_coords {
             commands.entity(entity).despawn();
-            break;
         }
     }
 }
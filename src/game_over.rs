@@ -0,0 +1,188 @@
+use crate::game::{GameResult, GameStatus, Players};
+use crate::menu::{button_system, NORMAL_BUTTON};
+use crate::network::NetworkGame;
+use crate::pgn::GameRecord;
+use crate::util::copy_to_clipboard;
+use crate::{GameState, TEXT_COLOR};
+use bevy::color::palettes::css::CRIMSON;
+use bevy::ecs::spawn::SpawnIter;
+use bevy::prelude::*;
+use chess_core::write_pgn;
+use chess_protocol::Rematch;
+
+pub fn game_over_plugin(app: &mut App) {
+    app.add_systems(OnEnter(GameStatus::GameOver), game_over_setup)
+        .add_systems(
+            Update,
+            (game_over_action, button_system).run_if(in_state(GameStatus::GameOver)),
+        )
+        .add_systems(
+            Update,
+            rematch_text_system
+                .run_if(in_state(GameStatus::GameOver).and(resource_exists::<NetworkGame>)),
+        );
+}
+
+#[derive(Component, Clone, Copy)]
+enum GameOverButtonAction {
+    Rematch,
+    ExportPgn,
+    MainMenu,
+}
+
+/// Says whether Export PGN worked, and how a rematch over the network is
+/// going.
+#[derive(Component)]
+struct StatusText;
+
+/// The result and how the game ended, with buttons for a rematch with the
+/// colours swapped, copying the game as PGN, and the menu.
+fn game_over_setup(
+    mut commands: Commands,
+    result: Res<GameResult>,
+    network_game: Option<Res<NetworkGame>>,
+) {
+    let button_node = Node {
+        width: px(300),
+        height: px(65),
+        margin: UiRect::all(px(15)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_font = TextFont {
+        font_size: 33.0,
+        ..default()
+    };
+    let mut actions = vec![
+        (GameOverButtonAction::ExportPgn, "Export PGN"),
+        (GameOverButtonAction::MainMenu, "Main Menu"),
+    ];
+    // spectators can't ask for one
+    if network_game.is_none_or(|network_game| !network_game.is_watching()) {
+        actions.insert(0, (GameOverButtonAction::Rematch, "Rematch"));
+    }
+
+    commands.spawn((
+        DespawnOnExit(GameStatus::GameOver),
+        Node {
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            children![
+                (
+                    Text::new(result.0.to_string()),
+                    TextFont {
+                        font_size: 50.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        margin: UiRect::all(px(40)),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    Children::spawn(SpawnIter(actions.into_iter().map(move |(action, text)| {
+                        (
+                            Button,
+                            button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            action,
+                            children![(
+                                Text::new(text),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            )],
+                        )
+                    }))),
+                ),
+                (
+                    StatusText,
+                    Text::new(""),
+                    TextFont {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        margin: UiRect::all(px(15)),
+                        ..default()
+                    },
+                ),
+            ]
+        )],
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn game_over_action(
+    interaction_query: Query<(&Interaction, &GameOverButtonAction), Changed<Interaction>>,
+    mut players: ResMut<Players>,
+    record: Res<GameRecord>,
+    mut network_game: Option<ResMut<NetworkGame>>,
+    game_state: Res<GameState>,
+    result: Res<GameResult>,
+    mut status_text: Single<&mut Text, With<StatusText>>,
+    mut next_status: ResMut<NextState<GameStatus>>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match action {
+            GameOverButtonAction::Rematch => match &mut network_game {
+                // both players have to want one, and the host starts it
+                Some(network_game) => network_game.offer_rematch(&game_state.board),
+                None => {
+                    let players = &mut *players;
+                    std::mem::swap(&mut players.white, &mut players.black);
+                    next_status.set(GameStatus::Game);
+                }
+            },
+            GameOverButtonAction::ExportPgn => {
+                let pgn = write_pgn(&record.tags, &game_state.board, Some(result.0));
+                status_text.0 = match copy_to_clipboard(pgn) {
+                    Ok(()) => "Copied the game to the clipboard as PGN".to_string(),
+                    Err(error) => format!("Could not copy the game: {}", error),
+                };
+            }
+            GameOverButtonAction::MainMenu => next_status.set(GameStatus::Menu),
+        }
+    }
+}
+
+/// Says who has offered a rematch over the network.
+fn rematch_text_system(
+    network_game: Res<NetworkGame>,
+    mut status_text: Single<&mut Text, With<StatusText>>,
+    mut shown: Local<Rematch>,
+) {
+    let rematch = network_game.rematch();
+    if rematch == *shown {
+        return;
+    }
+    *shown = rematch;
+    let opponent = network_game.opponent_name();
+    status_text.0 = match rematch {
+        Rematch::None => return,
+        Rematch::Offered => format!("Offered {} a rematch", opponent),
+        Rematch::Asked => format!("{} offers a rematch", opponent),
+        Rematch::Agreed => format!("Waiting for {} to start the rematch", opponent),
+    };
+}
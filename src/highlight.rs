@@ -0,0 +1,198 @@
+use crate::board::{index_for_square, square_for_label, TILE_SIZE};
+use crate::game::{BoardOrientation, GameStatus, PieceInput};
+use crate::history::{shown_board, HistoryView};
+use crate::{GameState, Piece};
+use bevy::prelude::*;
+use chess_core::{PieceType, Square, Team};
+
+const HINT_COLOR: Color = Color::srgba(0.1, 0.1, 0.1, 0.3);
+const LAST_MOVE_COLOR: Color = Color::srgba(1.0, 0.85, 0.2, 0.4);
+const CHECK_COLOR: Color = Color::srgba(0.9, 0.1, 0.1, 0.6);
+
+/// Whether the selected piece's legal moves are marked, with a dot on each
+/// empty tile and a ring around each piece it can take. A piece being dragged
+/// always shows where it can be dropped.
+#[derive(Resource, Component, Clone, Copy, Eq, PartialEq, Debug)]
+pub struct MoveHints(pub bool);
+
+impl Default for MoveHints {
+    fn default() -> Self {
+        MoveHints(true)
+    }
+}
+
+/// Whether the tiles the last move was made from and to are tinted.
+#[derive(Resource, Component, Clone, Copy, Eq, PartialEq, Debug)]
+pub struct LastMoveHighlight(pub bool);
+
+impl Default for LastMoveHighlight {
+    fn default() -> Self {
+        LastMoveHighlight(true)
+    }
+}
+
+/// Whether the king's tile turns red while it is in check.
+#[derive(Resource, Component, Clone, Copy, Eq, PartialEq, Debug)]
+pub struct CheckHighlight(pub bool);
+
+impl Default for CheckHighlight {
+    fn default() -> Self {
+        CheckHighlight(true)
+    }
+}
+
+#[derive(Resource)]
+struct HintMeshes {
+    dot: Handle<Mesh>,
+    ring: Handle<Mesh>,
+    material: Handle<ColorMaterial>,
+}
+
+#[derive(Component)]
+struct MoveHint;
+
+/// A tinted tile showing something about the position: the last move or a
+/// king in check.
+#[derive(Component)]
+struct PositionOverlay;
+
+/// The position, the move being looked at, the way up the board is and the
+/// settings the overlays were last drawn for.
+type OverlayKey = (u64, usize, Option<usize>, Team, bool, bool);
+
+pub fn highlight_plugin(app: &mut App) {
+    app.init_resource::<MoveHints>()
+        .init_resource::<LastMoveHighlight>()
+        .init_resource::<CheckHighlight>()
+        .add_systems(Startup, load_hint_meshes)
+        .add_systems(
+            Update,
+            (move_hint_system, position_overlay_system).run_if(in_state(GameStatus::Game)),
+        );
+}
+
+fn load_hint_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(HintMeshes {
+        dot: meshes.add(Circle::new(TILE_SIZE.x * 0.16)),
+        ring: meshes.add(Annulus::new(TILE_SIZE.x * 0.4, TILE_SIZE.x * 0.5)),
+        material: materials.add(HINT_COLOR),
+    });
+}
+
+/// Marks where the picked up or selected piece can go whenever the selection
+/// changes. The move hints setting only covers selecting a piece; one being
+/// dragged always shows where it can go.
+fn move_hint_system(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    move_hints: Res<MoveHints>,
+    hint_meshes: Res<HintMeshes>,
+    pieces: Query<&Piece>,
+    hints: Query<Entity, With<MoveHint>>,
+    mut shown: Local<Option<(PieceInput, MoveHints)>>,
+) {
+    let key = (game_state.piece_input, *move_hints);
+    if *shown == Some(key) && !game_state.is_added() {
+        return;
+    }
+    *shown = Some(key);
+
+    for entity in &hints {
+        commands.entity(entity).despawn();
+    }
+    let dragging = matches!(
+        game_state.piece_input,
+        PieceInput::Held { dragging: true, .. }
+    );
+    let selected = game_state
+        .piece_input
+        .piece()
+        .and_then(|entity| pieces.get(entity).ok());
+    let Some(piece) = selected.filter(|_| dragging || move_hints.0) else {
+        return;
+    };
+
+    let board = &game_state.board;
+    let from = square_for_label(piece.position.position_label);
+    for target in &piece.available_moves {
+        let to = square_for_label(target.position_label);
+        let captures = board
+            .find_move(from, to, None)
+            .or_else(|| board.find_move(from, to, Some(PieceType::Queen)))
+            .is_some_and(|mv| mv.captured_square().is_some());
+        let mesh = if captures {
+            hint_meshes.ring.clone()
+        } else {
+            hint_meshes.dot.clone()
+        };
+        commands.spawn((
+            DespawnOnExit(GameStatus::Game),
+            MoveHint,
+            Mesh2d(mesh),
+            MeshMaterial2d(hint_meshes.material.clone()),
+            Transform::from_translation(target.coordinates.extend(1.0)),
+        ));
+    }
+}
+
+/// Tints the tiles of the last move and the king in check in the position on
+/// the board, which is the one being looked at when going through the moves.
+#[allow(clippy::too_many_arguments)]
+fn position_overlay_system(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    history_view: Option<Res<HistoryView>>,
+    orientation: Res<BoardOrientation>,
+    last_move_highlight: Res<LastMoveHighlight>,
+    check_highlight: Res<CheckHighlight>,
+    overlays: Query<Entity, With<PositionOverlay>>,
+    mut shown: Local<Option<OverlayKey>>,
+) {
+    let live = &game_state.board;
+    let history_view = history_view.as_deref();
+    let key = (
+        live.hash(),
+        live.moves().count(),
+        history_view.map(HistoryView::ply),
+        orientation.0,
+        last_move_highlight.0,
+        check_highlight.0,
+    );
+    if *shown == Some(key) && !game_state.is_added() {
+        return;
+    }
+    *shown = Some(key);
+
+    for entity in &overlays {
+        commands.entity(entity).despawn();
+    }
+    let board = shown_board(live, history_view);
+    let mut tint = |square: Square, color: Color| {
+        let (row, col) = index_for_square(square);
+        let coordinates = game_state.tiles[row][col].position.coordinates;
+        commands.spawn((
+            DespawnOnExit(GameStatus::Game),
+            PositionOverlay,
+            Sprite {
+                color,
+                custom_size: Some(TILE_SIZE),
+                ..default()
+            },
+            Transform::from_translation(coordinates.extend(0.5)),
+        ));
+    };
+
+    if let Some(mv) = board.last_move().filter(|_| last_move_highlight.0) {
+        tint(mv.from, LAST_MOVE_COLOR);
+        tint(mv.to, LAST_MOVE_COLOR);
+    }
+    if check_highlight.0 && board.is_in_check() {
+        if let Some(king) = board.king_square(board.side_to_move()) {
+            tint(king, CHECK_COLOR);
+        }
+    }
+}
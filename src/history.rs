@@ -0,0 +1,502 @@
+use crate::game::{respawn_pieces, show_position, GamePhase, GameStatus, ImageCache};
+use crate::menu::{HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON};
+use crate::pieces::get_piece_image;
+use crate::{GameState, Piece, TEXT_COLOR};
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use chess_core::{Board, PieceType, Team};
+
+const PANEL_WIDTH: f32 = 200.0;
+const PANEL_COLOR: Color = Color::srgb(0.1, 0.1, 0.1);
+const MOVE_FONT_SIZE: f32 = 18.0;
+const LINE_HEIGHT: f32 = 24.0;
+const TRAY_PIECE_SIZE: f32 = 20.0;
+
+/// A position from earlier in the game shown on the board in place of the live
+/// one. Nothing can be moved while it is shown, and a change to the live
+/// position, such as the computer moving, goes back to it.
+#[derive(Resource)]
+pub struct HistoryView {
+    ply: usize,
+    /// The hash and ply of the live position when the view was opened.
+    live: (u64, usize),
+}
+
+impl HistoryView {
+    /// How many moves into the game the position being looked at is.
+    pub(crate) fn ply(&self) -> usize {
+        self.ply
+    }
+
+    /// The position being looked at, in the game `live` has reached.
+    pub(crate) fn board(&self, live: &Board) -> Board {
+        let mut board = live.clone();
+        while board.moves().count() > self.ply {
+            board.unmake_move();
+        }
+        board
+    }
+}
+
+/// The position on the board: the live one, or the earlier one being looked at.
+pub(crate) fn shown_board(live: &Board, history_view: Option<&HistoryView>) -> Board {
+    history_view.map_or_else(|| live.clone(), |view| view.board(live))
+}
+
+/// Moves the view through the game. The move buttons, "Back to live" and the
+/// arrow keys write these.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+enum HistoryStep {
+    /// Shows the position after this many moves.
+    Ply(usize),
+    Back,
+    Forward,
+    Live,
+}
+
+#[derive(Component)]
+struct MoveList;
+
+/// The button for a move, holding how many moves have been played after it.
+#[derive(Component)]
+struct MoveButton(usize);
+
+#[derive(Component)]
+struct BackToLiveButton;
+
+/// The pieces `team` has captured, and its lead in material if it has one.
+#[derive(Component)]
+struct CapturedTray(Team);
+
+pub fn history_plugin(app: &mut App) {
+    app.add_message::<HistoryStep>()
+        .add_systems(OnEnter(GameStatus::Game), move_panel_setup)
+        .add_systems(
+            Update,
+            (
+                (history_keyboard_system, history_button_system)
+                    .run_if(in_state(GamePhase::Playing)),
+                live_position_changed_system,
+                apply_history_step_system,
+                move_list_system,
+                captured_tray_system,
+                move_list_scroll_system,
+            )
+                .chain()
+                .run_if(in_state(GameStatus::Game)),
+        )
+        .add_systems(OnExit(GameStatus::Game), close_history_view);
+}
+
+fn move_panel_setup(mut commands: Commands) {
+    let tray_node = Node {
+        flex_direction: FlexDirection::Row,
+        flex_wrap: FlexWrap::Wrap,
+        align_items: AlignItems::Center,
+        min_height: px(TRAY_PIECE_SIZE * 2.0),
+        padding: UiRect::all(px(4)),
+        ..default()
+    };
+
+    // Black's captures sit at the top, on Black's side of the board
+    commands.spawn((
+        DespawnOnExit(GameStatus::Game),
+        Node {
+            position_type: PositionType::Absolute,
+            left: px(10),
+            top: px(40),
+            bottom: px(40),
+            width: px(PANEL_WIDTH),
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        BackgroundColor(PANEL_COLOR),
+        children![
+            (CapturedTray(Team::Black), tray_node.clone()),
+            (
+                MoveList,
+                Node {
+                    flex_direction: FlexDirection::Column,
+                    flex_grow: 1.0,
+                    overflow: Overflow::scroll_y(),
+                    padding: UiRect::horizontal(px(4)),
+                    ..default()
+                },
+                ScrollPosition::default(),
+            ),
+            (
+                Button,
+                BackToLiveButton,
+                Node {
+                    height: px(36),
+                    margin: UiRect::all(px(4)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                BackgroundColor(NORMAL_BUTTON),
+                Visibility::Hidden,
+                children![(
+                    Text::new("Back to live"),
+                    TextFont {
+                        font_size: MOVE_FONT_SIZE,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                )],
+            ),
+            (CapturedTray(Team::White), tray_node),
+        ],
+    ));
+}
+
+fn history_keyboard_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut step_writer: MessageWriter<HistoryStep>,
+) {
+    if keys.just_pressed(KeyCode::ArrowLeft) {
+        step_writer.write(HistoryStep::Back);
+    } else if keys.just_pressed(KeyCode::ArrowRight) {
+        step_writer.write(HistoryStep::Forward);
+    }
+}
+
+type HistoryButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Interaction,
+        Option<&'static MoveButton>,
+        Option<&'static mut BackgroundColor>,
+    ),
+    (
+        Changed<Interaction>,
+        Or<(With<MoveButton>, With<BackToLiveButton>)>,
+    ),
+>;
+
+fn history_button_system(
+    mut interaction_query: HistoryButtonQuery,
+    mut step_writer: MessageWriter<HistoryStep>,
+) {
+    for (interaction, move_button, back_to_live) in &mut interaction_query {
+        match (move_button, back_to_live) {
+            (Some(MoveButton(ply)), _) if *interaction == Interaction::Pressed => {
+                step_writer.write(HistoryStep::Ply(*ply));
+            }
+            // the move buttons keep their colour, which marks the move on the board
+            (None, Some(mut background_color)) => {
+                *background_color = match *interaction {
+                    Interaction::Pressed => {
+                        step_writer.write(HistoryStep::Live);
+                        PRESSED_BUTTON.into()
+                    }
+                    Interaction::Hovered => HOVERED_BUTTON.into(),
+                    Interaction::None => NORMAL_BUTTON.into(),
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Closes the view when the live position changes under it. Whatever changed
+/// it has already put the live pieces back on the board.
+fn live_position_changed_system(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    history_view: Option<Res<HistoryView>>,
+) {
+    if history_view.is_some_and(|view| view.live != position_key(&game_state.board)) {
+        commands.remove_resource::<HistoryView>();
+    }
+}
+
+fn apply_history_step_system(
+    mut commands: Commands,
+    mut step_reader: MessageReader<HistoryStep>,
+    mut game_state: ResMut<GameState>,
+    history_view: Option<Res<HistoryView>>,
+    image_cache: Res<ImageCache>,
+    query_pieces: Query<Entity, With<Piece>>,
+) {
+    let live_ply = game_state.board.moves().count();
+    let current = history_view.map_or(live_ply, |view| view.ply);
+    let mut target = current;
+    for step in step_reader.read() {
+        target = match step {
+            HistoryStep::Ply(ply) => *ply,
+            HistoryStep::Back => target.saturating_sub(1),
+            HistoryStep::Forward => target + 1,
+            HistoryStep::Live => live_ply,
+        }
+        .min(live_ply);
+    }
+    // a move half made can't be looked away from
+    if target == current || game_state.pending_promotion.is_some() {
+        return;
+    }
+
+    if target == live_ply {
+        commands.remove_resource::<HistoryView>();
+        respawn_pieces(&mut commands, &image_cache, &mut game_state, &query_pieces);
+        return;
+    }
+    let view = HistoryView {
+        ply: target,
+        live: position_key(&game_state.board),
+    };
+    let board = view.board(&game_state.board);
+    commands.insert_resource(view);
+    show_position(
+        &mut commands,
+        &image_cache,
+        &mut game_state,
+        &query_pieces,
+        &board,
+    );
+}
+
+fn position_key(board: &Board) -> (u64, usize) {
+    (board.hash(), board.moves().count())
+}
+
+/// The game in SAN with the position it started from.
+fn game_moves(board: &Board) -> (Board, Vec<String>) {
+    let mut start = board.clone();
+    while start.unmake_move().is_some() {}
+    let mut position = start.clone();
+    let sans = board
+        .moves()
+        .map(|mv| {
+            let san = position.san(mv);
+            position.make_move(mv);
+            san
+        })
+        .collect();
+    (start, sans)
+}
+
+/// Rebuilds the move list whenever the game or the move being viewed changes,
+/// one row per move number with the move shown on the board highlighted.
+fn move_list_system(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    history_view: Option<Res<HistoryView>>,
+    list: Single<(Entity, &mut ScrollPosition), With<MoveList>>,
+    mut back_to_live: Single<&mut Visibility, With<BackToLiveButton>>,
+    mut shown: Local<Option<(u64, usize, Option<usize>)>>,
+) {
+    let board = &game_state.board;
+    let viewed = history_view.map(|view| view.ply);
+    let key = (board.hash(), board.moves().count(), viewed);
+    // a new game brings a new GameState, and a new panel to fill
+    if *shown == Some(key) && !game_state.is_added() {
+        return;
+    }
+    let new_move = shown.is_none_or(|(_, plies, _)| plies != key.1);
+    *shown = Some(key);
+
+    **back_to_live = if viewed.is_some() {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+
+    let (start, sans) = game_moves(board);
+    let highlighted = viewed.unwrap_or(sans.len());
+    let (list, mut scroll_position) = list.into_inner();
+    commands.entity(list).despawn_related::<Children>();
+    commands.entity(list).with_children(|parent| {
+        let mut number = start.fullmove_number();
+        let mut white_moves_first = start.side_to_move() == Team::White;
+        let mut sans = sans.into_iter().enumerate().peekable();
+        while sans.peek().is_some() {
+            let mut row = parent.spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                height: px(LINE_HEIGHT),
+                flex_shrink: 0.0,
+                ..default()
+            });
+            row.with_children(|row| {
+                row.spawn((
+                    Text::new(format!("{}.", number)),
+                    move_text_style(),
+                    Node {
+                        width: px(36),
+                        ..default()
+                    },
+                ));
+                // a game from a position with Black to move starts "1... e5"
+                if !white_moves_first {
+                    row.spawn((Text::new("..."), move_text_style(), move_cell_node()));
+                }
+                let moves_in_row = if white_moves_first { 2 } else { 1 };
+                for (index, san) in sans.by_ref().take(moves_in_row) {
+                    let ply = index + 1;
+                    let color = if ply == highlighted {
+                        PRESSED_BUTTON
+                    } else {
+                        Color::NONE
+                    };
+                    row.spawn((
+                        Button,
+                        MoveButton(ply),
+                        move_cell_node(),
+                        BackgroundColor(color),
+                        children![(Text::new(san), move_text_style())],
+                    ));
+                }
+            });
+            number += 1;
+            white_moves_first = true;
+        }
+    });
+
+    // follow the game as it is played; layout clamps this to the last row
+    if new_move {
+        scroll_position.y = f32::MAX;
+    }
+}
+
+fn move_text_style() -> (TextFont, TextColor) {
+    (
+        TextFont {
+            font_size: MOVE_FONT_SIZE,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    )
+}
+
+fn move_cell_node() -> Node {
+    Node {
+        width: px(72),
+        height: px(LINE_HEIGHT - 2.0),
+        padding: UiRect::horizontal(px(4)),
+        align_items: AlignItems::Center,
+        ..default()
+    }
+}
+
+/// Shows the pieces each side has captured in the position on the board and
+/// the material lead, counting pawns as 1, minor pieces 3, rooks 5 and queens 9.
+fn captured_tray_system(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    history_view: Option<Res<HistoryView>>,
+    image_cache: Res<ImageCache>,
+    trays: Query<(Entity, &CapturedTray)>,
+    mut shown: Local<Option<(u64, usize, Option<usize>)>>,
+) {
+    let live = &game_state.board;
+    let history_view = history_view.as_deref();
+    let key = (
+        live.hash(),
+        live.moves().count(),
+        history_view.map(|view| view.ply),
+    );
+    // a new game brings a new GameState, and a new panel to fill
+    if *shown == Some(key) && !game_state.is_added() {
+        return;
+    }
+    *shown = Some(key);
+
+    let board = shown_board(live, history_view);
+    let mut start = board.clone();
+    while start.unmake_move().is_some() {}
+
+    let white_lead = material(&board, Team::White) - material(&board, Team::Black);
+    for (tray, CapturedTray(team)) in &trays {
+        let captured = team.opponent();
+        commands.entity(tray).despawn_related::<Children>();
+        commands.entity(tray).with_children(|parent| {
+            for piece_type in [
+                PieceType::Pawn,
+                PieceType::Knight,
+                PieceType::Bishop,
+                PieceType::Rook,
+                PieceType::Queen,
+            ] {
+                // promotions can leave more on the board than there were
+                let missing = count(&start, captured, piece_type)
+                    .saturating_sub(count(&board, captured, piece_type));
+                for _ in 0..missing {
+                    parent.spawn((
+                        ImageNode::new(get_piece_image(&image_cache, captured, piece_type)),
+                        Node {
+                            width: px(TRAY_PIECE_SIZE),
+                            height: px(TRAY_PIECE_SIZE),
+                            ..default()
+                        },
+                    ));
+                }
+            }
+            let lead = match team {
+                Team::White => white_lead,
+                Team::Black => -white_lead,
+            };
+            if lead > 0 {
+                parent.spawn((
+                    Text::new(format!("+{}", lead)),
+                    move_text_style(),
+                    Node {
+                        margin: UiRect::left(px(4)),
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+fn count(board: &Board, team: Team, piece_type: PieceType) -> usize {
+    chess_core::Square::all()
+        .filter(|square| board.piece_at(*square) == Some((team, piece_type)))
+        .count()
+}
+
+fn material(board: &Board, team: Team) -> i32 {
+    chess_core::Square::all()
+        .filter_map(|square| board.piece_at(square))
+        .filter(|(owner, _)| *owner == team)
+        .map(|(_, piece_type)| match piece_type {
+            PieceType::Pawn => 1,
+            PieceType::Knight | PieceType::Bishop => 3,
+            PieceType::Rook => 5,
+            PieceType::Queen => 9,
+            PieceType::King => 0,
+        })
+        .sum()
+}
+
+/// Scrolls the move list with the mouse wheel while the pointer is over the
+/// panel. Bevy leaves wheel scrolling to the app.
+fn move_list_scroll_system(
+    mut wheel_reader: MessageReader<MouseWheel>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    list: Single<(&mut ScrollPosition, &ComputedNode), With<MoveList>>,
+) {
+    let over_panel = window
+        .cursor_position()
+        .is_some_and(|cursor| cursor.x <= 10.0 + PANEL_WIDTH);
+    let (mut scroll_position, computed) = list.into_inner();
+    for wheel in wheel_reader.read() {
+        if !over_panel {
+            continue;
+        }
+        let lines = match wheel.unit {
+            MouseScrollUnit::Line => wheel.y * LINE_HEIGHT,
+            MouseScrollUnit::Pixel => wheel.y,
+        };
+        // start from where layout clamped it, not from where it was asked to be
+        let current = computed.scroll_position.y * computed.inverse_scale_factor;
+        scroll_position.y = (current - lines).max(0.0);
+    }
+}
+
+fn close_history_view(mut commands: Commands) {
+    commands.remove_resource::<HistoryView>();
+}
@@ -0,0 +1,196 @@
+use crate::clock::ClockSetting;
+use crate::game::{DrawRules, GameStatus, StartingPosition};
+use crate::menu::{
+    setting_button, HumanSide, MenuButtonAction, MenuState, SelectedOption, NORMAL_BUTTON,
+};
+use crate::network::NetworkGame;
+use crate::text_input::{TextInput, TextInputError};
+use crate::TEXT_COLOR;
+use bevy::color::palettes::css::CRIMSON;
+use bevy::prelude::*;
+use chess_core::Team;
+
+#[derive(Component)]
+struct HostButton;
+
+pub fn lobby_plugin(app: &mut App) {
+    app.add_systems(OnEnter(MenuState::Main), leave_network_game)
+        .add_systems(OnEnter(MenuState::HostGame), host_game_menu_setup)
+        .add_systems(
+            Update,
+            (setting_button::<HumanSide>, host_button_system).run_if(in_state(MenuState::HostGame)),
+        )
+        .add_systems(
+            Update,
+            network_lobby_menu_system.run_if(
+                in_state(MenuState::HostGame)
+                    .or(in_state(MenuState::JoinGame))
+                    .or(in_state(MenuState::WatchGame)),
+            ),
+        );
+}
+
+/// The side to play and a Host button, which starts waiting for another
+/// copy of the game to join. The line below says who it is waiting for.
+fn host_game_menu_setup(
+    mut commands: Commands,
+    mut text_input: ResMut<TextInput>,
+    human_side: Res<HumanSide>,
+) {
+    text_input.error = None;
+
+    let button_node = Node {
+        width: px(200),
+        height: px(65),
+        margin: UiRect::all(px(20)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 33.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+    let row_node = Node {
+        flex_direction: FlexDirection::Row,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            DespawnOnExit(MenuState::HostGame),
+            Node {
+                width: percent(100),
+                height: percent(100),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(CRIMSON.into()),
+                ))
+                .with_children(|parent| {
+                    parent.spawn(row_node.clone()).with_children(|parent| {
+                        parent.spawn((
+                            Text::new("Play as"),
+                            button_text_style.clone(),
+                            Node {
+                                width: px(150),
+                                margin: UiRect::all(px(20)),
+                                ..default()
+                            },
+                        ));
+                        for (side, text) in [
+                            (HumanSide(Team::White), "White"),
+                            (HumanSide(Team::Black), "Black"),
+                        ] {
+                            let mut entity = parent.spawn((
+                                Button,
+                                Node {
+                                    width: px(150),
+                                    height: px(65),
+                                    margin: UiRect::all(px(10)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                BackgroundColor(NORMAL_BUTTON),
+                                side,
+                                children![(Text::new(text), button_text_style.clone())],
+                            ));
+                            if *human_side == side {
+                                entity.insert(SelectedOption);
+                            }
+                        }
+                    });
+                    parent.spawn((
+                        TextInputError,
+                        Text::new(""),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                        Node {
+                            margin: UiRect::all(px(10)),
+                            ..default()
+                        },
+                    ));
+                    parent.spawn(row_node).with_children(|parent| {
+                        parent.spawn((
+                            Button,
+                            button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            HostButton,
+                            children![(Text::new("Host"), button_text_style.clone())],
+                        ));
+                        parent.spawn((
+                            Button,
+                            button_node,
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToMainMenu,
+                            children![(Text::new("Back"), button_text_style)],
+                        ));
+                    });
+                });
+        });
+}
+
+fn host_button_system(
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<HostButton>)>,
+    mut commands: Commands,
+    mut text_input: ResMut<TextInput>,
+    human_side: Res<HumanSide>,
+    clock_setting: Res<ClockSetting>,
+    draw_rules: Res<DrawRules>,
+    network_game: Option<Res<NetworkGame>>,
+) {
+    for interaction in &interaction_query {
+        if *interaction != Interaction::Pressed || network_game.is_some() {
+            continue;
+        }
+        match NetworkGame::host(human_side.0, clock_setting.time_control(), *draw_rules) {
+            Ok(network_game) => {
+                commands.insert_resource(StartingPosition::default());
+                commands.insert_resource(network_game);
+            }
+            Err(error) => text_input.error = Some(format!("Could not host: {}", error)),
+        }
+    }
+}
+
+/// Shows how finding the other player is going on the host, join and
+/// watch screens, and starts the game once they are found.
+fn network_lobby_menu_system(
+    network_game: Option<Res<NetworkGame>>,
+    mut text_input: ResMut<TextInput>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_state: ResMut<NextState<GameStatus>>,
+) {
+    let Some(network_game) = network_game else {
+        return;
+    };
+    if network_game.is_ready() {
+        game_state.set(GameStatus::Game);
+        menu_state.set(MenuState::Disabled);
+    } else if text_input.error.as_deref() != Some(network_game.status()) {
+        text_input.error = Some(network_game.status().to_string());
+    }
+}
+
+/// Stops hosting or joining when the player goes back to the main menu.
+fn leave_network_game(mut commands: Commands) {
+    commands.remove_resource::<NetworkGame>();
+}
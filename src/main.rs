@@ -1,7 +1,8 @@
-use crate::board::{simulate_move, ColLabel, Position, PositionLabel, Tile};
-use crate::pieces::{get_possible_moves_for_piece, KingData, PieceType, Team};
+use crate::board::{Position, Tile};
+use crate::pieces::{PieceType, Team};
 use bevy::window::{WindowResolution, WindowTheme};
 use bevy::{prelude::*, window::PresentMode};
+use chess_core::{Board, Move};
 
 #[derive(Resource, Debug, Component, PartialEq, Eq, Clone, Copy)]
 enum DisplayQuality {
@@ -18,9 +19,6 @@ const TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
 const WINDOW_WIDTH: u32 = 1080;
 const WINDOW_HEIGHT: u32 = 720;
 
-#[derive(Component)]
-pub struct Selected;
-
 #[derive(Component)]
 struct Light {
     coordinates: Vec2,
@@ -37,18 +35,42 @@ pub struct Piece {
 
 #[derive(Resource)]
 pub struct GameState {
-    turn: Team,
+    board: Board,
+    tiles: [[Tile; 8]; 8],
     highlight_coords: Vec2,
-    selected_piece: Option<Entity>,
-    board: [[Tile; 8]; 8],
-    white_king_data: KingData,
-    black_king_data: KingData,
+    piece_input: game::PieceInput,
+    pending_promotion: Option<Move>,
+    /// Moves taken back with undo, the most recently undone last.
+    undone_moves: Vec<Move>,
+}
+
+impl GameState {
+    /// Plays `mv` on the board. A new move forgets whatever could be redone.
+    fn play_move(&mut self, mv: Move) {
+        self.board.make_move(mv);
+        self.undone_moves.clear();
+    }
 }
 
 mod board;
-mod check;
+mod clock;
+mod engine;
+mod external;
+mod flip;
 mod game;
+mod game_over;
+mod highlight;
+mod history;
+mod lobby;
+mod network;
+mod offer;
+mod pause;
+mod pgn;
 mod pieces;
+mod promotion;
+mod replay;
+mod takeback;
+mod text_input;
 mod util;
 
 fn main() {
@@ -74,7 +96,29 @@ fn main() {
         .insert_resource(Volume(7))
         .init_state::<game::GameStatus>()
         .add_systems(Startup, setup)
-        .add_plugins((splash::splash_plugin, menu::menu_plugin, game::game_plugin))
+        .add_plugins((
+            splash::splash_plugin,
+            menu::menu_plugin,
+            text_input::text_input_plugin,
+            lobby::lobby_plugin,
+            pause::pause_plugin,
+            game::game_plugin,
+            highlight::highlight_plugin,
+            history::history_plugin,
+            flip::flip_plugin,
+            clock::clock_plugin,
+            network::network_plugin,
+            offer::offer_plugin,
+        ))
+        .add_plugins((
+            engine::engine_plugin,
+            external::external_engine_plugin,
+            promotion::promotion_plugin,
+            pgn::pgn_plugin,
+            replay::replay_plugin,
+            takeback::takeback_plugin,
+            game_over::game_over_plugin,
+        ))
         .run();
 }
 
@@ -134,26 +178,87 @@ mod splash {
 
 mod menu {
     use bevy::{app::AppExit, color::palettes::css::CRIMSON, ecs::spawn::SpawnIter, prelude::*};
+    use chess_core::Team;
 
-    use super::{game::GameStatus, TEXT_COLOR};
+    use super::{
+        clock::ClockSetting,
+        engine::Difficulty,
+        external::EngineProtocol,
+        game::{DrawRules, GamePhase, GameStatus, Players, StartingPosition},
+        highlight::{CheckHighlight, LastMoveHighlight, MoveHints},
+        DisplayQuality, Volume, TEXT_COLOR,
+    };
 
     pub fn menu_plugin(app: &mut App) {
         app.init_state::<MenuState>()
+            .init_resource::<HumanSide>()
+            .init_resource::<Opponent>()
             .add_systems(OnEnter(GameStatus::Menu), menu_setup)
             .add_systems(OnEnter(MenuState::Main), main_menu_setup)
             .add_systems(OnEnter(MenuState::Settings), settings_menu_setup)
+            .add_systems(
+                OnEnter(MenuState::SettingsDisplay),
+                display_settings_menu_setup,
+            )
+            .add_systems(
+                Update,
+                setting_button::<DisplayQuality>.run_if(in_state(MenuState::SettingsDisplay)),
+            )
+            .add_systems(OnEnter(MenuState::SettingsSound), sound_settings_menu_setup)
             .add_systems(
                 Update,
-                (menu_action, button_system).run_if(in_state(GameStatus::Menu)),
+                setting_button::<Volume>.run_if(in_state(MenuState::SettingsSound)),
+            )
+            .add_systems(OnEnter(MenuState::SettingsClock), clock_settings_menu_setup)
+            .add_systems(
+                Update,
+                setting_button::<ClockSetting>.run_if(in_state(MenuState::SettingsClock)),
+            )
+            .add_systems(OnEnter(MenuState::SettingsBoard), board_settings_menu_setup)
+            .add_systems(
+                Update,
+                (
+                    setting_button::<MoveHints>,
+                    setting_button::<LastMoveHighlight>,
+                    setting_button::<CheckHighlight>,
+                    setting_button::<DrawRules>,
+                )
+                    .run_if(in_state(MenuState::SettingsBoard)),
+            )
+            .add_systems(OnEnter(MenuState::PlayComputer), play_computer_menu_setup)
+            .add_systems(
+                Update,
+                (
+                    setting_button::<HumanSide>,
+                    setting_button::<Difficulty>,
+                    setting_button::<Opponent>,
+                )
+                    .run_if(in_state(MenuState::PlayComputer)),
+            )
+            .add_systems(
+                Update,
+                (menu_action, button_system)
+                    .run_if(in_state(GameStatus::Menu).or(in_state(GamePhase::Paused))),
             );
     }
 
     #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
-    enum MenuState {
+    pub(crate) enum MenuState {
         Main,
+        PlayComputer,
         Settings,
         SettingsDisplay,
         SettingsSound,
+        SettingsClock,
+        SettingsBoard,
+        HostGame,
+        JoinGame,
+        WatchGame,
+        LoadFen,
+        LoadPgn,
+        LoadEngine,
+        /// Over a paused game, which the settings screens go back to.
+        Pause,
         #[default]
         Disabled,
     }
@@ -165,8 +270,8 @@ mod menu {
     fn main_menu_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         let button_node = Node {
             width: px(300),
-            height: px(65),
-            margin: UiRect::all(px(20)),
+            height: px(50),
+            margin: UiRect::axes(px(20), px(4)),
             justify_content: JustifyContent::Center,
             align_items: AlignItems::Center,
             ..default()
@@ -183,6 +288,12 @@ mod menu {
         };
 
         let right_icon = asset_server.load("pieces/bB.png");
+        let computer_icon = asset_server.load("pieces/bQ.png");
+        let host_icon = asset_server.load("pieces/wP.png");
+        let join_icon = asset_server.load("pieces/bP.png");
+        let watch_icon = asset_server.load("pieces/wB.png");
+        let fen_icon = asset_server.load("pieces/wN.png");
+        let pgn_icon = asset_server.load("pieces/bN.png");
         let wrench_icon = asset_server.load("pieces/wK.png");
         let exit_icon = asset_server.load("pieces/wR.png");
 
@@ -212,7 +323,7 @@ mod menu {
                         },
                         TextColor(TEXT_COLOR),
                         Node {
-                            margin: UiRect::all(px(50)),
+                            margin: UiRect::all(px(20)),
                             ..default()
                         },
                     ),
@@ -230,6 +341,90 @@ mod menu {
                             ),
                         ]
                     ),
+                    (
+                        Button,
+                        button_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::PlayComputer,
+                        children![
+                            (ImageNode::new(computer_icon), button_icon_node.clone()),
+                            (
+                                Text::new("vs Computer"),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ),
+                        ]
+                    ),
+                    (
+                        Button,
+                        button_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::HostGame,
+                        children![
+                            (ImageNode::new(host_icon), button_icon_node.clone()),
+                            (
+                                Text::new("Host Game"),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ),
+                        ]
+                    ),
+                    (
+                        Button,
+                        button_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::JoinGame,
+                        children![
+                            (ImageNode::new(join_icon), button_icon_node.clone()),
+                            (
+                                Text::new("Join Game"),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ),
+                        ]
+                    ),
+                    (
+                        Button,
+                        button_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::WatchGame,
+                        children![
+                            (ImageNode::new(watch_icon), button_icon_node.clone()),
+                            (
+                                Text::new("Watch Game"),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ),
+                        ]
+                    ),
+                    (
+                        Button,
+                        button_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::LoadFen,
+                        children![
+                            (ImageNode::new(fen_icon), button_icon_node.clone()),
+                            (
+                                Text::new("Load FEN"),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ),
+                        ]
+                    ),
+                    (
+                        Button,
+                        button_node.clone(),
+                        BackgroundColor(NORMAL_BUTTON),
+                        MenuButtonAction::LoadPgn,
+                        children![
+                            (ImageNode::new(pgn_icon), button_icon_node.clone()),
+                            (
+                                Text::new("Load PGN"),
+                                button_text_font.clone(),
+                                TextColor(TEXT_COLOR),
+                            ),
+                        ]
+                    ),
                     (
                         Button,
                         button_node.clone(),
@@ -298,6 +493,8 @@ mod menu {
                     [
                         (MenuButtonAction::SettingsDisplay, "Display"),
                         (MenuButtonAction::SettingsSound, "Sound"),
+                        (MenuButtonAction::SettingsClock, "Clock"),
+                        (MenuButtonAction::SettingsBoard, "Board"),
                         (MenuButtonAction::BackToMainMenu, "Back"),
                     ]
                     .into_iter()
@@ -315,12 +512,532 @@ mod menu {
         ));
     }
 
+    /// A button for each display quality, with the current one selected.
+    fn display_settings_menu_setup(mut commands: Commands, display_quality: Res<DisplayQuality>) {
+        let button_node = Node {
+            width: px(200),
+            height: px(65),
+            margin: UiRect::all(px(20)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        let button_text_style = (
+            TextFont {
+                font_size: 33.0,
+                ..default()
+            },
+            TextColor(TEXT_COLOR),
+        );
+
+        commands
+            .spawn((
+                DespawnOnExit(MenuState::SettingsDisplay),
+                Node {
+                    width: percent(100),
+                    height: percent(100),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                OnSettingsMenuScreen,
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(CRIMSON.into()),
+                    ))
+                    .with_children(|parent| {
+                        parent
+                            .spawn(Node {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn((
+                                    Text::new("Display Quality"),
+                                    button_text_style.clone(),
+                                ));
+                                for quality in [
+                                    DisplayQuality::Low,
+                                    DisplayQuality::Medium,
+                                    DisplayQuality::High,
+                                ] {
+                                    let mut entity = parent.spawn((
+                                        Button,
+                                        Node {
+                                            width: px(150),
+                                            ..button_node.clone()
+                                        },
+                                        BackgroundColor(NORMAL_BUTTON),
+                                        quality,
+                                        children![(
+                                            Text::new(format!("{:?}", quality)),
+                                            button_text_style.clone(),
+                                        )],
+                                    ));
+                                    if *display_quality == quality {
+                                        entity.insert(SelectedOption);
+                                    }
+                                }
+                            });
+                        parent.spawn((
+                            Button,
+                            button_node,
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToSettings,
+                            children![(Text::new("Back"), button_text_style)],
+                        ));
+                    });
+            });
+    }
+
+    /// A row of buttons setting the volume from 0 to 9.
+    fn sound_settings_menu_setup(mut commands: Commands, volume: Res<Volume>) {
+        let button_node = Node {
+            width: px(200),
+            height: px(65),
+            margin: UiRect::all(px(20)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        let button_text_style = (
+            TextFont {
+                font_size: 33.0,
+                ..default()
+            },
+            TextColor(TEXT_COLOR),
+        );
+
+        commands
+            .spawn((
+                DespawnOnExit(MenuState::SettingsSound),
+                Node {
+                    width: percent(100),
+                    height: percent(100),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                OnSettingsMenuScreen,
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(CRIMSON.into()),
+                    ))
+                    .with_children(|parent| {
+                        parent
+                            .spawn(Node {
+                                align_items: AlignItems::Center,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent.spawn((Text::new("Volume"), button_text_style.clone()));
+                                for volume_setting in 0..10 {
+                                    let mut entity = parent.spawn((
+                                        Button,
+                                        Node {
+                                            width: px(30),
+                                            height: px(65),
+                                            ..button_node.clone()
+                                        },
+                                        BackgroundColor(NORMAL_BUTTON),
+                                        Volume(volume_setting),
+                                    ));
+                                    if *volume == Volume(volume_setting) {
+                                        entity.insert(SelectedOption);
+                                    }
+                                }
+                            });
+                        parent.spawn((
+                            Button,
+                            button_node,
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToSettings,
+                            children![(Text::new("Back"), button_text_style)],
+                        ));
+                    });
+            });
+    }
+
+    /// One button for each time control, with the one new games use selected.
+    fn clock_settings_menu_setup(mut commands: Commands, clock_setting: Res<ClockSetting>) {
+        let button_node = Node {
+            width: px(450),
+            height: px(55),
+            margin: UiRect::axes(px(20), px(5)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        let button_text_style = (
+            TextFont {
+                font_size: 28.0,
+                ..default()
+            },
+            TextColor(TEXT_COLOR),
+        );
+
+        commands
+            .spawn((
+                DespawnOnExit(MenuState::SettingsClock),
+                Node {
+                    width: percent(100),
+                    height: percent(100),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                OnSettingsMenuScreen,
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::vertical(px(10)),
+                            ..default()
+                        },
+                        BackgroundColor(CRIMSON.into()),
+                    ))
+                    .with_children(|parent| {
+                        for setting in ClockSetting::ALL {
+                            let mut entity = parent.spawn((
+                                Button,
+                                button_node.clone(),
+                                BackgroundColor(NORMAL_BUTTON),
+                                setting,
+                                children![(Text::new(setting.name()), button_text_style.clone())],
+                            ));
+                            if *clock_setting == setting {
+                                entity.insert(SelectedOption);
+                            }
+                        }
+                        parent.spawn((
+                            Button,
+                            button_node.clone(),
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToSettings,
+                            children![(Text::new("Back"), button_text_style.clone())],
+                        ));
+                    });
+            });
+    }
+
+    /// An On and an Off button for each of the overlays drawn on the board,
+    /// and for whether draws that could be claimed end the game on their own.
+    fn board_settings_menu_setup(
+        mut commands: Commands,
+        move_hints: Res<MoveHints>,
+        last_move_highlight: Res<LastMoveHighlight>,
+        check_highlight: Res<CheckHighlight>,
+        draw_rules: Res<DrawRules>,
+    ) {
+        let button_node = Node {
+            width: px(200),
+            height: px(65),
+            margin: UiRect::all(px(20)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+
+        commands
+            .spawn((
+                DespawnOnExit(MenuState::SettingsBoard),
+                Node {
+                    width: percent(100),
+                    height: percent(100),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                OnSettingsMenuScreen,
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(CRIMSON.into()),
+                    ))
+                    .with_children(|parent| {
+                        toggle_row(parent, "Move hints", *move_hints, MoveHints);
+                        toggle_row(parent, "Last move", *last_move_highlight, LastMoveHighlight);
+                        toggle_row(parent, "Check", *check_highlight, CheckHighlight);
+                        toggle_row(parent, "Auto draws", *draw_rules, |automatic_draw_claims| {
+                            DrawRules {
+                                automatic_draw_claims,
+                            }
+                        });
+                        parent.spawn((
+                            Button,
+                            button_node,
+                            BackgroundColor(NORMAL_BUTTON),
+                            MenuButtonAction::BackToSettings,
+                            children![(
+                                Text::new("Back"),
+                                TextFont {
+                                    font_size: 33.0,
+                                    ..default()
+                                },
+                                TextColor(TEXT_COLOR),
+                            )],
+                        ));
+                    });
+            });
+    }
+
+    /// A labelled row with On and Off buttons for a setting that is one or the
+    /// other, made from a bool by `setting`.
+    fn toggle_row<T: Component + PartialEq>(
+        parent: &mut ChildSpawnerCommands,
+        label: &str,
+        current: T,
+        setting: fn(bool) -> T,
+    ) {
+        let text_style = (
+            TextFont {
+                font_size: 33.0,
+                ..default()
+            },
+            TextColor(TEXT_COLOR),
+        );
+        parent
+            .spawn(Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                ..default()
+            })
+            .with_children(|parent| {
+                parent.spawn((
+                    Text::new(label),
+                    text_style.clone(),
+                    Node {
+                        width: px(220),
+                        margin: UiRect::all(px(20)),
+                        ..default()
+                    },
+                ));
+                for (on, text) in [(true, "On"), (false, "Off")] {
+                    let option = setting(on);
+                    let selected = option == current;
+                    let mut entity = parent.spawn((
+                        Button,
+                        Node {
+                            width: px(120),
+                            height: px(65),
+                            margin: UiRect::all(px(10)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(NORMAL_BUTTON),
+                        option,
+                        children![(Text::new(text), text_style.clone())],
+                    ));
+                    if selected {
+                        entity.insert(SelectedOption);
+                    }
+                }
+            });
+    }
+
+    fn play_computer_menu_setup(
+        mut commands: Commands,
+        human_side: Res<HumanSide>,
+        difficulty: Res<Difficulty>,
+        opponent: Res<Opponent>,
+    ) {
+        let button_node = Node {
+            width: px(200),
+            height: px(65),
+            margin: UiRect::all(px(20)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        let option_node = Node {
+            width: px(150),
+            height: px(65),
+            margin: UiRect::all(px(10)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        let button_text_style = (
+            TextFont {
+                font_size: 33.0,
+                ..default()
+            },
+            TextColor(TEXT_COLOR),
+        );
+        let row_node = Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..default()
+        };
+        let label_node = Node {
+            width: px(150),
+            margin: UiRect::all(px(20)),
+            ..default()
+        };
+
+        commands
+            .spawn((
+                DespawnOnExit(MenuState::PlayComputer),
+                Node {
+                    width: percent(100),
+                    height: percent(100),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+            ))
+            .with_children(|parent| {
+                parent
+                    .spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(CRIMSON.into()),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(row_node.clone()).with_children(|parent| {
+                            parent.spawn((
+                                Text::new("Play as"),
+                                button_text_style.clone(),
+                                label_node.clone(),
+                            ));
+                            for (side, text) in [
+                                (HumanSide(Team::White), "White"),
+                                (HumanSide(Team::Black), "Black"),
+                            ] {
+                                let mut entity = parent.spawn((
+                                    Button,
+                                    option_node.clone(),
+                                    BackgroundColor(NORMAL_BUTTON),
+                                    side,
+                                    children![(Text::new(text), button_text_style.clone())],
+                                ));
+                                if *human_side == side {
+                                    entity.insert(SelectedOption);
+                                }
+                            }
+                        });
+                        parent.spawn(row_node.clone()).with_children(|parent| {
+                            parent.spawn((
+                                Text::new("Level"),
+                                button_text_style.clone(),
+                                label_node.clone(),
+                            ));
+                            for level in Difficulty::ALL {
+                                let mut entity = parent.spawn((
+                                    Button,
+                                    option_node.clone(),
+                                    BackgroundColor(NORMAL_BUTTON),
+                                    level,
+                                    children![(Text::new(level.name()), button_text_style.clone())],
+                                ));
+                                if *difficulty == level {
+                                    entity.insert(SelectedOption);
+                                }
+                            }
+                        });
+                        parent.spawn(row_node.clone()).with_children(|parent| {
+                            parent.spawn((
+                                Text::new("Engine"),
+                                button_text_style.clone(),
+                                label_node.clone(),
+                            ));
+                            for (choice, text) in [
+                                (Opponent::BuiltIn, "Built-in"),
+                                (Opponent::Uci, "UCI"),
+                                (Opponent::Xboard, "CECP"),
+                            ] {
+                                let mut entity = parent.spawn((
+                                    Button,
+                                    option_node.clone(),
+                                    BackgroundColor(NORMAL_BUTTON),
+                                    choice,
+                                    children![(Text::new(text), button_text_style.clone())],
+                                ));
+                                if *opponent == choice {
+                                    entity.insert(SelectedOption);
+                                }
+                            }
+                        });
+                        parent.spawn((
+                            row_node,
+                            Children::spawn(SpawnIter(
+                                [
+                                    (MenuButtonAction::StartVsComputer, "Start"),
+                                    (MenuButtonAction::BackToMainMenu, "Back"),
+                                ]
+                                .into_iter()
+                                .map(move |(action, text)| {
+                                    (
+                                        Button,
+                                        button_node.clone(),
+                                        BackgroundColor(NORMAL_BUTTON),
+                                        action,
+                                        children![(Text::new(text), button_text_style.clone())],
+                                    )
+                                }),
+                            )),
+                        ));
+                    });
+            });
+    }
+
+    type ChangedButton = (Changed<Interaction>, With<Button>);
+    type SelectedOptionOf<T> = (With<SelectedOption>, With<T>);
+
+    /// Makes the pressed option button the selected one of its kind and stores
+    /// its value in the resource of the same type.
+    pub(crate) fn setting_button<T: Resource + Component + PartialEq + Copy>(
+        interaction_query: Query<(&Interaction, &T, Entity), ChangedButton>,
+        selected_query: Single<(Entity, &mut BackgroundColor), SelectedOptionOf<T>>,
+        mut commands: Commands,
+        mut setting: ResMut<T>,
+    ) {
+        let (previous_button, mut previous_button_color) = selected_query.into_inner();
+        for (interaction, button_setting, entity) in &interaction_query {
+            if *interaction == Interaction::Pressed && *setting != *button_setting {
+                *previous_button_color = NORMAL_BUTTON.into();
+                commands.entity(previous_button).remove::<SelectedOption>();
+                commands.entity(entity).insert(SelectedOption);
+                *setting = *button_setting;
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn menu_action(
-        interaction_query: Query<
-            (&Interaction, &MenuButtonAction),
-            (Changed<Interaction>, With<Button>),
-        >,
+        interaction_query: Query<(&Interaction, &MenuButtonAction), ChangedButton>,
+        mut commands: Commands,
         mut app_exit_writer: MessageWriter<AppExit>,
+        human_side: Res<HumanSide>,
+        opponent: Res<Opponent>,
+        game_phase: Option<Res<State<GamePhase>>>,
         mut menu_state: ResMut<NextState<MenuState>>,
         mut game_state: ResMut<NextState<GameStatus>>,
     ) {
@@ -331,9 +1048,27 @@ mod menu {
                         app_exit_writer.write(AppExit::Success);
                     }
                     MenuButtonAction::Play => {
+                        commands.insert_resource(StartingPosition::default());
+                        commands.insert_resource(Players::default());
                         game_state.set(GameStatus::Game);
                         menu_state.set(MenuState::Disabled);
                     }
+                    MenuButtonAction::PlayComputer => menu_state.set(MenuState::PlayComputer),
+                    MenuButtonAction::StartVsComputer => {
+                        commands.insert_resource(StartingPosition::default());
+                        commands.insert_resource(Players::against_computer(human_side.0));
+                        if opponent.protocol().is_some() {
+                            menu_state.set(MenuState::LoadEngine);
+                        } else {
+                            game_state.set(GameStatus::Game);
+                            menu_state.set(MenuState::Disabled);
+                        }
+                    }
+                    MenuButtonAction::HostGame => menu_state.set(MenuState::HostGame),
+                    MenuButtonAction::JoinGame => menu_state.set(MenuState::JoinGame),
+                    MenuButtonAction::WatchGame => menu_state.set(MenuState::WatchGame),
+                    MenuButtonAction::LoadFen => menu_state.set(MenuState::LoadFen),
+                    MenuButtonAction::LoadPgn => menu_state.set(MenuState::LoadPgn),
                     MenuButtonAction::Settings => menu_state.set(MenuState::Settings),
                     MenuButtonAction::SettingsDisplay => {
                         menu_state.set(MenuState::SettingsDisplay);
@@ -341,6 +1076,16 @@ mod menu {
                     MenuButtonAction::SettingsSound => {
                         menu_state.set(MenuState::SettingsSound);
                     }
+                    MenuButtonAction::SettingsClock => {
+                        menu_state.set(MenuState::SettingsClock);
+                    }
+                    MenuButtonAction::SettingsBoard => {
+                        menu_state.set(MenuState::SettingsBoard);
+                    }
+                    // the settings are opened from the pause screen during a game
+                    MenuButtonAction::BackToMainMenu if game_phase.is_some() => {
+                        menu_state.set(MenuState::Pause);
+                    }
                     MenuButtonAction::BackToMainMenu => menu_state.set(MenuState::Main),
                     MenuButtonAction::BackToSettings => {
                         menu_state.set(MenuState::Settings);
@@ -350,10 +1095,10 @@ mod menu {
         }
     }
 
-    fn button_system(
+    pub(crate) fn button_system(
         mut interaction_query: Query<
             (&Interaction, &mut BackgroundColor, Option<&SelectedOption>),
-            (Changed<Interaction>, With<Button>),
+            ChangedButton,
         >,
     ) {
         for (interaction, mut background_color, selected) in &mut interaction_query {
@@ -373,21 +1118,60 @@ mod menu {
     struct OnSettingsMenuScreen;
 
     #[derive(Component)]
-    struct SelectedOption;
+    pub(crate) struct SelectedOption;
+
+    /// The colour the player takes against the computer.
+    #[derive(Resource, Component, Clone, Copy, PartialEq, Eq, Debug)]
+    pub(crate) struct HumanSide(pub(crate) Team);
+
+    impl Default for HumanSide {
+        fn default() -> Self {
+            HumanSide(Team::White)
+        }
+    }
+
+    /// What plays the computer's side: the built-in search, or an engine
+    /// program that speaks UCI or CECP.
+    #[derive(Resource, Component, Clone, Copy, Default, PartialEq, Eq, Debug)]
+    pub(crate) enum Opponent {
+        #[default]
+        BuiltIn,
+        Uci,
+        Xboard,
+    }
+
+    impl Opponent {
+        pub(crate) fn protocol(self) -> Option<EngineProtocol> {
+            match self {
+                Opponent::BuiltIn => None,
+                Opponent::Uci => Some(EngineProtocol::Uci),
+                Opponent::Xboard => Some(EngineProtocol::Xboard),
+            }
+        }
+    }
 
     #[derive(Component)]
-    enum MenuButtonAction {
+    pub(crate) enum MenuButtonAction {
         Play,
+        PlayComputer,
+        StartVsComputer,
+        HostGame,
+        JoinGame,
+        WatchGame,
+        LoadFen,
+        LoadPgn,
         Settings,
         SettingsDisplay,
         SettingsSound,
+        SettingsClock,
+        SettingsBoard,
         BackToMainMenu,
         BackToSettings,
         Quit,
     }
 
-    const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
-    const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+    pub(crate) const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+    pub(crate) const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
     const HOVERED_PRESSED_BUTTON: Color = Color::srgb(0.25, 0.65, 0.25);
-    const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+    pub(crate) const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
 }
@@ -0,0 +1,394 @@
+use crate::clock::{ClockSystems, GameClock};
+use crate::game::{
+    respawn_pieces, DrawRules, GameResult, GameStatus, ImageCache, PlayerKind, Players,
+    StartingPosition,
+};
+use crate::offer::{Offer, OfferSystems};
+use crate::{GameState, Piece, TEXT_COLOR};
+use bevy::prelude::*;
+use chess_core::{Board, DrawReason, GameOutcome, Team, TimeControl, WinReason};
+use chess_protocol::net::{NetMessage, DEFAULT_PORT};
+use chess_protocol::{Peer, PeerEvent, Position};
+use std::io;
+use std::time::Duration;
+
+/// A game against another copy of the game over the network, or one being
+/// watched, from choosing to host, join or watch one until going back to the
+/// menu, with any rematches on the way.
+#[derive(Resource, Deref, DerefMut)]
+pub struct NetworkGame {
+    #[deref]
+    peer: Peer,
+    /// Times from the host for the clocks, still to be put on them.
+    clock_times: Option<(Duration, Duration)>,
+    /// The position the guest was sent before the game started.
+    position: Option<Board>,
+}
+
+impl NetworkGame {
+    /// Starts listening for a guest to play the other side from `local_side`.
+    pub fn host(
+        local_side: Team,
+        time_control: Option<TimeControl>,
+        draw_rules: DrawRules,
+    ) -> io::Result<NetworkGame> {
+        let peer = Peer::host(
+            DEFAULT_PORT,
+            &local_name(),
+            local_side,
+            time_control,
+            draw_rules.automatic_draw_claims,
+        )?;
+        Ok(NetworkGame::new(peer))
+    }
+
+    /// Starts connecting to the host at `address`, given as "host" or
+    /// "host:port". The host says which side the guest plays.
+    pub fn join(address: &str) -> NetworkGame {
+        NetworkGame::new(Peer::join(address, &local_name()))
+    }
+
+    /// Starts connecting to the host at `address` to watch its game.
+    pub fn watch(address: &str) -> NetworkGame {
+        NetworkGame::new(Peer::watch(address, &local_name()))
+    }
+
+    fn new(peer: Peer) -> NetworkGame {
+        NetworkGame {
+            peer,
+            clock_times: None,
+            position: None,
+        }
+    }
+
+    fn players(&self) -> Players {
+        let mut players = Players::default();
+        if self.is_watching() || self.local_side() == Team::Black {
+            players.white = PlayerKind::Remote;
+        }
+        if self.is_watching() || self.local_side() == Team::White {
+            players.black = PlayerKind::Remote;
+        }
+        players
+    }
+}
+
+/// What to show the other player as this one's name.
+fn local_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Player".to_string())
+}
+
+#[derive(Component)]
+struct NetworkStatusText;
+
+pub fn network_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        network_lobby_system.run_if(in_state(GameStatus::Menu).and(resource_exists::<NetworkGame>)),
+    )
+    .add_systems(
+        OnEnter(GameStatus::Game),
+        network_panel_setup.run_if(resource_exists::<NetworkGame>),
+    )
+    .add_systems(
+        Update,
+        (
+            network_receive_system.before(ClockSystems),
+            network_send_system.after(ClockSystems),
+            send_offers_system.after(OfferSystems),
+            network_panel_system,
+        )
+            .run_if(in_state(GameStatus::Game).and(resource_exists::<NetworkGame>)),
+    )
+    .add_systems(
+        OnEnter(GameStatus::GameOver),
+        // the last move may not have gone out yet
+        (network_send_system, announce_result_system)
+            .chain()
+            .run_if(resource_exists::<NetworkGame>),
+    )
+    .add_systems(
+        Update,
+        network_rematch_system
+            .run_if(in_state(GameStatus::GameOver).and(resource_exists::<NetworkGame>)),
+    );
+}
+
+/// Finds the other player while the host or join screen is up, and sets the
+/// game up when it has.
+fn network_lobby_system(
+    mut commands: Commands,
+    mut network_game: ResMut<NetworkGame>,
+    starting_position: Res<StartingPosition>,
+) {
+    let network_game = &mut *network_game;
+    while !network_game.is_ready() {
+        match network_game.peer.poll(&starting_position.0, None) {
+            Some(PeerEvent::Game { board, clocks }) => {
+                network_game.position = Some(board);
+                network_game.clock_times = clocks;
+            }
+            Some(event) => log_event(&event),
+            None => return,
+        }
+    }
+    if let Some(board) = network_game.position.take() {
+        commands.insert_resource(StartingPosition(board));
+    }
+    commands.insert_resource(network_game.players());
+}
+
+/// Logs what the other player, or anyone else who connected, did that the
+/// game itself has nothing to do with.
+fn log_event(event: &PeerEvent) {
+    match event {
+        PeerEvent::Notice(notice) => info!("{}", notice),
+        PeerEvent::Warning(warning) => warn!("{}", warning),
+        _ => {}
+    }
+}
+
+/// Plays what the other player has sent: their moves, draw offers,
+/// resignations and, for the guest, the whole game again. A spectator plays
+/// both players' moves from the host. Polling also looks after the
+/// connection, taking the guest back in or connecting to the host again when
+/// it has been lost.
+#[allow(clippy::too_many_arguments)]
+fn network_receive_system(
+    mut commands: Commands,
+    mut network_game: ResMut<NetworkGame>,
+    mut game_state: ResMut<GameState>,
+    mut game_clock: Option<ResMut<GameClock>>,
+    mut offer_writer: MessageWriter<Offer>,
+    image_cache: Res<ImageCache>,
+    query_pieces: Query<Entity, With<Piece>>,
+    mut next_status: ResMut<NextState<GameStatus>>,
+) {
+    let network_game = &mut *network_game;
+    let remote = network_game.local_side().opponent();
+    let watching = network_game.is_watching();
+    let mut outcome = None;
+    loop {
+        let clock = game_clock.as_ref().map(|game_clock| &game_clock.clock);
+        let Some(event) = network_game.peer.poll(&game_state.board, clock) else {
+            break;
+        };
+        let message = match event {
+            PeerEvent::Move { mv, clock } => {
+                let mover = game_state.board.side_to_move();
+                info!("{:?} {:?} {}", mover, mv.piece, mv);
+                game_state.play_move(mv);
+                respawn_pieces(&mut commands, &image_cache, &mut game_state, &query_pieces);
+                if let (Some(game_clock), Some(remaining)) = (&mut game_clock, clock) {
+                    game_clock.correct(mover, remaining);
+                }
+                continue;
+            }
+            PeerEvent::Game { board, clocks } => {
+                network_game.clock_times = clocks;
+                if Position::from_board(&board) != Position::from_board(&game_state.board) {
+                    info!("Caught up with the host's game");
+                    game_state.board = board;
+                    game_state.undone_moves.clear();
+                    game_state.pending_promotion = None;
+                    respawn_pieces(&mut commands, &image_cache, &mut game_state, &query_pieces);
+                }
+                continue;
+            }
+            PeerEvent::Message(message) => message,
+            event => {
+                log_event(&event);
+                continue;
+            }
+        };
+        match message {
+            NetMessage::OfferDraw if !watching => {
+                offer_writer.write(Offer::Draw(remote));
+            }
+            NetMessage::AcceptDraw if !watching => {
+                offer_writer.write(Offer::AcceptDraw(remote));
+            }
+            NetMessage::DeclineDraw if !watching => {
+                offer_writer.write(Offer::DeclineDraw(remote));
+            }
+            NetMessage::ClaimDraw if !watching => {
+                offer_writer.write(Offer::ClaimDraw(remote));
+            }
+            NetMessage::Resign if !watching => {
+                offer_writer.write(Offer::Resign(remote));
+            }
+            // spectators are told how the game ended, not what led to it
+            NetMessage::AcceptDraw => {
+                outcome = Some(GameOutcome::Draw(DrawReason::Agreement));
+            }
+            NetMessage::ClaimDraw => {
+                outcome = game_state.board.claimable_draw().map(GameOutcome::Draw);
+            }
+            NetMessage::Resigned(side) if watching => {
+                outcome = Some(GameOutcome::Decisive {
+                    winner: side.opponent(),
+                    reason: WinReason::Resignation,
+                });
+            }
+            NetMessage::Error(error) => {
+                warn!("{} reports: {}", network_game.opponent_name(), error)
+            }
+            _ => {}
+        }
+    }
+
+    if let (Some((white, black)), Some(game_clock)) = (network_game.clock_times, &mut game_clock) {
+        network_game.clock_times = None;
+        game_clock.correct(Team::White, white);
+        game_clock.correct(Team::Black, black);
+    }
+    if let Some(outcome) = outcome {
+        info!("{}", outcome);
+        commands.insert_resource(GameResult(outcome));
+        next_status.set(GameStatus::GameOver);
+    }
+}
+
+/// Sends the moves made here to the other player, with the time the clock
+/// shows once it has been pressed for them. The host also keeps its
+/// spectators up to date.
+fn network_send_system(
+    mut network_game: ResMut<NetworkGame>,
+    game_state: Res<GameState>,
+    game_clock: Option<Res<GameClock>>,
+) {
+    let clock = game_clock.as_ref().map(|game_clock| &game_clock.clock);
+    network_game.send_moves(&game_state.board, clock);
+}
+
+/// Sends the other player the draw offers, answers, claims and resignations
+/// made here.
+fn send_offers_system(
+    mut network_game: ResMut<NetworkGame>,
+    mut offer_reader: MessageReader<Offer>,
+) {
+    let local = network_game.local_side();
+    for offer in offer_reader.read() {
+        let message = match *offer {
+            Offer::Resign(side) if side == local => NetMessage::Resign,
+            Offer::Draw(side) if side == local => NetMessage::OfferDraw,
+            Offer::AcceptDraw(side) if side == local => NetMessage::AcceptDraw,
+            Offer::DeclineDraw(side) if side == local => NetMessage::DeclineDraw,
+            Offer::ClaimDraw(side) if side == local => NetMessage::ClaimDraw,
+            _ => continue,
+        };
+        network_game.send(message);
+    }
+}
+
+/// Tells spectators how the game ended when the moves and clocks they have
+/// been sent don't show it.
+fn announce_result_system(mut network_game: ResMut<NetworkGame>, result: Res<GameResult>) {
+    match result.0 {
+        GameOutcome::Decisive {
+            winner,
+            reason: WinReason::Resignation,
+        } => network_game.tell_spectators(NetMessage::Resigned(winner.opponent())),
+        GameOutcome::Draw(DrawReason::Agreement) => {
+            network_game.tell_spectators(NetMessage::AcceptDraw)
+        }
+        GameOutcome::Draw(DrawReason::FiftyMoveRule | DrawReason::ThreefoldRepetition)
+            if !network_game.automatic_draw_claims() =>
+        {
+            network_game.tell_spectators(NetMessage::ClaimDraw)
+        }
+        _ => {}
+    }
+}
+
+fn network_panel_setup(mut commands: Commands) {
+    // where Undo and Redo are in other games
+    commands.spawn((
+        DespawnOnExit(GameStatus::Game),
+        Node {
+            position_type: PositionType::Absolute,
+            right: px(10),
+            width: px(200),
+            height: percent(100),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            NetworkStatusText,
+            Text::new(""),
+            TextFont {
+                font_size: 18.0,
+                ..default()
+            },
+            TextColor(TEXT_COLOR),
+            TextLayout::new_with_justify(Justify::Center),
+            Node {
+                margin: UiRect::all(px(8)),
+                ..default()
+            },
+        )],
+    ));
+}
+
+fn network_panel_system(
+    network_game: Res<NetworkGame>,
+    mut status_text: Single<&mut Text, With<NetworkStatusText>>,
+) {
+    if !network_game.is_changed() {
+        return;
+    }
+    if network_game.is_watching() {
+        status_text.0 = network_game.status().to_string();
+        return;
+    }
+    let spectators = match network_game.spectators() {
+        0 => String::new(),
+        1 => "\n1 spectator".to_string(),
+        count => format!("\n{} spectators", count),
+    };
+    status_text.0 = format!(
+        "{}\nYou play {:?}{}",
+        network_game.status(),
+        network_game.local_side(),
+        spectators
+    );
+}
+
+/// Keeps the connection up once the game is over, and starts the rematch
+/// when both players have agreed to one, with the sides swapped.
+fn network_rematch_system(
+    mut commands: Commands,
+    mut network_game: ResMut<NetworkGame>,
+    game_state: Res<GameState>,
+    game_clock: Option<Res<GameClock>>,
+    mut next_status: ResMut<NextState<GameStatus>>,
+) {
+    let network_game = &mut *network_game;
+    let clock = game_clock.as_ref().map(|game_clock| &game_clock.clock);
+    while let Some(event) = network_game.peer.poll(&game_state.board, clock) {
+        match event {
+            PeerEvent::Rematch { board, clocks } => {
+                info!(
+                    "Starting a rematch against {}",
+                    network_game.opponent_name()
+                );
+                network_game.clock_times = clocks;
+                commands.insert_resource(StartingPosition(board));
+                commands.insert_resource(network_game.players());
+                next_status.set(GameStatus::Game);
+                return;
+            }
+            PeerEvent::Message(NetMessage::OfferRematch) => {
+                info!("{} offers a rematch", network_game.opponent_name())
+            }
+            PeerEvent::Message(NetMessage::Error(error)) => {
+                warn!("{} reports: {}", network_game.opponent_name(), error)
+            }
+            event => log_event(&event),
+        }
+    }
+}
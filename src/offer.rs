@@ -0,0 +1,312 @@
+use crate::game::{GamePhase, GameResult, GameStatus, Players};
+use crate::menu::{HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON};
+use crate::{GameState, TEXT_COLOR};
+use bevy::prelude::*;
+use chess_core::{Board, DrawReason, GameOutcome, Team, WinReason};
+
+/// A side resigning, offering, accepting or declining a draw, or claiming one
+/// by the fifty-move rule or threefold repetition. The buttons write these for
+/// human players, the engines for the computer and the network game for the
+/// player on the other end.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Offer {
+    Resign(Team),
+    Draw(Team),
+    AcceptDraw(Team),
+    DeclineDraw(Team),
+    ClaimDraw(Team),
+}
+
+/// A draw offered by `by` that the other side hasn't answered. Moving instead
+/// of answering turns it down.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawOffer {
+    pub by: Team,
+    /// How many moves had been played when it was made.
+    made_at: usize,
+    /// It lapses once more moves than this have been played.
+    lapses_after: usize,
+}
+
+impl DrawOffer {
+    fn new(by: Team, board: &Board) -> DrawOffer {
+        let made_at = board.moves().count();
+        // an offer made on your own turn stands through your move
+        let lapses_after = if board.side_to_move() == by {
+            made_at + 1
+        } else {
+            made_at
+        };
+        DrawOffer {
+            by,
+            made_at,
+            lapses_after,
+        }
+    }
+
+    fn has_lapsed(&self, board: &Board) -> bool {
+        let plies = board.moves().count();
+        plies > self.lapses_after || plies < self.made_at
+    }
+}
+
+/// The systems that act on offers, for others that pass them on once they
+/// have been made.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OfferSystems;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum OfferButton {
+    Draw,
+    Resign,
+    Accept,
+    Decline,
+    Claim,
+}
+
+#[derive(Component)]
+struct OfferText;
+
+pub fn offer_plugin(app: &mut App) {
+    app.add_message::<Offer>()
+        .add_systems(OnEnter(GameStatus::Game), offer_buttons_setup)
+        .add_systems(
+            Update,
+            (
+                (offer_button_system, claim_draw_keyboard_system)
+                    .run_if(in_state(GamePhase::Playing)),
+                apply_offer_system,
+                lapse_draw_offer_system,
+                offer_panel_system,
+            )
+                .chain()
+                .in_set(OfferSystems)
+                .run_if(in_state(GameStatus::Game)),
+        )
+        .add_systems(OnExit(GameStatus::Game), withdraw_draw_offer);
+}
+
+/// The side the buttons resign or offer a draw for: the human's, or the side
+/// to move when both are human. There is none when neither is.
+fn acting_side(players: &Players, board: &Board) -> Option<Team> {
+    let side_to_move = board.side_to_move();
+    [side_to_move, side_to_move.opponent()]
+        .into_iter()
+        .find(|side| players.is_human(*side))
+}
+
+fn offer_buttons_setup(mut commands: Commands) {
+    let button_node = Node {
+        width: px(180),
+        height: px(50),
+        margin: UiRect::all(px(6)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+
+    // between Black's clock and Undo
+    commands
+        .spawn((
+            DespawnOnExit(GameStatus::Game),
+            Node {
+                position_type: PositionType::Absolute,
+                right: px(10),
+                top: px(125),
+                width: px(200),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                OfferText,
+                Text::new(""),
+                TextFont {
+                    font_size: 18.0,
+                    ..default()
+                },
+                TextColor(TEXT_COLOR),
+            ));
+            for (action, text) in [
+                (OfferButton::Draw, "Offer draw"),
+                (OfferButton::Claim, "Claim draw"),
+                (OfferButton::Resign, "Resign"),
+                (OfferButton::Accept, "Accept draw"),
+                (OfferButton::Decline, "Decline draw"),
+            ] {
+                parent.spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    action,
+                    children![(Text::new(text), button_text_style.clone())],
+                ));
+            }
+        });
+}
+
+fn offer_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &OfferButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    draw_offer: Option<Res<DrawOffer>>,
+    mut offer_writer: MessageWriter<Offer>,
+) {
+    for (interaction, action, mut background_color) in &mut interaction_query {
+        *background_color = match *interaction {
+            Interaction::Pressed => PRESSED_BUTTON.into(),
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        // an offer is answered by the side it was made to
+        let answering = draw_offer.as_ref().map(|offer| offer.by.opponent());
+        let offer = match (action, acting_side(&players, &game_state.board), answering) {
+            (OfferButton::Draw, Some(side), _) => Offer::Draw(side),
+            (OfferButton::Resign, Some(side), _) => Offer::Resign(side),
+            (OfferButton::Accept, _, Some(side)) => Offer::AcceptDraw(side),
+            (OfferButton::Decline, _, Some(side)) => Offer::DeclineDraw(side),
+            (OfferButton::Claim, Some(side), _) => Offer::ClaimDraw(side),
+            _ => continue,
+        };
+        offer_writer.write(offer);
+    }
+}
+
+/// C claims a draw for the same side as the buttons.
+fn claim_draw_keyboard_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    mut offer_writer: MessageWriter<Offer>,
+) {
+    if !keys.just_pressed(KeyCode::KeyC) || game_state.pending_promotion.is_some() {
+        return;
+    }
+    if let Some(side) = acting_side(&players, &game_state.board) {
+        offer_writer.write(Offer::ClaimDraw(side));
+    }
+}
+
+/// Ends the game on a resignation, an accepted draw or a draw claimed when
+/// there is one to claim, and keeps track of the draw offer standing. Two
+/// sides offering a draw agree to one.
+fn apply_offer_system(
+    mut commands: Commands,
+    mut offer_reader: MessageReader<Offer>,
+    game_state: Res<GameState>,
+    draw_offer: Option<Res<DrawOffer>>,
+    mut next_status: ResMut<NextState<GameStatus>>,
+) {
+    let mut standing = draw_offer.as_deref().copied();
+    let mut outcome = None;
+    for offer in offer_reader.read() {
+        let offered_by = standing.map(|standing| standing.by);
+        match *offer {
+            Offer::Resign(side) => {
+                outcome = Some(GameOutcome::Decisive {
+                    winner: side.opponent(),
+                    reason: WinReason::Resignation,
+                });
+            }
+            Offer::Draw(side) | Offer::AcceptDraw(side) if offered_by == Some(side.opponent()) => {
+                outcome = Some(GameOutcome::Draw(DrawReason::Agreement));
+            }
+            Offer::Draw(side) if offered_by.is_none() => {
+                info!("{:?} offers a draw", side);
+                standing = Some(DrawOffer::new(side, &game_state.board));
+            }
+            Offer::DeclineDraw(side) if offered_by == Some(side.opponent()) => {
+                info!("{:?} declines the draw", side);
+                standing = None;
+            }
+            Offer::ClaimDraw(side) => match game_state.board.claimable_draw() {
+                Some(reason) => outcome = Some(GameOutcome::Draw(reason)),
+                None => info!("{:?} claims a draw, but there is none to claim", side),
+            },
+            _ => {}
+        }
+        if outcome.is_some() {
+            break;
+        }
+    }
+
+    if let Some(outcome) = outcome {
+        info!("{}", outcome);
+        commands.insert_resource(GameResult(outcome));
+        next_status.set(GameStatus::GameOver);
+    } else if standing != draw_offer.as_deref().copied() {
+        match standing {
+            Some(standing) => commands.insert_resource(standing),
+            None => commands.remove_resource::<DrawOffer>(),
+        }
+    }
+}
+
+fn lapse_draw_offer_system(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    draw_offer: Option<Res<DrawOffer>>,
+) {
+    if draw_offer.is_some_and(|offer| offer.has_lapsed(&game_state.board)) {
+        commands.remove_resource::<DrawOffer>();
+    }
+}
+
+/// Shows Offer draw and Resign to the side that can use them, and Accept and
+/// Decline in their place while a human has a draw offer to answer. Claim
+/// draw takes the place of Offer draw while there is a draw to claim.
+fn offer_panel_system(
+    game_state: Res<GameState>,
+    players: Res<Players>,
+    draw_offer: Option<Res<DrawOffer>>,
+    mut buttons: Query<(&OfferButton, &mut Node)>,
+    mut offer_text: Single<&mut Text, With<OfferText>>,
+) {
+    let acting = acting_side(&players, &game_state.board);
+    let offer = draw_offer.as_deref();
+    let answering = offer
+        .map(|offer| offer.by.opponent())
+        .filter(|side| players.is_human(*side));
+    let claimable = game_state.board.claimable_draw().is_some();
+    for (button, mut node) in &mut buttons {
+        let shown = match button {
+            OfferButton::Draw => {
+                acting.is_some() && answering.is_none() && offer.is_none() && !claimable
+            }
+            OfferButton::Resign => acting.is_some() && answering.is_none(),
+            OfferButton::Accept | OfferButton::Decline => answering.is_some(),
+            OfferButton::Claim => acting.is_some() && answering.is_none() && claimable,
+        };
+        let display = if shown { Display::Flex } else { Display::None };
+        if node.display != display {
+            node.display = display;
+        }
+    }
+    let text = match offer {
+        Some(offer) if answering.is_some() => format!("{:?} offers a draw", offer.by),
+        Some(_) => "Draw offered".to_string(),
+        None => String::new(),
+    };
+    if offer_text.0 != text {
+        offer_text.0 = text;
+    }
+}
+
+fn withdraw_draw_offer(mut commands: Commands) {
+    commands.remove_resource::<DrawOffer>();
+}
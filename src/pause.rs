@@ -0,0 +1,174 @@
+use crate::game::{GamePhase, GameStatus};
+use crate::menu::{MenuState, NORMAL_BUTTON};
+use crate::network::NetworkGame;
+use crate::pgn::SaveGame;
+use crate::TEXT_COLOR;
+use bevy::color::palettes::css::CRIMSON;
+use bevy::ecs::spawn::SpawnIter;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+
+#[derive(Component, Clone, Copy)]
+enum PauseButtonAction {
+    Resume,
+    Settings,
+    SaveGame,
+    QuitToMenu,
+}
+
+pub fn pause_plugin(app: &mut App) {
+    app.add_systems(
+        Update,
+        pause_keyboard_system.run_if(in_state(GameStatus::Game)),
+    )
+    .add_systems(OnEnter(GamePhase::Paused), open_pause_menu)
+    .add_systems(OnExit(GamePhase::Paused), close_pause_menu)
+    .add_systems(OnEnter(MenuState::Pause), pause_menu_setup)
+    .add_systems(Update, pause_action.run_if(in_state(MenuState::Pause)));
+}
+
+/// Escape pauses the game, and resumes it from the pause screen or any of
+/// the settings screens opened from there.
+fn pause_keyboard_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    game_phase: Res<State<GamePhase>>,
+    mut next_game_phase: ResMut<NextState<GamePhase>>,
+) {
+    if keys.just_pressed(KeyCode::Escape) {
+        next_game_phase.set(match game_phase.get() {
+            GamePhase::Playing => GamePhase::Paused,
+            GamePhase::Paused => GamePhase::Playing,
+        });
+    }
+}
+
+fn open_pause_menu(mut menu_state: ResMut<NextState<MenuState>>) {
+    menu_state.set(MenuState::Pause);
+}
+
+/// Closes whichever screen is up on resuming, and when the game ends or is
+/// left while paused.
+fn close_pause_menu(mut menu_state: ResMut<NextState<MenuState>>) {
+    menu_state.set(MenuState::Disabled);
+}
+
+/// Resume, Settings, Save Game and Quit to Menu over the board, which is
+/// dimmed and can't be clicked through.
+fn pause_menu_setup(mut commands: Commands, network_game: Option<Res<NetworkGame>>) {
+    let button_node = Node {
+        width: px(300),
+        height: px(65),
+        margin: UiRect::all(px(15)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 33.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+
+    commands.spawn((
+        DespawnOnExit(MenuState::Pause),
+        Node {
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+        FocusPolicy::Block,
+        GlobalZIndex(1),
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            children![
+                (
+                    Text::new("Paused"),
+                    TextFont {
+                        font_size: 50.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        margin: UiRect::all(px(40)),
+                        ..default()
+                    },
+                ),
+                (
+                    Text::new("The clocks keep running\nin a game over the network"),
+                    TextFont {
+                        font_size: 24.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                    TextLayout::new_with_justify(Justify::Center),
+                    Node {
+                        display: if network_game.is_some() {
+                            Display::Flex
+                        } else {
+                            Display::None
+                        },
+                        margin: UiRect::bottom(px(20)),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    Children::spawn(SpawnIter(
+                        [
+                            (PauseButtonAction::Resume, "Resume"),
+                            (PauseButtonAction::Settings, "Settings"),
+                            (PauseButtonAction::SaveGame, "Save Game"),
+                            (PauseButtonAction::QuitToMenu, "Quit to Menu"),
+                        ]
+                        .into_iter()
+                        .map(move |(action, text)| {
+                            (
+                                Button,
+                                button_node.clone(),
+                                BackgroundColor(NORMAL_BUTTON),
+                                action,
+                                children![(Text::new(text), button_text_style.clone())],
+                            )
+                        })
+                    )),
+                ),
+            ]
+        )],
+    ));
+}
+
+fn pause_action(
+    interaction_query: Query<(&Interaction, &PauseButtonAction), Changed<Interaction>>,
+    mut save_writer: MessageWriter<SaveGame>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut next_status: ResMut<NextState<GameStatus>>,
+    mut next_game_phase: ResMut<NextState<GamePhase>>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match action {
+            PauseButtonAction::Resume => next_game_phase.set(GamePhase::Playing),
+            PauseButtonAction::Settings => menu_state.set(MenuState::Settings),
+            PauseButtonAction::SaveGame => {
+                save_writer.write(SaveGame);
+            }
+            PauseButtonAction::QuitToMenu => next_status.set(GameStatus::Menu),
+        }
+    }
+}
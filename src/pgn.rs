@@ -0,0 +1,159 @@
+use crate::engine::Difficulty;
+use crate::external::ExternalEngine;
+use crate::game::{GamePhase, GameResult, GameStatus, PlayerKind, Players};
+use crate::network::NetworkGame;
+use crate::GameState;
+use bevy::prelude::*;
+use chess_core::{write_pgn, Board, GameOutcome, PgnTags};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+/// Where games are saved: `CHESS_PGN_DIR` when it is set, otherwise a `games`
+/// directory under the working directory.
+#[derive(Resource, Debug)]
+pub struct PgnDirectory(pub PathBuf);
+
+impl Default for PgnDirectory {
+    fn default() -> Self {
+        PgnDirectory(
+            env::var_os("CHESS_PGN_DIR")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from("games")),
+        )
+    }
+}
+
+/// The tags of the game being played and the file it is saved to. Saving the
+/// same game again overwrites that file.
+#[derive(Resource, Debug)]
+pub struct GameRecord {
+    pub(crate) tags: PgnTags,
+    file_name: String,
+}
+
+/// Asks for the game being played to be saved as it stands. S writes this, as
+/// does Save Game on the pause screen.
+#[derive(Message)]
+pub struct SaveGame;
+
+pub fn pgn_plugin(app: &mut App) {
+    app.init_resource::<PgnDirectory>()
+        .add_message::<SaveGame>()
+        .add_systems(OnEnter(GameStatus::Game), start_game_record)
+        .add_systems(OnEnter(GameStatus::GameOver), save_finished_game)
+        .add_systems(
+            Update,
+            (
+                save_game_keyboard_system.run_if(in_state(GamePhase::Playing)),
+                save_game_system,
+            )
+                .chain()
+                .run_if(in_state(GameStatus::Game)),
+        );
+}
+
+fn start_game_record(
+    mut commands: Commands,
+    players: Res<Players>,
+    difficulty: Res<Difficulty>,
+    external_engine: Option<Res<ExternalEngine>>,
+    network_game: Option<Res<NetworkGame>>,
+) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    let time_of_day = seconds % 86_400;
+    let player_name = |kind| match kind {
+        PlayerKind::Human => "?".to_string(),
+        PlayerKind::Computer => match &external_engine {
+            Some(engine) => engine.name.clone(),
+            None => format!("Computer ({})", difficulty.name()),
+        },
+        PlayerKind::Remote => match &network_game {
+            Some(network_game) => network_game.opponent_name().to_string(),
+            None => "?".to_string(),
+        },
+    };
+    commands.insert_resource(GameRecord {
+        tags: PgnTags {
+            date: format!("{:04}.{:02}.{:02}", year, month, day),
+            white: player_name(players.white),
+            black: player_name(players.black),
+            ..default()
+        },
+        file_name: format!(
+            "game-{:04}{:02}{:02}-{:02}{:02}{:02}.pgn",
+            year,
+            month,
+            day,
+            time_of_day / 3600,
+            time_of_day / 60 % 60,
+            time_of_day % 60
+        ),
+    });
+}
+
+fn save_game_keyboard_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut save_writer: MessageWriter<SaveGame>,
+) {
+    if keys.just_pressed(KeyCode::KeyS) {
+        save_writer.write(SaveGame);
+    }
+}
+
+fn save_game_system(
+    mut save_reader: MessageReader<SaveGame>,
+    directory: Res<PgnDirectory>,
+    record: Res<GameRecord>,
+    game_state: Res<GameState>,
+) {
+    if save_reader.read().count() > 0 {
+        save_game(&directory, &record, &game_state.board, None);
+    }
+}
+
+fn save_finished_game(
+    directory: Res<PgnDirectory>,
+    record: Res<GameRecord>,
+    game_state: Res<GameState>,
+    result: Res<GameResult>,
+) {
+    save_game(&directory, &record, &game_state.board, Some(result.0));
+}
+
+fn save_game(
+    directory: &PgnDirectory,
+    record: &GameRecord,
+    board: &Board,
+    outcome: Option<GameOutcome>,
+) {
+    let path = directory.0.join(&record.file_name);
+    let pgn = write_pgn(&record.tags, board, outcome);
+    match fs::create_dir_all(&directory.0).and_then(|_| fs::write(&path, pgn)) {
+        Ok(()) => info!("Saved game to {}", path.display()),
+        Err(error) => warn!("Could not save game to {}: {}", path.display(), error),
+    }
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) date.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // shift the epoch to 0000-03-01 so leap days fall at the end of each year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
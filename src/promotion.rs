@@ -0,0 +1,191 @@
+use crate::board::index_for_square;
+use crate::game::{GamePhase, GameStatus, ImageCache};
+use crate::menu::{HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON};
+use crate::pieces::{get_piece_image, PieceType};
+use crate::{GameState, Piece, TEXT_COLOR};
+use bevy::color::palettes::css::CRIMSON;
+use bevy::ecs::spawn::SpawnIter;
+use bevy::prelude::*;
+use chess_core::Move;
+
+/// Resolves the pending pawn promotion. The picker overlay writes this when one of
+/// its buttons is pressed; engines and scripted players can write it directly.
+#[derive(Message, Debug, Clone, Copy)]
+pub struct PromotionChoice(pub PieceType);
+
+#[derive(Component)]
+struct PromotionPicker;
+
+#[derive(Component)]
+struct PromotionButton(PieceType);
+
+pub fn promotion_plugin(app: &mut App) {
+    app.add_message::<PromotionChoice>().add_systems(
+        Update,
+        (
+            promotion_picker_setup,
+            promotion_button_system.run_if(in_state(GamePhase::Playing)),
+            apply_promotion_system,
+            promotion_picker_cleanup,
+        )
+            .chain()
+            .run_if(in_state(GameStatus::Game)),
+    );
+}
+
+fn promotion_picker_setup(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    image_cache: Res<ImageCache>,
+    query_picker: Query<(), With<PromotionPicker>>,
+) {
+    if game_state.pending_promotion.is_none() || !query_picker.is_empty() {
+        return;
+    }
+
+    let button_node = Node {
+        width: px(90),
+        height: px(90),
+        margin: UiRect::all(px(10)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    // the move has not been made yet, so the promoting side is still to move
+    let team = game_state.board.side_to_move();
+    let choices: Vec<(PieceType, Handle<Image>)> = PieceType::PROMOTIONS
+        .into_iter()
+        .map(|piece_type| (piece_type, get_piece_image(&image_cache, team, piece_type)))
+        .collect();
+
+    commands.spawn((
+        DespawnOnExit(GameStatus::Game),
+        PromotionPicker,
+        Node {
+            position_type: PositionType::Absolute,
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(px(20)),
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            children![
+                (
+                    Text::new("Promote to"),
+                    TextFont {
+                        font_size: 33.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    Children::spawn(SpawnIter(choices.into_iter().map(
+                        move |(piece_type, handle)| {
+                            (
+                                Button,
+                                button_node.clone(),
+                                BackgroundColor(NORMAL_BUTTON),
+                                PromotionButton(piece_type),
+                                children![(
+                                    ImageNode::new(handle),
+                                    Node {
+                                        width: px(70),
+                                        ..default()
+                                    },
+                                )],
+                            )
+                        }
+                    ))),
+                ),
+            ]
+        )],
+    ));
+}
+
+fn promotion_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &PromotionButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut promotion_writer: MessageWriter<PromotionChoice>,
+) {
+    for (interaction, button, mut background_color) in &mut interaction_query {
+        *background_color = match *interaction {
+            Interaction::Pressed => {
+                promotion_writer.write(PromotionChoice(button.0));
+                PRESSED_BUTTON.into()
+            }
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+fn apply_promotion_system(
+    mut promotion_reader: MessageReader<PromotionChoice>,
+    mut game_state: ResMut<GameState>,
+    image_cache: Res<ImageCache>,
+    mut query_pieces: Query<(&mut Piece, &mut Sprite)>,
+) {
+    let Some(choice) = promotion_reader
+        .read()
+        .filter(|choice| PieceType::PROMOTIONS.contains(&choice.0))
+        .last()
+        .copied()
+    else {
+        return;
+    };
+    let Some(mv) = game_state.pending_promotion else {
+        return;
+    };
+    let mv = Move {
+        promotion: Some(choice.0),
+        ..mv
+    };
+
+    // the pawn's sprite already stands on the promotion square
+    let (row, col) = index_for_square(mv.to);
+    if let Some(pawn) = game_state.tiles[row][col].piece {
+        if let Ok((mut piece, mut sprite)) = query_pieces.get_mut(pawn) {
+            piece.piece_type = choice.0;
+            sprite.image = get_piece_image(&image_cache, piece.team, choice.0);
+        }
+    }
+
+    info!(
+        "{:?} {:?} {}",
+        game_state.board.side_to_move(),
+        mv.piece,
+        mv
+    );
+    game_state.play_move(mv);
+    game_state.pending_promotion = None;
+}
+
+/// Removes the picker once nothing is waiting on it, whether the promotion was
+/// chosen or the move was taken back.
+fn promotion_picker_cleanup(
+    mut commands: Commands,
+    game_state: Res<GameState>,
+    query_picker: Query<Entity, With<PromotionPicker>>,
+) {
+    if game_state.pending_promotion.is_some() {
+        return;
+    }
+    for entity in query_picker.iter() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,298 @@
+use crate::board::{init_board, Tile};
+use crate::game::{spawn_board, spawn_pieces, BoardOrientation, GameStatus, ImageCache};
+use crate::menu::{button_system, NORMAL_BUTTON};
+use crate::{Piece, TEXT_COLOR};
+use bevy::prelude::*;
+use chess_core::{Board, PgnGame, Team};
+
+/// Games loaded from a PGN file, with the game on the board and how many of
+/// its moves have been played.
+#[derive(Resource)]
+pub struct Replay {
+    games: Vec<PgnGame>,
+    game: usize,
+    ply: usize,
+    tiles: [[Tile; 8]; 8],
+}
+
+impl Replay {
+    /// `games` must not be empty.
+    pub fn new(games: Vec<PgnGame>) -> Replay {
+        Replay {
+            games,
+            game: 0,
+            ply: 0,
+            tiles: init_board(),
+        }
+    }
+
+    fn current(&self) -> &PgnGame {
+        &self.games[self.game]
+    }
+
+    /// The position after the first `ply` moves of the current game.
+    fn position_at(&self, ply: usize) -> Board {
+        let game = self.current();
+        let mut board = game.starting_position.clone();
+        for mv in &game.moves[..ply] {
+            board.make_move(*mv);
+        }
+        board
+    }
+}
+
+/// Moves the replay through the loaded games. The buttons and arrow keys
+/// write these.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayStep {
+    Start,
+    Back,
+    Forward,
+    End,
+    PreviousGame,
+    NextGame,
+}
+
+#[derive(Component)]
+struct ReplayButton(ReplayStep);
+
+#[derive(Component)]
+struct ReplayMenuButton;
+
+#[derive(Component)]
+struct ReplayInfoText;
+
+pub fn replay_plugin(app: &mut App) {
+    app.add_message::<ReplayStep>()
+        .add_systems(OnEnter(GameStatus::Replay), replay_setup)
+        .add_systems(
+            Update,
+            (
+                replay_keyboard_system,
+                replay_button_system,
+                apply_replay_step_system,
+                replay_info_system,
+                button_system,
+            )
+                .chain()
+                .run_if(in_state(GameStatus::Replay)),
+        )
+        .add_systems(OnExit(GameStatus::Replay), teardown_replay);
+}
+
+fn replay_setup(
+    mut commands: Commands,
+    image_cache: Res<ImageCache>,
+    orientation: Res<BoardOrientation>,
+    mut replay: ResMut<Replay>,
+) {
+    replay.tiles = spawn_board(
+        &mut commands,
+        &image_cache,
+        &replay.position_at(replay.ply),
+        orientation.0,
+        GameStatus::Replay,
+    );
+
+    let button_node = Node {
+        width: px(180),
+        height: px(50),
+        margin: UiRect::all(px(8)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+
+    commands.spawn((
+        DespawnOnExit(GameStatus::Replay),
+        ReplayInfoText,
+        Text::new(""),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+        Node {
+            position_type: PositionType::Absolute,
+            left: px(10),
+            top: px(20),
+            width: px(200),
+            ..default()
+        },
+    ));
+
+    commands
+        .spawn((
+            DespawnOnExit(GameStatus::Replay),
+            Node {
+                position_type: PositionType::Absolute,
+                right: px(10),
+                width: px(200),
+                height: percent(100),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            for (step, text) in [
+                (ReplayStep::Start, "Start"),
+                (ReplayStep::Back, "Back"),
+                (ReplayStep::Forward, "Forward"),
+                (ReplayStep::End, "End"),
+                (ReplayStep::PreviousGame, "Previous Game"),
+                (ReplayStep::NextGame, "Next Game"),
+            ] {
+                parent.spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    ReplayButton(step),
+                    children![(Text::new(text), button_text_style.clone())],
+                ));
+            }
+            parent.spawn((
+                Button,
+                button_node.clone(),
+                BackgroundColor(NORMAL_BUTTON),
+                ReplayMenuButton,
+                children![(Text::new("Menu"), button_text_style.clone())],
+            ));
+        });
+}
+
+/// Left and Right step through the moves, Home and End jump to either end,
+/// Up and Down switch games and Escape goes back to the menu.
+fn replay_keyboard_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut step_writer: MessageWriter<ReplayStep>,
+    mut game_state: ResMut<NextState<GameStatus>>,
+) {
+    for (key, step) in [
+        (KeyCode::Home, ReplayStep::Start),
+        (KeyCode::ArrowLeft, ReplayStep::Back),
+        (KeyCode::ArrowRight, ReplayStep::Forward),
+        (KeyCode::End, ReplayStep::End),
+        (KeyCode::ArrowUp, ReplayStep::PreviousGame),
+        (KeyCode::ArrowDown, ReplayStep::NextGame),
+    ] {
+        if keys.just_pressed(key) {
+            step_writer.write(step);
+        }
+    }
+    if keys.just_pressed(KeyCode::Escape) {
+        game_state.set(GameStatus::Menu);
+    }
+}
+
+fn replay_button_system(
+    step_query: Query<(&Interaction, &ReplayButton), Changed<Interaction>>,
+    menu_query: Query<&Interaction, (Changed<Interaction>, With<ReplayMenuButton>)>,
+    mut step_writer: MessageWriter<ReplayStep>,
+    mut game_state: ResMut<NextState<GameStatus>>,
+) {
+    for (interaction, button) in &step_query {
+        if *interaction == Interaction::Pressed {
+            step_writer.write(button.0);
+        }
+    }
+    if menu_query
+        .iter()
+        .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        game_state.set(GameStatus::Menu);
+    }
+}
+
+fn apply_replay_step_system(
+    mut commands: Commands,
+    mut step_reader: MessageReader<ReplayStep>,
+    mut replay: ResMut<Replay>,
+    image_cache: Res<ImageCache>,
+    query_pieces: Query<Entity, With<Piece>>,
+) {
+    let (mut game, mut ply) = (replay.game, replay.ply);
+    for step in step_reader.read() {
+        let last_game = replay.games.len() - 1;
+        match step {
+            ReplayStep::Start => ply = 0,
+            ReplayStep::Back => ply = ply.saturating_sub(1),
+            ReplayStep::Forward => ply = (ply + 1).min(replay.games[game].moves.len()),
+            ReplayStep::End => ply = replay.games[game].moves.len(),
+            ReplayStep::PreviousGame => {
+                game = game.saturating_sub(1);
+                ply = 0;
+            }
+            ReplayStep::NextGame => {
+                game = (game + 1).min(last_game);
+                ply = 0;
+            }
+        }
+    }
+    if (game, ply) == (replay.game, replay.ply) {
+        return;
+    }
+
+    replay.game = game;
+    replay.ply = ply;
+    for entity in query_pieces.iter() {
+        commands.entity(entity).despawn();
+    }
+    let board = replay.position_at(ply);
+    spawn_pieces(
+        &mut commands,
+        &image_cache,
+        &board,
+        &mut replay.tiles,
+        GameStatus::Replay,
+    );
+}
+
+fn replay_info_system(replay: Res<Replay>, mut query_text: Query<&mut Text, With<ReplayInfoText>>) {
+    if !replay.is_changed() {
+        return;
+    }
+
+    let game = replay.current();
+    let last_move = match replay.ply.checked_sub(1) {
+        None => "Starting position".to_string(),
+        Some(previous) => {
+            let board = replay.position_at(previous);
+            let dots = if board.side_to_move() == Team::White {
+                "."
+            } else {
+                "..."
+            };
+            let mv = game.moves[previous];
+            format!("{}{} {}", board.fullmove_number(), dots, board.san(mv))
+        }
+    };
+    let info = format!(
+        "{} vs {}\n{}\n{}\n\nGame {} of {}\nResult: {}\n\nMove {} of {}\n{}",
+        game.tag("White").unwrap_or("?"),
+        game.tag("Black").unwrap_or("?"),
+        game.tag("Event").unwrap_or("?"),
+        game.tag("Date").unwrap_or("?"),
+        replay.game + 1,
+        replay.games.len(),
+        game.result,
+        replay.ply,
+        game.moves.len(),
+        last_move
+    );
+    for mut text in &mut query_text {
+        text.0 = info.clone();
+    }
+}
+
+fn teardown_replay(mut commands: Commands) {
+    commands.remove_resource::<Replay>();
+}
@@ -0,0 +1,160 @@
+use crate::game::{respawn_pieces, GamePhase, GameStatus, ImageCache, Players};
+use crate::menu::{HOVERED_BUTTON, NORMAL_BUTTON, PRESSED_BUTTON};
+use crate::network::NetworkGame;
+use crate::{GameState, Piece, TEXT_COLOR};
+use bevy::prelude::*;
+
+/// Takes back the last move or replays the last move taken back. The Undo and
+/// Redo buttons, Ctrl+Z and Ctrl+Y write these.
+#[derive(Message, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Takeback {
+    Undo,
+    Redo,
+}
+
+#[derive(Component)]
+struct TakebackButton(Takeback);
+
+pub fn takeback_plugin(app: &mut App) {
+    app.add_message::<Takeback>()
+        // moves can't be taken back in a game over the network
+        .add_systems(
+            OnEnter(GameStatus::Game),
+            takeback_buttons_setup.run_if(not(resource_exists::<NetworkGame>)),
+        )
+        .add_systems(
+            Update,
+            (
+                takeback_keyboard_system,
+                takeback_button_system,
+                apply_takeback_system,
+            )
+                .chain()
+                .run_if(in_state(GamePhase::Playing).and(not(resource_exists::<NetworkGame>))),
+        );
+}
+
+fn takeback_buttons_setup(mut commands: Commands) {
+    let button_node = Node {
+        width: px(180),
+        height: px(50),
+        margin: UiRect::all(px(8)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+
+    commands
+        .spawn((
+            DespawnOnExit(GameStatus::Game),
+            Node {
+                position_type: PositionType::Absolute,
+                right: px(10),
+                width: px(200),
+                height: percent(100),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            for (takeback, text) in [(Takeback::Undo, "Undo"), (Takeback::Redo, "Redo")] {
+                parent.spawn((
+                    Button,
+                    button_node.clone(),
+                    BackgroundColor(NORMAL_BUTTON),
+                    TakebackButton(takeback),
+                    children![(Text::new(text), button_text_style.clone())],
+                ));
+            }
+        });
+}
+
+fn takeback_keyboard_system(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut takeback_writer: MessageWriter<Takeback>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ)) {
+        takeback_writer.write(Takeback::Redo);
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        takeback_writer.write(Takeback::Undo);
+    }
+}
+
+fn takeback_button_system(
+    mut interaction_query: Query<
+        (&Interaction, &TakebackButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut takeback_writer: MessageWriter<Takeback>,
+) {
+    for (interaction, button, mut background_color) in &mut interaction_query {
+        *background_color = match *interaction {
+            Interaction::Pressed => {
+                takeback_writer.write(button.0);
+                PRESSED_BUTTON.into()
+            }
+            Interaction::Hovered => HOVERED_BUTTON.into(),
+            Interaction::None => NORMAL_BUTTON.into(),
+        }
+    }
+}
+
+/// Steps the board back or forward and rebuilds the piece sprites from it, which
+/// brings back captured pieces and puts castled rooks home. Undoing while a
+/// promotion piece is being chosen cancels that move instead. Against the
+/// computer, its moves are stepped over so it is always the player's turn after.
+fn apply_takeback_system(
+    mut commands: Commands,
+    mut takeback_reader: MessageReader<Takeback>,
+    mut game_state: ResMut<GameState>,
+    players: Res<Players>,
+    image_cache: Res<ImageCache>,
+    query_pieces: Query<Entity, With<Piece>>,
+) {
+    let mut changed = false;
+    for takeback in takeback_reader.read() {
+        match takeback {
+            Takeback::Undo if game_state.pending_promotion.is_some() => {
+                game_state.pending_promotion = None;
+                changed = true;
+            }
+            Takeback::Undo => {
+                while let Some(mv) = game_state.board.unmake_move() {
+                    info!("Undo {}", mv);
+                    game_state.undone_moves.push(mv);
+                    changed = true;
+                    if players.is_human(game_state.board.side_to_move()) {
+                        break;
+                    }
+                }
+            }
+            Takeback::Redo if game_state.pending_promotion.is_some() => {}
+            Takeback::Redo => {
+                while let Some(mv) = game_state.undone_moves.pop() {
+                    info!("Redo {}", mv);
+                    game_state.board.make_move(mv);
+                    changed = true;
+                    if players.is_human(game_state.board.side_to_move()) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    if changed {
+        respawn_pieces(&mut commands, &image_cache, &mut game_state, &query_pieces);
+    }
+}
@@ -0,0 +1,405 @@
+use crate::external::{EngineProtocol, ExternalEngine};
+use crate::game::{GameStatus, Players, StartingPosition};
+use crate::menu::{MenuState, Opponent, NORMAL_BUTTON};
+use crate::network::NetworkGame;
+use crate::pgn::PgnDirectory;
+use crate::replay::Replay;
+use crate::util::paste_from_clipboard;
+use crate::TEXT_COLOR;
+use bevy::color::palettes::css::CRIMSON;
+use bevy::ecs::spawn::SpawnIter;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use chess_core::{read_pgn, Board};
+use std::fs;
+use std::path::PathBuf;
+
+/// The text typed on one of the text input screens and why it was last
+/// rejected, if it was. The host, join and watch screens show how finding the
+/// other player is going in `error` too.
+#[derive(Resource, Default)]
+pub(crate) struct TextInput {
+    text: String,
+    pub(crate) error: Option<String>,
+}
+
+/// Asks for the text on the current screen to be used.
+#[derive(Message)]
+struct SubmitTextInput;
+
+#[derive(Component)]
+struct TextInputField;
+
+/// The line below the text, which says why it was rejected.
+#[derive(Component)]
+pub(crate) struct TextInputError;
+
+#[derive(Component, Clone, Copy)]
+enum TextInputButton {
+    Paste,
+    Submit,
+    Back,
+}
+
+pub fn text_input_plugin(app: &mut App) {
+    app.init_resource::<TextInput>()
+        .add_message::<SubmitTextInput>()
+        .add_systems(OnEnter(MenuState::LoadFen), load_fen_menu_setup)
+        .add_systems(OnEnter(MenuState::LoadPgn), load_pgn_menu_setup)
+        .add_systems(OnEnter(MenuState::LoadEngine), load_engine_menu_setup)
+        .add_systems(OnEnter(MenuState::JoinGame), join_game_menu_setup)
+        .add_systems(OnEnter(MenuState::WatchGame), watch_game_menu_setup)
+        .add_systems(
+            Update,
+            (
+                text_typing_system,
+                text_input_button_system,
+                submit_text_input_system,
+            )
+                .chain()
+                .run_if(
+                    in_state(MenuState::LoadFen)
+                        .or(in_state(MenuState::LoadPgn))
+                        .or(in_state(MenuState::LoadEngine))
+                        .or(in_state(MenuState::JoinGame))
+                        .or(in_state(MenuState::WatchGame)),
+                ),
+        )
+        .add_systems(
+            Update,
+            text_display_system
+                .after(submit_text_input_system)
+                .run_if(in_state(GameStatus::Menu)),
+        );
+}
+
+fn load_fen_menu_setup(commands: Commands, text_input: ResMut<TextInput>) {
+    text_input_screen_setup(
+        commands,
+        text_input,
+        MenuState::LoadFen,
+        "Type or paste a FEN",
+    );
+}
+
+fn load_pgn_menu_setup(commands: Commands, text_input: ResMut<TextInput>) {
+    text_input_screen_setup(
+        commands,
+        text_input,
+        MenuState::LoadPgn,
+        "Path to a PGN file",
+    );
+}
+
+fn load_engine_menu_setup(
+    commands: Commands,
+    text_input: ResMut<TextInput>,
+    opponent: Res<Opponent>,
+) {
+    let title = match opponent.protocol() {
+        Some(EngineProtocol::Xboard) => "Path to a CECP engine",
+        _ => "Path to a UCI engine",
+    };
+    text_input_screen_setup(commands, text_input, MenuState::LoadEngine, title);
+}
+
+fn join_game_menu_setup(commands: Commands, text_input: ResMut<TextInput>) {
+    text_input_screen_setup(
+        commands,
+        text_input,
+        MenuState::JoinGame,
+        "Address of the host",
+    );
+}
+
+fn watch_game_menu_setup(commands: Commands, text_input: ResMut<TextInput>) {
+    text_input_screen_setup(
+        commands,
+        text_input,
+        MenuState::WatchGame,
+        "Address of the game to watch",
+    );
+}
+
+/// A screen with one line of text to type or paste, an error line below it,
+/// and Paste, Start and Back buttons.
+fn text_input_screen_setup(
+    mut commands: Commands,
+    mut text_input: ResMut<TextInput>,
+    screen: MenuState,
+    title: &str,
+) {
+    text_input.text.clear();
+    text_input.error = None;
+
+    let button_node = Node {
+        width: px(200),
+        height: px(65),
+        margin: UiRect::all(px(20)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let button_text_style = (
+        TextFont {
+            font_size: 33.0,
+            ..default()
+        },
+        TextColor(TEXT_COLOR),
+    );
+
+    commands.spawn((
+        DespawnOnExit(screen),
+        Node {
+            width: percent(100),
+            height: percent(100),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        children![(
+            Node {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(CRIMSON.into()),
+            children![
+                (
+                    Text::new(title),
+                    button_text_style.clone(),
+                    Node {
+                        margin: UiRect::all(px(20)),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        width: px(760),
+                        min_height: px(40),
+                        margin: UiRect::horizontal(px(20)),
+                        padding: UiRect::all(px(8)),
+                        ..default()
+                    },
+                    BackgroundColor(NORMAL_BUTTON),
+                    children![(
+                        TextInputField,
+                        Text::new(""),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(TEXT_COLOR),
+                    )],
+                ),
+                (
+                    TextInputError,
+                    Text::new(""),
+                    TextFont {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                    TextColor(TEXT_COLOR),
+                    Node {
+                        margin: UiRect::all(px(10)),
+                        ..default()
+                    },
+                ),
+                (
+                    Node {
+                        flex_direction: FlexDirection::Row,
+                        ..default()
+                    },
+                    Children::spawn(SpawnIter(
+                        [
+                            (TextInputButton::Paste, "Paste"),
+                            (TextInputButton::Submit, "Start"),
+                            (TextInputButton::Back, "Back"),
+                        ]
+                        .into_iter()
+                        .map(move |(action, text)| {
+                            (
+                                Button,
+                                button_node.clone(),
+                                BackgroundColor(NORMAL_BUTTON),
+                                action,
+                                children![(Text::new(text), button_text_style.clone())],
+                            )
+                        })
+                    )),
+                ),
+            ]
+        )],
+    ));
+}
+
+/// Edits the text being entered: typed characters are appended, Backspace
+/// deletes, Ctrl+V pastes and Enter submits it.
+fn text_typing_system(
+    mut keyboard_reader: MessageReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut text_input: ResMut<TextInput>,
+    mut submit_writer: MessageWriter<SubmitTextInput>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    for input in keyboard_reader.read() {
+        if !input.state.is_pressed() {
+            continue;
+        }
+        match &input.logical_key {
+            Key::Enter => {
+                submit_writer.write(SubmitTextInput);
+            }
+            Key::Backspace => {
+                text_input.text.pop();
+            }
+            Key::Character(letter) if ctrl && letter.eq_ignore_ascii_case("v") => {
+                paste_text(&mut text_input);
+            }
+            _ if ctrl => {}
+            _ => {
+                if let Some(text) = &input.text {
+                    text_input
+                        .text
+                        .extend(text.chars().filter(|letter| !letter.is_control()));
+                }
+            }
+        }
+    }
+}
+
+fn text_input_button_system(
+    interaction_query: Query<(&Interaction, &TextInputButton), Changed<Interaction>>,
+    mut text_input: ResMut<TextInput>,
+    mut submit_writer: MessageWriter<SubmitTextInput>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+) {
+    for (interaction, button) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            TextInputButton::Paste => paste_text(&mut text_input),
+            TextInputButton::Submit => {
+                submit_writer.write(SubmitTextInput);
+            }
+            TextInputButton::Back => menu_state.set(MenuState::Main),
+        }
+    }
+}
+
+fn text_display_system(
+    text_input: Res<TextInput>,
+    mut field_text: Query<&mut Text, (With<TextInputField>, Without<TextInputError>)>,
+    mut error_text: Query<&mut Text, (With<TextInputError>, Without<TextInputField>)>,
+) {
+    if !text_input.is_changed() {
+        return;
+    }
+    for mut text in &mut field_text {
+        text.0 = text_input.text.clone();
+    }
+    for mut text in &mut error_text {
+        text.0 = text_input.error.clone().unwrap_or_default();
+    }
+}
+
+fn paste_text(text_input: &mut TextInput) {
+    match paste_from_clipboard() {
+        Ok(text) => {
+            text_input.text = text.trim().to_string();
+            text_input.error = None;
+        }
+        Err(error) => text_input.error = Some(format!("Could not paste: {}", error)),
+    }
+}
+
+/// Acts on the text entered on the current screen and starts the game or
+/// replay if it is accepted. Otherwise the reason is shown below the text.
+#[allow(clippy::too_many_arguments)]
+fn submit_text_input_system(
+    mut submit_reader: MessageReader<SubmitTextInput>,
+    mut commands: Commands,
+    screen: Res<State<MenuState>>,
+    pgn_directory: Res<PgnDirectory>,
+    opponent: Res<Opponent>,
+    mut text_input: ResMut<TextInput>,
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut game_state: ResMut<NextState<GameStatus>>,
+) {
+    if submit_reader.read().count() == 0 {
+        return;
+    }
+
+    let accepted = match **screen {
+        MenuState::LoadFen => {
+            start_from_fen(&mut commands, &text_input.text).map(|_| GameStatus::Game)
+        }
+        MenuState::LoadPgn => {
+            open_pgn(&mut commands, &text_input.text, &pgn_directory).map(|_| GameStatus::Replay)
+        }
+        MenuState::LoadEngine => {
+            let protocol = opponent.protocol().unwrap_or(EngineProtocol::Uci);
+            launch_engine(&mut commands, &text_input.text, protocol).map(|_| GameStatus::Game)
+        }
+        MenuState::JoinGame => {
+            // the game starts once the host answers
+            commands.insert_resource(NetworkGame::join(&text_input.text));
+            return;
+        }
+        MenuState::WatchGame => {
+            commands.insert_resource(NetworkGame::watch(&text_input.text));
+            return;
+        }
+        _ => return,
+    };
+    match accepted {
+        Ok(next) => {
+            game_state.set(next);
+            menu_state.set(MenuState::Disabled);
+        }
+        Err(error) => text_input.error = Some(error),
+    }
+}
+
+fn start_from_fen(commands: &mut Commands, fen: &str) -> Result<(), String> {
+    let board = Board::from_fen(fen).map_err(|error| format!("Invalid FEN: {}", error))?;
+    commands.insert_resource(StartingPosition(board));
+    commands.insert_resource(Players::default());
+    Ok(())
+}
+
+/// Starts the engine at `path` to play the computer's side of the game
+/// chosen on the previous screen.
+fn launch_engine(
+    commands: &mut Commands,
+    path: &str,
+    protocol: EngineProtocol,
+) -> Result<(), String> {
+    let path = PathBuf::from(path.trim());
+    let engine = ExternalEngine::launch(&path, protocol)
+        .map_err(|error| format!("Could not start {}: {}", path.display(), error))?;
+    commands.insert_resource(engine);
+    Ok(())
+}
+
+/// Loads the games in the PGN file at `path`. A path that doesn't exist as
+/// given is looked for in the directory games are saved to.
+fn open_pgn(
+    commands: &mut Commands,
+    path: &str,
+    pgn_directory: &PgnDirectory,
+) -> Result<(), String> {
+    let mut path = PathBuf::from(path.trim());
+    if !path.exists() {
+        path = pgn_directory.0.join(path);
+    }
+    let text = fs::read_to_string(&path)
+        .map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+    let games = read_pgn(&text).map_err(|error| format!("Invalid PGN: {}", error))?;
+    if games.is_empty() {
+        return Err(format!("No games found in {}", path.display()));
+    }
+    commands.insert_resource(Replay::new(games));
+    Ok(())
+}
@@ -1,7 +1,12 @@
 use crate::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use arboard::Clipboard;
 use bevy::asset::{AssetServer, Handle};
 use bevy::math::Vec2;
 use bevy::prelude::{Image, Res};
+use std::sync::Mutex;
+
+// on Linux the copied text only lasts as long as the clipboard that set it
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
 
 pub fn load_image(asset_server: &Res<AssetServer>, piece: &str) -> Handle<Image> {
     asset_server.load(format!("pieces/{}.png", piece))
@@ -12,3 +17,23 @@ pub fn transform_mouse_coords(mouse_coords: Vec2) -> Vec2 {
     let half_height: f32 = WINDOW_HEIGHT as f32 * 0.5;
     Vec2::from((mouse_coords.x - half_width, -(mouse_coords.y - half_height)))
 }
+
+fn with_clipboard<T>(
+    action: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>,
+) -> Result<T, arboard::Error> {
+    let mut clipboard = CLIPBOARD
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if clipboard.is_none() {
+        *clipboard = Some(Clipboard::new()?);
+    }
+    action(clipboard.as_mut().unwrap())
+}
+
+pub fn copy_to_clipboard(text: String) -> Result<(), arboard::Error> {
+    with_clipboard(|clipboard| clipboard.set_text(text))
+}
+
+pub fn paste_from_clipboard() -> Result<String, arboard::Error> {
+    with_clipboard(|clipboard| clipboard.get_text())
+}